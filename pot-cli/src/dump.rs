@@ -0,0 +1,377 @@
+use std::fmt::{Display, Write as _};
+use std::io::{Read, Write};
+use std::str;
+
+use pot::format::{self, Kind, Nucleus};
+use pot::reader::{BufferedBytes, Reader};
+use pot::{Error, Result};
+
+/// The maximum number of encoded bytes shown for each atom.
+const HEX_PREVIEW_LEN: usize = 8;
+
+/// The width of the column containing the encoded bytes of each atom.
+const HEX_COLUMN_WIDTH: usize = HEX_PREVIEW_LEN * 3 + 3;
+
+/// The deepest nesting level that atoms are indented for. Atoms nested more
+/// deeply are indented as if they were at this depth.
+const MAX_INDENT_DEPTH: usize = 64;
+
+/// Writes a line to `output` for every atom in `payload`, indented by the
+/// atom's nesting depth, up to 64 levels.
+///
+/// Atoms are written as they are read. If the payload is invalid, every atom
+/// before the problem is listed, followed by a line describing the error at
+/// the offset of the atom that could not be read.
+///
+/// Payloads serialized with `Compatibility::Full` encode enum variants without
+/// associated data as a named value without a value, which cannot be
+/// distinguished from other named values without knowing the original data
+/// structure. These payloads are listed as if every named value has a value.
+pub fn dump(payload: &[u8], output: &mut impl Write) -> Result<()> {
+    let mut dumper = Dumper {
+        input: Input {
+            data: payload,
+            position: 0,
+        },
+        payload,
+        atom_start: 0,
+        symbols: Vec::new(),
+        output,
+    };
+    let result = dumper.dump();
+    if let Err(err) = &result {
+        writeln!(dumper.output, "{:08x}  error: {err}", dumper.atom_start)?;
+    }
+    result
+}
+
+enum Remaining {
+    Values(u64),
+    UntilEnd,
+}
+
+struct Dumper<'a, W> {
+    input: Input<'a>,
+    payload: &'a [u8],
+    atom_start: usize,
+    symbols: Vec<&'a str>,
+    output: W,
+}
+
+impl<W: Write> Dumper<'_, W> {
+    #[allow(clippy::cast_possible_truncation)]
+    fn dump(&mut self) -> Result<()> {
+        let version = format::read_header(&mut self.input)?;
+        self.write_line(0, format_args!("header (version {version})"))?;
+
+        let mut budget = usize::MAX;
+        let mut containers = vec![Remaining::Values(1)];
+        while let Some(container) = containers.last_mut() {
+            if matches!(container, Remaining::Values(0)) {
+                containers.pop();
+                continue;
+            }
+
+            self.atom_start = self.input.position;
+            let atom = format::read_atom(&mut self.input, &mut budget, &mut Vec::new())?;
+            let is_end = matches!(atom.nucleus, Some(Nucleus::DynamicEnd));
+            let mut depth = containers.len() - 1;
+            match containers.last_mut() {
+                Some(Remaining::Values(remaining)) if !is_end => *remaining -= 1,
+                Some(Remaining::UntilEnd) if is_end => {
+                    containers.pop();
+                    depth -= 1;
+                }
+                Some(Remaining::UntilEnd) | None => {}
+                Some(Remaining::Values(_)) => {
+                    return Err(Error::Message(String::from("unexpected dynamic end")));
+                }
+            }
+
+            let description = match (atom.kind, &atom.nucleus) {
+                (Kind::Sequence, _) => {
+                    containers.push(Remaining::Values(atom.arg));
+                    String::new()
+                }
+                (Kind::Map, _) => {
+                    containers.push(Remaining::Values(
+                        atom.arg.checked_mul(2).ok_or(Error::InvalidAtomHeader)?,
+                    ));
+                    String::new()
+                }
+                (Kind::Symbol, _) => self.read_symbol(atom.arg)?,
+                (_, Some(Nucleus::Named)) => {
+                    // A named value is a symbol followed by a value.
+                    containers.push(Remaining::Values(2));
+                    describe_nucleus(atom.nucleus.as_ref())
+                }
+                (_, Some(Nucleus::InternedBytes | Nucleus::BytesReference)) => {
+                    // Interned bytes are followed by the bytes, and references
+                    // are followed by the id of the bytes.
+                    containers.push(Remaining::Values(1));
+                    describe_nucleus(atom.nucleus.as_ref())
+                }
+                (_, Some(Nucleus::PackedArray)) => {
+                    // Packed arrays are followed by the element type and the
+                    // elements.
+                    containers.push(Remaining::Values(2));
+                    describe_nucleus(atom.nucleus.as_ref())
+                }
+                (_, Some(Nucleus::DynamicMap | Nucleus::DynamicSequence)) => {
+                    containers.push(Remaining::UntilEnd);
+                    describe_nucleus(atom.nucleus.as_ref())
+                }
+                (_, nucleus) => describe_nucleus(nucleus.as_ref()),
+            };
+            let separator = if description.is_empty() { "" } else { " " };
+            self.write_line(
+                depth,
+                format_args!("{:?}({}){separator}{description}", atom.kind, atom.arg),
+            )?;
+        }
+
+        if self.input.data.is_empty() {
+            Ok(())
+        } else {
+            self.atom_start = self.input.position;
+            Err(Error::TrailingBytes)
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn read_symbol(&mut self, arg: u64) -> Result<String> {
+        let id = arg >> 1;
+        if arg & 0b1 == 0 {
+            let BufferedBytes::Data(name) = self
+                .input
+                .buffered_read_bytes(id as usize, &mut Vec::new())?
+            else {
+                unreachable!("input always borrows")
+            };
+            let name = str::from_utf8(name)?;
+            self.symbols.push(name);
+            Ok(format!(
+                "{name:?} (defines symbol #{})",
+                self.symbols.len() - 1
+            ))
+        } else {
+            let name = self
+                .symbols
+                .get(id as usize)
+                .ok_or(Error::UnknownSymbol(id))?;
+            Ok(format!("symbol #{id}: {name:?}"))
+        }
+    }
+
+    fn write_line(&mut self, depth: usize, description: impl Display) -> Result<()> {
+        let bytes = &self.payload[self.atom_start..self.input.position];
+        let mut hex = String::new();
+        for byte in bytes.iter().take(HEX_PREVIEW_LEN) {
+            write!(hex, "{byte:02x} ").expect("writing to a string is infallible");
+        }
+        if bytes.len() > HEX_PREVIEW_LEN {
+            hex.push_str("..");
+        }
+        let mut indent = String::new();
+        for _ in 0..depth.min(MAX_INDENT_DEPTH) {
+            indent.push_str("  ");
+        }
+        writeln!(
+            self.output,
+            "{:08x}  {hex:HEX_COLUMN_WIDTH$}{indent}{description}",
+            self.atom_start,
+        )?;
+        Ok(())
+    }
+}
+
+fn nucleus_name(nucleus: &Nucleus<'_>) -> &'static str {
+    match nucleus {
+        Nucleus::Boolean(_) => "Boolean",
+        Nucleus::Integer(_) => "Integer",
+        Nucleus::Float(_) => "Float",
+        Nucleus::Bytes(_) => "Bytes",
+        Nucleus::Unit => "Unit",
+        Nucleus::Named => "Named",
+        Nucleus::DynamicMap => "DynamicMap",
+        Nucleus::DynamicEnd => "DynamicEnd",
+        Nucleus::DynamicSequence => "DynamicSequence",
+        Nucleus::InternedBytes => "InternedBytes",
+        Nucleus::BytesReference => "BytesReference",
+        Nucleus::PackedArray => "PackedArray",
+    }
+}
+
+fn describe_nucleus(nucleus: Option<&Nucleus<'_>>) -> String {
+    match nucleus {
+        None => String::from("None"),
+        Some(Nucleus::Boolean(value)) => value.to_string(),
+        Some(Nucleus::Integer(value)) => value.to_string(),
+        Some(Nucleus::Float(value)) => value.to_string(),
+        Some(Nucleus::Bytes(BufferedBytes::Data(bytes))) => match str::from_utf8(bytes) {
+            Ok(string) => format!("{string:?}"),
+            Err(_) => format!("{} bytes", bytes.len()),
+        },
+        Some(nucleus) => String::from(nucleus_name(nucleus)),
+    }
+}
+
+/// A reader over the payload that tracks the current offset, and returns an
+/// error rather than reading past the end of the payload.
+struct Input<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl Read for Input<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.data.read(buf)?;
+        self.position += read;
+        Ok(read)
+    }
+}
+
+impl<'a> Reader<'a> for Input<'a> {
+    fn buffered_read_bytes(
+        &mut self,
+        length: usize,
+        _scratch: &mut Vec<u8>,
+    ) -> Result<BufferedBytes<'a>> {
+        if length > self.data.len() {
+            return Err(Error::Eof);
+        }
+        let (bytes, remaining) = self.data.split_at(length);
+        self.data = remaining;
+        self.position += length;
+        Ok(BufferedBytes::Data(bytes))
+    }
+}
+
+#[test]
+fn dump_payload() {
+    use serde_derive::Serialize;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Circle { radius: f32 },
+        Empty,
+    }
+
+    #[derive(Serialize)]
+    struct Drawing<'a> {
+        name: &'a str,
+        shapes: Vec<Shape>,
+    }
+
+    let payload = pot::Config::new()
+        .compatibility(pot::Compatibility::V5)
+        .serialize(&Drawing {
+            name: "example",
+            shapes: vec![Shape::Circle { radius: 1.5 }, Shape::Empty, Shape::Empty],
+        })
+        .unwrap();
+    let mut output = Vec::new();
+    dump(&payload, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\
+00000000  50 6f 74 02                header (version 2)
+00000004  a2                         Map(2)
+00000005  c8 6e 61 6d 65               Symbol(8) \"name\" (defines symbol #0)
+0000000a  e7 65 78 61 6d 70 6c 65      Bytes(7) \"example\"
+00000012  cc 73 68 61 70 65 73         Symbol(12) \"shapes\" (defines symbol #1)
+00000019  83                           Sequence(3)
+0000001a  04                             Special(4) Named
+0000001b  cc 43 69 72 63 6c 65             Symbol(12) \"Circle\" (defines symbol #2)
+00000022  a1                               Map(1)
+00000023  cc 72 61 64 69 75 73               Symbol(12) \"radius\" (defines symbol #3)
+0000002a  61 00 3e                           Float(1) 1.5
+0000002d  ca 45 6d 70 74 79              Symbol(10) \"Empty\" (defines symbol #4)
+00000033  c9                             Symbol(9) symbol #4: \"Empty\"
+"
+    );
+
+    let mut output = Vec::new();
+    assert!(matches!(
+        dump(&payload[..0x30], &mut output),
+        Err(Error::Eof)
+    ));
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.ends_with("0000002d  error: unexpected end of file\n"));
+
+    let mut unknown_symbol = payload[..0x1b].to_vec();
+    unknown_symbol.push(0xcb);
+    let mut output = Vec::new();
+    assert!(matches!(
+        dump(&unknown_symbol, &mut output),
+        Err(Error::UnknownSymbol(5))
+    ));
+}
+
+#[test]
+fn dump_compact_payload() {
+    use pot::packed::Packed;
+    use serde_derive::Serialize;
+
+    #[derive(Serialize)]
+    struct Sample<'a> {
+        path: &'a str,
+        copy: &'a str,
+        level: u8,
+        offset: i8,
+        weights: Packed<u16>,
+    }
+
+    let payload = pot::Config::new()
+        .compatibility(pot::Compatibility::V7)
+        .intern_bytes(Some(4))
+        .serialize(&Sample {
+            path: "/tmp/pot",
+            copy: "/tmp/pot",
+            level: 3,
+            offset: -2,
+            weights: Packed(vec![1000, 2000]),
+        })
+        .unwrap();
+    let mut output = Vec::new();
+    dump(&payload, &mut output).unwrap();
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "\
+00000000  50 6f 74 04                header (version 4)
+00000004  a5                         Map(5)
+00000005  c8 70 61 74 68               Symbol(8) \"path\" (defines symbol #0)
+0000000a  08                           Special(8) InternedBytes
+0000000b  e8 2f 74 6d 70 2f 70 6f ..     Bytes(8) \"/tmp/pot\"
+00000014  c8 63 6f 70 79               Symbol(8) \"copy\" (defines symbol #1)
+00000019  09                           Special(9) BytesReference
+0000001a  50                             UInt(16) 0
+0000001b  ca 6c 65 76 65 6c            Symbol(10) \"level\" (defines symbol #2)
+00000021  53                           UInt(19) 3
+00000022  cc 6f 66 66 73 65 74         Symbol(12) \"offset\" (defines symbol #3)
+00000029  3e                           Int(30) -2
+0000002a  ce 77 65 69 67 68 74 73      Symbol(14) \"weights\" (defines symbol #4)
+00000032  0a                           Special(10) PackedArray
+00000033  40 21                          UInt(0) 33
+00000035  e4 e8 03 d0 07                 Bytes(4) 4 bytes
+"
+    );
+}
+
+#[test]
+fn dump_deeply_nested_payload() {
+    let depth = 200_000;
+    let mut payload = b"Pot\0".to_vec();
+    for _ in 0..depth {
+        format::write_atom_header(&mut payload, Kind::Sequence, 1).unwrap();
+    }
+    format::write_u8(&mut payload, 0).unwrap();
+
+    let mut output = Vec::new();
+    dump(&payload, &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    assert_eq!(output.lines().count(), depth + 2);
+    let last = output.lines().last().unwrap();
+    let indent = " ".repeat(MAX_INDENT_DEPTH * 2);
+    assert!(last.ends_with(&format!("  {indent}UInt(0) 0")), "{last:?}");
+}
@@ -0,0 +1,101 @@
+//! The `pot` command-line tool, which inspects Pot payloads.
+
+#![forbid(unsafe_code)]
+#![warn(clippy::pedantic, future_incompatible, rust_2018_idioms)]
+
+mod dump;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+/// Tools for inspecting Pot payloads.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Prints every atom in a payload, along with its offset, encoded bytes
+    /// and decoded contents.
+    Dump {
+        /// The file containing the payload. If not provided, the payload is
+        /// read from stdin.
+        path: Option<PathBuf>,
+    },
+    /// Converts a payload between Pot and another format, writing the result
+    /// to stdout.
+    Convert {
+        /// Converts a Pot payload to this format.
+        #[arg(
+            long,
+            value_enum,
+            required_unless_present = "from",
+            conflicts_with = "from"
+        )]
+        to: Option<Format>,
+        /// Converts a payload in this format to Pot.
+        #[arg(long, value_enum)]
+        from: Option<Format>,
+        /// The file containing the payload. If not provided, the payload is
+        /// read from stdin.
+        path: Option<PathBuf>,
+    },
+}
+
+/// A format that Pot payloads can be converted to and from.
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Format {
+    /// JSON, as described by `pot::json`.
+    Json,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let result = match args.command {
+        Command::Dump { path } => read_input(path.as_deref())
+            .and_then(|payload| dump::dump(&payload, &mut io::stdout().lock())),
+        Command::Convert { to, from, path } => {
+            read_input(path.as_deref()).and_then(|input| convert(&input, to, from))
+        }
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn convert(input: &[u8], to: Option<Format>, from: Option<Format>) -> pot::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match (to, from) {
+        (Some(Format::Json), _) => {
+            pot::json::to_json_writer(input, &mut stdout)?;
+            writeln!(stdout)?;
+        }
+        (None, Some(Format::Json)) => {
+            stdout.write_all(&pot::json::from_json_reader(input)?)?;
+        }
+        (None, None) => unreachable!("clap requires --to or --from"),
+    }
+    Ok(stdout.flush()?)
+}
+
+fn read_input(path: Option<&Path>) -> pot::Result<Vec<u8>> {
+    if let Some(path) = path {
+        Ok(fs::read(path)?)
+    } else {
+        let mut input = Vec::new();
+        io::stdin().lock().read_to_end(&mut input)?;
+        Ok(input)
+    }
+}
@@ -0,0 +1,73 @@
+use std::future::poll_fn;
+use std::io;
+use std::pin::Pin;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::framing::{Framer, Progress};
+use crate::{Config, Error};
+
+/// The maximum number of bytes requested from a reader at once. Atom headers
+/// can claim lengths far larger than the data that follows them, so the
+/// buffer only grows as data actually arrives.
+const MAX_READ_CHUNK: usize = 16 * 1024;
+
+/// Reads the bytes of exactly one complete Pot payload from `reader`,
+/// including its header.
+///
+/// Returns [`Error::TooManyBytesRead`] if the payload would exceed the
+/// allocation budget of `config`, or [`Error::TooManyInputBytes`] if it would
+/// exceed its input limit.
+pub(crate) async fn read_payload<R>(reader: &mut R, config: &Config) -> Result<Vec<u8>, Error>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    let mut framer = Framer::with_known_symbols(0);
+    let mut buffer = Vec::new();
+    loop {
+        match framer.advance(&buffer)? {
+            Progress::Complete(_) => return Ok(buffer),
+            Progress::NeedMore(needed) => {
+                let start = buffer.len();
+                let end = start + needed.min(MAX_READ_CHUNK);
+                if end > config.allocation_budget {
+                    return Err(Error::TooManyBytesRead);
+                }
+                config.limits.check_input_length(end)?;
+                buffer.resize(end, 0);
+                read_exact(reader, &mut buffer[start..]).await?;
+            }
+        }
+    }
+}
+
+async fn read_exact<R>(reader: &mut R, mut buffer: &mut [u8]) -> Result<(), Error>
+where
+    R: AsyncRead + Unpin + ?Sized,
+{
+    while !buffer.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *reader).poll_read(cx, buffer)).await {
+            Ok(0) => return Err(Error::from(io::Error::from(io::ErrorKind::UnexpectedEof))),
+            Ok(read) => buffer = &mut buffer[read..],
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(Error::from(err)),
+        }
+    }
+    Ok(())
+}
+
+/// Writes all of `bytes` to `writer`.
+pub(crate) async fn write_all<W>(writer: &mut W, mut bytes: &[u8]) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin + ?Sized,
+{
+    while !bytes.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, bytes)).await {
+            Ok(0) => return Err(Error::from(io::Error::from(io::ErrorKind::WriteZero))),
+            Ok(written) => bytes = &bytes[written..],
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(Error::from(err)),
+        }
+    }
+    Ok(())
+}
@@ -1,14 +1,20 @@
-use std::borrow::Cow;
-use std::collections::VecDeque;
-use std::fmt::Debug;
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::{Debug, Display};
+use core::ops::{Deref, Range};
+use core::str;
+#[cfg(feature = "std")]
 use std::io::Read;
-use std::ops::{Deref, Range};
-use std::str;
 
-use byteorder::ReadBytesExt;
 use format::Kind;
+use serde::de::value::{CowStrDeserializer, SeqDeserializer};
 use serde::de::{
-    self, DeserializeSeed, EnumAccess, Error as _, MapAccess, SeqAccess, VariantAccess, Visitor,
+    self, DeserializeSeed, EnumAccess, Error as _, IgnoredAny, MapAccess, SeqAccess, VariantAccess,
+    Visitor,
 };
 use serde::ser::SerializeSeq;
 use serde::{Deserialize, Serialize};
@@ -16,10 +22,20 @@ use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
 use crate::format::{
-    self, Atom, Float, InnerFloat, InnerInteger, Integer, Nucleus, CURRENT_VERSION,
+    self, Atom, Float, InnerFloat, InnerInteger, Integer, InternedBytes, Nucleus, Special,
+    CURRENT_VERSION,
 };
-use crate::reader::{BufferedBytes, IoReader, Reader, SliceReader};
-use crate::{Error, Result};
+use crate::limits::LimitTracker;
+use crate::packed::PackedElements;
+#[cfg(feature = "std")]
+use crate::reader::IoReader;
+use crate::reader::{BufferedBytes, Reader, SliceReader};
+use crate::ser::numeric_field_id;
+use crate::{Error, Limits, Result};
+
+/// The maximum nesting depth used when one has not been configured using
+/// [`Config::max_depth`](crate::Config::max_depth).
+pub(crate) const DEFAULT_MAX_DEPTH: usize = 128;
 
 /// Deserializer for the Pot format.
 pub struct Deserializer<'s, 'de, R: Reader<'de>> {
@@ -27,11 +43,18 @@ pub struct Deserializer<'s, 'de, R: Reader<'de>> {
     symbols: SymbolMapRef<'s, 'de>,
     peeked_atom: VecDeque<Atom<'de>>,
     remaining_budget: usize,
+    remaining_depth: usize,
+    limits: LimitTracker,
+    error_positions: bool,
+    compact_bytes: bool,
+    diagnostics: Option<Vec<Diagnostic>>,
+    interned: InternedBytes<'de>,
     scratch: Vec<u8>,
+    path_keys: Vec<u8>,
 }
 
 impl<'s, 'de, R: Reader<'de>> Debug for Deserializer<'s, 'de, R> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Deserializer")
             .field("symbols", &self.symbols)
             .field("peeked_atom", &self.peeked_atom)
@@ -47,7 +70,7 @@ impl<'s, 'de> Deserializer<'s, 'de, SliceReader<'de>> {
         Self::from_slice_with_symbols(input, SymbolMapRef::temporary(), maximum_bytes_allocatable)
     }
 
-    fn from_slice_with_symbols(
+    pub(crate) fn from_slice_with_symbols(
         input: &'de [u8],
         symbols: SymbolMapRef<'s, 'de>,
         maximum_bytes_allocatable: usize,
@@ -63,7 +86,8 @@ impl<'s, 'de> Deserializer<'s, 'de, SliceReader<'de>> {
     }
 }
 
-impl<'s, 'de, R: ReadBytesExt> Deserializer<'s, 'de, IoReader<R>> {
+#[cfg(feature = "std")]
+impl<'s, 'de, R: Read> Deserializer<'s, 'de, IoReader<R>> {
     /// Returns a new deserializer for `input`.
     #[inline]
     pub(crate) fn from_read(
@@ -87,12 +111,161 @@ impl<'s, 'de, R: Reader<'de>> Deserializer<'s, 'de, R> {
             symbols,
             peeked_atom: VecDeque::new(),
             remaining_budget: maximum_bytes_allocatable,
+            remaining_depth: DEFAULT_MAX_DEPTH,
+            limits: LimitTracker::new(Limits::new(), 0),
+            error_positions: false,
+            compact_bytes: false,
+            diagnostics: None,
+            interned: InternedBytes::default(),
             scratch: Vec::new(),
+            path_keys: Vec::new(),
         };
         deserializer.read_header()?;
         Ok(deserializer)
     }
 
+    /// Sets the maximum number of nested sequences, maps, named values and
+    /// enum variants and returns self.
+    #[must_use]
+    pub(crate) const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.remaining_depth = max_depth;
+        self
+    }
+
+    /// Enforces `limits` while deserializing and returns self.
+    #[must_use]
+    pub(crate) fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = LimitTracker::new(limits, self.symbols.byte_len());
+        self
+    }
+
+    /// Sets whether errors are wrapped in [`Error::Positioned`] and returns
+    /// self.
+    #[must_use]
+    pub(crate) const fn with_error_positions(mut self, error_positions: bool) -> Self {
+        self.error_positions = error_positions;
+        self
+    }
+
+    /// Sets whether bytes are accepted where a sequence of `u8`s is expected
+    /// and returns self. See `Config::compact_bytes`.
+    #[must_use]
+    pub(crate) const fn with_compact_bytes(mut self, compact_bytes: bool) -> Self {
+        self.compact_bytes = compact_bytes;
+        self
+    }
+
+    /// Sets whether recoverable problems are recorded as [`Diagnostic`]s
+    /// rather than returned as errors and returns self.
+    #[must_use]
+    pub(crate) fn with_lenient(mut self, lenient: bool) -> Self {
+        self.diagnostics = lenient.then(Vec::new);
+        self
+    }
+
+    /// Returns the problems that have been recovered from.
+    pub(crate) fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        self.diagnostics
+            .as_mut()
+            .map(core::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Records `error` as a [`Diagnostic`] when lenient. Otherwise, `error`
+    /// is returned.
+    fn record(&mut self, error: Error) -> Result<()> {
+        let offset = self.input.bytes_read();
+        match &mut self.diagnostics {
+            Some(diagnostics) => {
+                diagnostics.push(Diagnostic { error, offset });
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+
+    /// Records `error`, which occurred while reading the value beginning with
+    /// `atom`, and skips the remainder of the value when lenient. Otherwise,
+    /// `error` is returned.
+    fn recover(&mut self, atom: Atom<'de>, error: Error) -> Result<()> {
+        if self.diagnostics.is_none() {
+            return Err(error);
+        }
+
+        self.record(error)?;
+        self.peeked_atom.push_front(atom);
+        de::Deserializer::deserialize_ignored_any(&mut *self, IgnoredAny)?;
+        Ok(())
+    }
+
+    /// Returns the value in `result`, or recovers from its error by returning
+    /// the default value when lenient.
+    fn or_default<T: Default>(&mut self, atom: Atom<'de>, result: Result<T>) -> Result<T> {
+        match result {
+            Ok(value) => Ok(value),
+            Err(err) => {
+                self.recover(atom, err)?;
+                Ok(T::default())
+            }
+        }
+    }
+
+    /// Recovers from encountering `atom` where a value of the `expected` kind,
+    /// described by `description`, was expected.
+    fn unexpected_kind(
+        &mut self,
+        atom: Atom<'de>,
+        expected: Kind,
+        description: &str,
+    ) -> Result<()> {
+        if self.diagnostics.is_some() {
+            let encountered = atom.kind;
+            self.recover(atom, Error::UnexpectedKind(encountered, expected))
+        } else {
+            Err(Error::custom(format!(
+                "expected {description}, got {:?}",
+                atom.kind
+            )))
+        }
+    }
+
+    /// Attaches the current position to `error`, prepending `segment` to its
+    /// path, if error positions are enabled.
+    pub(crate) fn locate(&self, error: Error, segment: Option<&PathKey<'de>>) -> Error {
+        if !self.error_positions {
+            return error;
+        }
+
+        let (error, offset, mut path) = match error {
+            Error::Positioned {
+                error,
+                offset,
+                path,
+            } => (error, offset, path),
+            error => (Box::new(error), self.input.bytes_read(), String::new()),
+        };
+        if let Some(segment) = segment {
+            path.insert_str(0, &segment.to_path_segment(&self.symbols, &self.path_keys));
+        }
+        Error::Positioned {
+            error,
+            offset,
+            path,
+        }
+    }
+
+    /// Invokes `visit` one level deeper, returning
+    /// [`Error::TooDeeplyNested`] if the maximum depth would be exceeded.
+    fn nested<T>(&mut self, visit: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.remaining_depth = self
+            .remaining_depth
+            .checked_sub(1)
+            .ok_or(Error::TooDeeplyNested)?;
+        let result = visit(self);
+        self.remaining_depth += 1;
+        result
+    }
+
     fn read_header(&mut self) -> Result<()> {
         let version = format::read_header(&mut self.input)?;
         if version <= CURRENT_VERSION {
@@ -106,11 +279,44 @@ impl<'s, 'de, R: Reader<'de>> Deserializer<'s, 'de, R> {
         if let Some(peeked) = self.peeked_atom.pop_front() {
             Ok(peeked)
         } else {
-            format::read_atom(
+            let limits = &mut self.limits;
+            match format::read_atom_resolved(
                 &mut self.input,
                 &mut self.remaining_budget,
                 &mut self.scratch,
-            )
+                &mut self.interned,
+                |kind, arg| limits.atom(kind, arg),
+            ) {
+                Err(Error::UnknownSpecial(special)) if self.diagnostics.is_some() => {
+                    // Unknown specials have no contents, so they can be
+                    // treated as None.
+                    self.record(Error::UnknownSpecial(special))?;
+                    Ok(Atom {
+                        kind: Kind::Special,
+                        arg: Special::None as u64,
+                        nucleus: None,
+                    })
+                }
+                result => result,
+            }
+        }
+    }
+
+    /// Visits the packed array whose [`Special::PackedArray`] atom was just
+    /// read as a sequence.
+    fn visit_packed_array<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let element_type = self.read_atom()?;
+        let elements = self.read_atom()?;
+        match format::packed_array_parts(element_type, elements)? {
+            (element_type, BufferedBytes::Data(bytes)) => {
+                visitor.visit_seq(PackedElements::borrowed(element_type, bytes))
+            }
+            (element_type, BufferedBytes::Scratch) => {
+                visitor.visit_seq(PackedElements::new(element_type, &self.scratch))
+            }
         }
     }
 
@@ -141,6 +347,7 @@ impl<'s, 'de, R: Reader<'de>> Deserializer<'s, 'de, R> {
             self.symbols.visit_symbol_id(arg, visitor)
         } else {
             // New symbol
+            self.limits.symbol(self.symbols.len(), arg as usize)?;
             let name = self
                 .input
                 .buffered_read_bytes(arg as usize, &mut self.scratch)?;
@@ -185,10 +392,24 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special => match &atom.nucleus {
                 Some(Nucleus::Boolean(value)) => visitor.visit_bool(*value),
                 Some(Nucleus::Unit) => visitor.visit_unit(),
-                Some(Nucleus::Named) => visitor.visit_map(AtomList::new(self, Some(1))),
-                Some(Nucleus::DynamicMap) => visitor.visit_map(AtomList::new(self, None)),
+                Some(Nucleus::Named) => {
+                    self.nested(|de| visitor.visit_map(AtomList::new(de, Some(1))))
+                }
+                Some(Nucleus::DynamicMap) => {
+                    self.nested(|de| AtomList::visit_dynamic_map(de, &[], visitor))
+                }
+                Some(Nucleus::DynamicSequence) => {
+                    self.nested(|de| AtomList::visit_dynamic_seq(de, visitor))
+                }
                 Some(Nucleus::DynamicEnd) => Err(Error::custom("unexpected dynamic end")),
-                Some(Nucleus::Bytes(_) | Nucleus::Integer(_) | Nucleus::Float(_)) => {
+                Some(Nucleus::PackedArray) => self.visit_packed_array(visitor),
+                Some(
+                    Nucleus::Bytes(_)
+                    | Nucleus::Integer(_)
+                    | Nucleus::Float(_)
+                    | Nucleus::InternedBytes
+                    | Nucleus::BytesReference,
+                ) => {
                     unreachable!("read_atom can't return this nucleus as a Special")
                 }
                 None => visitor.visit_none(),
@@ -230,8 +451,12 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
                 Some(Nucleus::Float(Float(InnerFloat::F64(value)))) => visitor.visit_f64(value),
                 _ => unreachable!("read_atom should never return anything else"),
             },
-            Kind::Sequence => visitor.visit_seq(AtomList::new(self, Some(atom.arg as usize))),
-            Kind::Map => visitor.visit_map(AtomList::new(self, Some(atom.arg as usize))),
+            Kind::Sequence => {
+                self.nested(|de| visitor.visit_seq(AtomList::new(de, Some(atom.arg as usize))))
+            }
+            Kind::Map => {
+                self.nested(|de| visitor.visit_map(AtomList::new(de, Some(atom.arg as usize))))
+            }
             Kind::Symbol => self.visit_symbol(&atom, visitor),
             Kind::Bytes => match &atom.nucleus {
                 Some(Nucleus::Bytes(bytes)) => match bytes {
@@ -269,11 +494,15 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
                 Some(Nucleus::Integer(integer)) => visitor.visit_bool(!integer.is_zero()),
                 Some(Nucleus::Boolean(b)) => visitor.visit_bool(b),
                 Some(Nucleus::Unit) | None => visitor.visit_bool(false),
-                other => Err(Error::custom(format!(
-                    "expected bool nucleus, got {other:?}"
-                ))),
+                _ => {
+                    self.unexpected_kind(atom, Kind::Special, "bool")?;
+                    visitor.visit_bool(false)
+                }
             },
-            other => Err(Error::custom(format!("expected bool, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Special, "bool")?;
+                visitor.visit_bool(false)
+            }
         }
     }
 
@@ -287,7 +516,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_i8(integer.as_i8()?)
+                    visitor.visit_i8(self.or_default(atom, integer.as_i8())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -295,7 +524,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_i8(0)
             }
-            other => Err(Error::custom(format!("expected i8, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Int, "i8")?;
+                visitor.visit_i8(0)
+            }
         }
     }
 
@@ -309,7 +541,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_i16(integer.as_i16()?)
+                    visitor.visit_i16(self.or_default(atom, integer.as_i16())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -317,7 +549,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_i16(0)
             }
-            other => Err(Error::custom(format!("expected i16, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Int, "i16")?;
+                visitor.visit_i16(0)
+            }
         }
     }
 
@@ -331,7 +566,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_i32(integer.as_i32()?)
+                    visitor.visit_i32(self.or_default(atom, integer.as_i32())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -339,7 +574,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_i32(0)
             }
-            other => Err(Error::custom(format!("expected i32, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Int, "i32")?;
+                visitor.visit_i32(0)
+            }
         }
     }
 
@@ -353,7 +591,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_i64(integer.as_i64()?)
+                    visitor.visit_i64(self.or_default(atom, integer.as_i64())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -361,7 +599,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_i64(0)
             }
-            other => Err(Error::custom(format!("expected i64, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Int, "i64")?;
+                visitor.visit_i64(0)
+            }
         }
     }
 
@@ -375,7 +616,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_i128(integer.as_i128()?)
+                    visitor.visit_i128(self.or_default(atom, integer.as_i128())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -383,7 +624,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_i128(0)
             }
-            other => Err(Error::custom(format!("expected i128, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Int, "i128")?;
+                visitor.visit_i128(0)
+            }
         }
     }
 
@@ -397,7 +641,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_u8(integer.as_u8()?)
+                    visitor.visit_u8(self.or_default(atom, integer.as_u8())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -405,7 +649,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_u8(0)
             }
-            other => Err(Error::custom(format!("expected u8, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::UInt, "u8")?;
+                visitor.visit_u8(0)
+            }
         }
     }
 
@@ -419,7 +666,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_u16(integer.as_u16()?)
+                    visitor.visit_u16(self.or_default(atom, integer.as_u16())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -427,7 +674,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_u16(0)
             }
-            other => Err(Error::custom(format!("expected u16, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::UInt, "u16")?;
+                visitor.visit_u16(0)
+            }
         }
     }
 
@@ -441,7 +691,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_u32(integer.as_u32()?)
+                    visitor.visit_u32(self.or_default(atom, integer.as_u32())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -449,7 +699,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_u32(0)
             }
-            other => Err(Error::custom(format!("expected u32, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::UInt, "u32")?;
+                visitor.visit_u32(0)
+            }
         }
     }
 
@@ -463,7 +716,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_u64(integer.as_u64()?)
+                    visitor.visit_u64(self.or_default(atom, integer.as_u64())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -471,7 +724,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_u64(0)
             }
-            other => Err(Error::custom(format!("expected u64, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::UInt, "u64")?;
+                visitor.visit_u64(0)
+            }
         }
     }
 
@@ -485,7 +741,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_u128(integer.as_u128()?)
+                    visitor.visit_u128(self.or_default(atom, integer.as_u128())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -493,7 +749,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_u128(0)
             }
-            other => Err(Error::custom(format!("expected i64, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::UInt, "i64")?;
+                visitor.visit_u128(0)
+            }
         }
     }
 
@@ -507,7 +766,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_f32(integer.as_f32()?)
+                    visitor.visit_f32(self.or_default(atom, integer.as_f32())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -515,7 +774,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
 
             Kind::Float => {
                 if let Some(Nucleus::Float(float)) = atom.nucleus {
-                    visitor.visit_f32(float.as_f32()?)
+                    visitor.visit_f32(self.or_default(atom, float.as_f32())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -523,7 +782,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_f32(0.)
             }
-            other => Err(Error::custom(format!("expected f32, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Float, "f32")?;
+                visitor.visit_f32(0.)
+            }
         }
     }
 
@@ -537,7 +799,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_f64(integer.as_f64()?)
+                    visitor.visit_f64(self.or_default(atom, integer.as_f64())?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -553,7 +815,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_f64(0.)
             }
-            other => Err(Error::custom(format!("expected f64, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Float, "f64")?;
+                visitor.visit_f64(0.)
+            }
         }
     }
 
@@ -567,10 +832,11 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::UInt | Kind::Int => {
                 if let Some(Nucleus::Integer(integer)) = atom.nucleus {
-                    visitor.visit_char(
-                        char::from_u32(integer.as_u32()?)
-                            .ok_or_else(|| Error::InvalidUtf8(String::from("invalid char")))?,
-                    )
+                    let char = integer.as_u32().and_then(|code| {
+                        char::from_u32(code)
+                            .ok_or_else(|| Error::InvalidUtf8(String::from("invalid char")))
+                    });
+                    visitor.visit_char(self.or_default(atom, char)?)
                 } else {
                     unreachable!("read_atom should never return anything else")
                 }
@@ -578,7 +844,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_char('\0')
             }
-            other => Err(Error::custom(format!("expected char, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::UInt, "char")?;
+                visitor.visit_char('\0')
+            }
         }
     }
 
@@ -592,10 +861,21 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         match atom.kind {
             Kind::Bytes => match atom.nucleus {
                 Some(Nucleus::Bytes(bytes)) => match bytes {
-                    BufferedBytes::Data(bytes) => {
-                        visitor.visit_borrowed_str(str::from_utf8(bytes)?)
-                    }
-                    BufferedBytes::Scratch => visitor.visit_str(str::from_utf8(&self.scratch)?),
+                    BufferedBytes::Data(bytes) => match str::from_utf8(bytes) {
+                        Ok(str) => visitor.visit_borrowed_str(str),
+                        Err(err) => {
+                            self.record(Error::from(err))?;
+                            visitor.visit_string(String::from_utf8_lossy(bytes).into_owned())
+                        }
+                    },
+                    BufferedBytes::Scratch => match str::from_utf8(&self.scratch) {
+                        Ok(str) => visitor.visit_str(str),
+                        Err(err) => {
+                            self.record(Error::from(err))?;
+                            visitor
+                                .visit_string(String::from_utf8_lossy(&self.scratch).into_owned())
+                        }
+                    },
                 },
                 _ => unreachable!("read_atom should never return anything else"),
             },
@@ -610,7 +890,10 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
                     self.visit_symbol(&atom, visitor)
                 }
             }
-            other => Err(Error::custom(format!("expected str, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Bytes, "str")?;
+                visitor.visit_borrowed_str("")
+            }
         }
     }
 
@@ -654,10 +937,28 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
                 }
                 visitor.visit_byte_buf(buffer)
             }
+            Kind::Special if matches!(atom.nucleus, Some(Nucleus::DynamicSequence)) => {
+                let mut buffer = Vec::new();
+                loop {
+                    match self.read_atom()?.nucleus {
+                        Some(Nucleus::DynamicEnd) => break,
+                        Some(Nucleus::Integer(integer)) => buffer.push(integer.as_u8()?),
+                        _ => {
+                            return Err(Error::custom(
+                                "expected byte array, encountered non-integer atom",
+                            ))
+                        }
+                    }
+                }
+                visitor.visit_byte_buf(buffer)
+            }
             Kind::Special if matches!(atom.nucleus, Some(Nucleus::Unit) | None) => {
                 visitor.visit_borrowed_bytes(b"")
             }
-            other => Err(Error::custom(format!("expected bytes, got {other:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Bytes, "bytes")?;
+                visitor.visit_borrowed_bytes(b"")
+            }
         }
     }
 
@@ -697,7 +998,8 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         if atom.kind == Kind::Special && matches!(atom.nucleus, Some(Nucleus::Unit)) {
             visitor.visit_unit()
         } else {
-            Err(Error::custom(format!("expected unit, got {:?}", atom.kind)))
+            self.unexpected_kind(atom, Kind::Special, "unit")?;
+            visitor.visit_unit()
         }
     }
 
@@ -732,14 +1034,28 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
     {
         let atom = self.read_atom()?;
         if atom.kind == Kind::Sequence {
-            visitor.visit_seq(AtomList::new(self, Some(atom.arg as usize)))
+            self.nested(|de| visitor.visit_seq(AtomList::new(de, Some(atom.arg as usize))))
+        } else if atom.kind == Kind::Special
+            && matches!(atom.nucleus, Some(Nucleus::DynamicSequence))
+        {
+            self.nested(|de| AtomList::visit_dynamic_seq(de, visitor))
+        } else if atom.kind == Kind::Special && matches!(atom.nucleus, Some(Nucleus::PackedArray)) {
+            self.visit_packed_array(visitor)
+        } else if let (Kind::Bytes, Some(Nucleus::Bytes(bytes)), true) =
+            (atom.kind, &atom.nucleus, self.compact_bytes)
+        {
+            // Sequences of bytes may be encoded as a single atom. See
+            // `Config::compact_bytes`. Strings are encoded the same way, so
+            // bytes are only accepted when compact bytes are enabled.
+            de::Deserializer::deserialize_any(
+                SeqDeserializer::new(bytes.as_slice(&self.scratch).iter().copied()),
+                visitor,
+            )
         } else if atom.kind == Kind::Special && matches!(atom.nucleus, Some(Nucleus::Unit) | None) {
             visitor.visit_seq(EmptyList)
         } else {
-            Err(Error::custom(format!(
-                "expected sequence, got {:?}",
-                atom.kind
-            )))
+            self.unexpected_kind(atom, Kind::Sequence, "sequence")?;
+            visitor.visit_seq(EmptyList)
         }
     }
 
@@ -773,28 +1089,62 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
         V: Visitor<'de>,
     {
         let atom = self.read_atom()?;
-        match (atom.kind, atom.nucleus) {
-            (Kind::Map, _) => visitor.visit_map(AtomList::new(self, Some(atom.arg as usize))),
+        match (atom.kind, &atom.nucleus) {
+            (Kind::Map, _) => {
+                self.nested(|de| visitor.visit_map(AtomList::new(de, Some(atom.arg as usize))))
+            }
             (Kind::Special, Some(Nucleus::DynamicMap)) => {
-                visitor.visit_map(AtomList::new(self, None))
+                self.nested(|de| AtomList::visit_dynamic_map(de, &[], visitor))
             }
             (Kind::Special, Some(Nucleus::Unit) | None) => visitor.visit_map(EmptyList),
-            (kind, _) => Err(Error::custom(format!("expected map, got {kind:?}"))),
+            _ => {
+                self.unexpected_kind(atom, Kind::Map, "map")?;
+                visitor.visit_map(EmptyList)
+            }
         }
     }
 
     #[cfg_attr(feature = "tracing", instrument(level = "trace", skip(visitor)))]
     #[inline]
+    #[allow(clippy::cast_possible_truncation)]
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        // Structs serialized using `StructRepr::Positional` are sequences of
+        // their field values. Maps are given the struct's fields so that keys
+        // written using `StructRepr::Numbered` can be resolved.
+        let atom = self.peek_atom()?;
+        match (atom.kind, &atom.nucleus) {
+            (Kind::Sequence, _) => {
+                let count = atom.arg as usize;
+                self.read_atom()?;
+                self.nested(|de| {
+                    AtomList::visit_positional_struct(de, Some(count), fields, visitor)
+                })
+            }
+            (Kind::Special, Some(Nucleus::DynamicSequence)) => {
+                self.read_atom()?;
+                self.nested(|de| AtomList::visit_positional_struct(de, None, fields, visitor))
+            }
+            (Kind::Map, _) => {
+                let count = atom.arg as usize;
+                self.read_atom()?;
+                self.nested(|de| {
+                    visitor.visit_map(AtomList::new(de, Some(count)).with_fields(fields))
+                })
+            }
+            (Kind::Special, Some(Nucleus::DynamicMap)) => {
+                self.read_atom()?;
+                self.nested(|de| AtomList::visit_dynamic_map(de, fields, visitor))
+            }
+            _ => self.deserialize_map(visitor),
+        }
     }
 
     #[cfg_attr(feature = "tracing", instrument(level = "trace", skip(visitor)))]
@@ -808,7 +1158,7 @@ impl<'a, 'de, 's, R: Reader<'de>> de::Deserializer<'de> for &'a mut Deserializer
     where
         V: Visitor<'de>,
     {
-        visitor.visit_enum(self)
+        self.nested(|de| visitor.visit_enum(de))
     }
 
     #[cfg_attr(feature = "tracing", instrument(level = "trace", skip(visitor)))]
@@ -878,20 +1228,165 @@ impl<'de> SeqAccess<'de> for EmptyList {
     }
 }
 
+/// Returns the name of the field in `fields` that has the numeric `id`.
+///
+/// Fields named `#` followed by a number use that number as their id, and all
+/// other fields use their index. See `StructRepr::Numbered`.
+fn field_with_id(fields: &'static [&'static str], id: u64) -> Option<&'static str> {
+    fields
+        .iter()
+        .find(|field| numeric_field_id(field) == Some(id))
+        .or_else(|| {
+            usize::try_from(id)
+                .ok()
+                .and_then(|index| fields.get(index))
+                .filter(|field| numeric_field_id(field).is_none())
+        })
+        .copied()
+}
+
+/// A deserializer for a field that was omitted from the end of a positional
+/// struct. Optional fields are deserialized as `None`, and all other types
+/// return [`Error::NotFound`].
+struct MissingField;
+
+impl<'de> de::Deserializer<'de> for MissingField {
+    type Error = Error;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct newtype_struct seq tuple tuple_struct map
+        struct enum identifier ignored_any
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+
+    fn deserialize_any<V>(self, _visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        Err(Error::NotFound)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_none()
+    }
+}
+
 struct AtomList<'a, 's, 'de, R: Reader<'de>> {
     de: &'a mut Deserializer<'s, 'de, R>,
     consumed: usize,
     count: Option<usize>,
     eof: bool,
+    key: PathKey<'de>,
+    key_start: usize,
+    fields: &'static [&'static str],
 }
 
 impl<'a, 's, 'de, R: Reader<'de>> AtomList<'a, 's, 'de, R> {
     fn new(de: &'a mut Deserializer<'s, 'de, R>, count: Option<usize>) -> Self {
+        let key_start = de.path_keys.len();
         Self {
             de,
             count,
             consumed: 0,
             eof: false,
+            key: PathKey::Unknown,
+            key_start,
+            fields: &[],
+        }
+    }
+
+    /// Sets the names of the fields of the struct being deserialized and
+    /// returns self.
+    fn with_fields(mut self, fields: &'static [&'static str]) -> Self {
+        self.fields = fields;
+        self
+    }
+
+    /// Reads the next map key if it is the numeric id of a struct field,
+    /// returning the name of the field. Ids that don't belong to a field are
+    /// returned as `#id`. See `StructRepr::Numbered`.
+    fn read_field_id(&mut self) -> Result<Option<Cow<'static, str>>> {
+        if self.fields.is_empty() {
+            return Ok(None);
+        }
+        let atom = self.de.peek_atom()?;
+        let (Kind::Int | Kind::UInt, Some(Nucleus::Integer(id))) = (atom.kind, &atom.nucleus)
+        else {
+            return Ok(None);
+        };
+        let id = id.as_u64()?;
+        self.de.read_atom()?;
+        if let Some(field) = field_with_id(self.fields, id) {
+            self.key = PathKey::Borrowed(field.as_bytes());
+            Ok(Some(Cow::Borrowed(field)))
+        } else {
+            self.key = PathKey::Integer(Integer::from(id));
+            Ok(Some(Cow::Owned(format!("#{id}"))))
+        }
+    }
+
+    /// Visits a struct whose field values were serialized as a sequence of
+    /// `count` values. Optional fields missing from the end of the sequence
+    /// are deserialized as `None`, and values beyond the last field are
+    /// skipped.
+    fn visit_positional_struct<V>(
+        de: &'a mut Deserializer<'s, 'de, R>,
+        count: Option<usize>,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut list = Self::new(de, count).with_fields(fields);
+        let value = visitor.visit_seq(&mut list)?;
+        while list.next_element::<IgnoredAny>()?.is_some() {}
+        Ok(value)
+    }
+
+    /// Visits a sequence of unknown length. The end of the sequence is read
+    /// even if `visitor` stops reading elements early, such as when
+    /// deserializing a tuple.
+    fn visit_dynamic_seq<V>(de: &'a mut Deserializer<'s, 'de, R>, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut list = Self::new(de, None);
+        let value = visitor.visit_seq(&mut list)?;
+        list.expect_end()?;
+        Ok(value)
+    }
+
+    /// Visits a map of unknown length. The end of the map is read even if
+    /// `visitor` stops reading entries early.
+    ///
+    /// If the map is a struct, `fields` contains the names of its fields.
+    fn visit_dynamic_map<V>(
+        de: &'a mut Deserializer<'s, 'de, R>,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut list = Self::new(de, None).with_fields(fields);
+        let value = visitor.visit_map(&mut list)?;
+        list.expect_end()?;
+        Ok(value)
+    }
+
+    fn expect_end(&mut self) -> Result<()> {
+        if self.check_is_eof()? {
+            Ok(())
+        } else {
+            Err(Error::custom("expected dynamic end"))
         }
     }
 
@@ -916,10 +1411,54 @@ impl<'a, 's, 'de, R: Reader<'de>> AtomList<'a, 's, 'de, R> {
 
         Ok(false)
     }
+
+    /// Remembers the key that is about to be read, so that it can be included
+    /// in the path of errors that occur while reading its value.
+    ///
+    /// Keys that were read into the scratch buffer are copied into the
+    /// deserializer's `path_keys` buffer, which is shared by every level of
+    /// nesting. Each map only uses the end of the buffer beyond `key_start`,
+    /// so the buffer is reused rather than allocating a string for each key.
+    fn record_key(&mut self) -> Result<()> {
+        let next_symbol = self.de.symbols.len() as u64;
+        self.de.peek_atom()?;
+        let atom = &self.de.peeked_atom[0];
+        self.key = match (atom.kind, &atom.nucleus) {
+            (Kind::Symbol, _) if atom.arg & 0b1 != 0 => PathKey::Symbol(atom.arg >> 1),
+            (Kind::Symbol, _) => PathKey::Symbol(next_symbol),
+            (_, Some(Nucleus::Bytes(BufferedBytes::Data(bytes)))) => PathKey::Borrowed(bytes),
+            (_, Some(Nucleus::Bytes(BufferedBytes::Scratch))) => {
+                self.de.path_keys.truncate(self.key_start);
+                self.de.path_keys.extend_from_slice(&self.de.scratch);
+                PathKey::Buffered(self.key_start..self.de.path_keys.len())
+            }
+            (_, Some(Nucleus::Integer(integer))) => PathKey::Integer(*integer),
+            _ => PathKey::Unknown,
+        };
+        Ok(())
+    }
+
+    /// Checks the length limit of a sequence or map of unknown length before
+    /// its next value is read.
+    fn check_dynamic_length(&self) -> Result<()> {
+        if self.count.is_none() {
+            self.de.limits.dynamic_element(self.consumed)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<'de, R: Reader<'de>> Drop for AtomList<'_, '_, 'de, R> {
+    fn drop(&mut self) {
+        // Release the keys recorded by this map so that sibling containers
+        // reuse the same space.
+        self.de.path_keys.truncate(self.key_start);
+    }
 }
 
 impl<'a, 's, 'de, R: Reader<'de>> Debug for AtomList<'a, 's, 'de, R> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("AtomList")
             .field("de", &self.de)
             .field("consumed", &self.consumed)
@@ -939,10 +1478,30 @@ impl<'a, 's, 'de, R: Reader<'de>> SeqAccess<'de> for AtomList<'a, 's, 'de, R> {
         T: DeserializeSeed<'de>,
     {
         if self.check_is_eof()? {
-            Ok(None)
+            let Some(field) = self.fields.get(self.consumed) else {
+                return Ok(None);
+            };
+            // Trailing fields of positional structs may be omitted. Returning
+            // `None` for fields that aren't optional lets serde use their
+            // default value or report them as missing.
+            self.consumed += 1;
+            match seed.deserialize(MissingField) {
+                Ok(value) => Ok(Some(value)),
+                Err(Error::NotFound) => Ok(None),
+                Err(err) => Err(self
+                    .de
+                    .locate(err, Some(&PathKey::Borrowed(field.as_bytes())))),
+            }
         } else {
+            self.check_dynamic_length()?;
             self.consumed += 1;
-            seed.deserialize(&mut *self.de).map(Some)
+            seed.deserialize(&mut *self.de).map(Some).map_err(|err| {
+                let key = match self.fields.get(self.consumed - 1) {
+                    Some(field) => PathKey::Borrowed(field.as_bytes()),
+                    None => PathKey::Index(self.consumed - 1),
+                };
+                self.de.locate(err, Some(&key))
+            })
         }
     }
 
@@ -964,8 +1523,17 @@ impl<'a, 's, 'de, R: Reader<'de>> MapAccess<'de> for AtomList<'a, 's, 'de, R> {
         if self.check_is_eof()? {
             Ok(None)
         } else {
+            self.check_dynamic_length()?;
             self.consumed += 1;
-            seed.deserialize(&mut *self.de).map(Some)
+            let result = if let Some(field) = self.read_field_id()? {
+                seed.deserialize(CowStrDeserializer::<Error>::new(field))
+            } else {
+                if self.de.error_positions {
+                    self.record_key()?;
+                }
+                seed.deserialize(&mut *self.de)
+            };
+            result.map(Some).map_err(|err| self.de.locate(err, None))
         }
     }
 
@@ -977,6 +1545,7 @@ impl<'a, 's, 'de, R: Reader<'de>> MapAccess<'de> for AtomList<'a, 's, 'de, R> {
     {
         // Deserialize a map value.
         seed.deserialize(&mut *self.de)
+            .map_err(|err| self.de.locate(err, Some(&self.key)))
     }
 
     #[inline]
@@ -1037,11 +1606,67 @@ impl<'a, 's, 'de, R: Reader<'de>> VariantAccess<'de> for &'a mut Deserializer<'s
 
     #[cfg_attr(feature = "tracing", instrument(level = "trace", skip(visitor)))]
     #[inline]
-    fn struct_variant<V>(self, _fields: &'static [&'static str], visitor: V) -> Result<V::Value>
+    fn struct_variant<V>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        de::Deserializer::deserialize_map(self, visitor)
+        de::Deserializer::deserialize_struct(self, "", fields, visitor)
+    }
+}
+
+/// A problem that was recovered from while deserializing in [lenient
+/// mode](crate::Config::lenient).
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    /// The problem that was encountered, such as [`Error::UnexpectedKind`],
+    /// [`Error::ImpreciseCastWouldLoseData`], [`Error::UnknownSpecial`] or
+    /// [`Error::InvalidUtf8`].
+    pub error: Error,
+    /// The number of bytes that had been read when the problem was
+    /// encountered, if known.
+    pub offset: Option<usize>,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        Display::fmt(&self.error, f)?;
+        if let Some(offset) = self.offset {
+            write!(f, " (byte {offset})")?;
+        }
+        Ok(())
+    }
+}
+
+/// A key or index within a sequence or map, used to build the path of
+/// [`Error::Positioned`].
+#[derive(Debug)]
+pub(crate) enum PathKey<'de> {
+    Index(usize),
+    Symbol(u64),
+    Borrowed(&'de [u8]),
+    /// A key stored in [`Deserializer`]'s `path_keys` buffer.
+    Buffered(Range<usize>),
+    Integer(Integer),
+    Unknown,
+}
+
+impl PathKey<'_> {
+    fn to_path_segment(&self, symbols: &SymbolMapRef<'_, '_>, path_keys: &[u8]) -> String {
+        match self {
+            PathKey::Index(index) => format!("[{index}]"),
+            PathKey::Symbol(id) => match symbols.get(*id) {
+                Ok(symbol) => format!(".{}", &*symbol),
+                Err(_) => String::from(".?"),
+            },
+            PathKey::Borrowed(bytes) => format!(".{}", String::from_utf8_lossy(bytes)),
+            PathKey::Buffered(range) => format!(
+                ".{}",
+                String::from_utf8_lossy(path_keys.get(range.clone()).unwrap_or_default())
+            ),
+            PathKey::Integer(integer) => format!("[{integer}]"),
+            PathKey::Unknown => String::from("[?]"),
+        }
     }
 }
 
@@ -1064,31 +1689,43 @@ impl<'a, 'de> SymbolMapRef<'a, 'de> {
         Self(SymbolMapRefPrivate::Temporary(SymbolList::new()))
     }
 
+    /// Returns the symbol with `symbol_id`, or an error if the symbol is not
+    /// known.
     #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn get(&self, symbol_id: u64) -> Result<SymbolStr<'de, '_>> {
+        match &self.0 {
+            SymbolMapRefPrivate::Temporary(vec) => vec.get(symbol_id as usize),
+            SymbolMapRefPrivate::Persistent(vec) => vec.get(symbol_id as usize),
+        }
+        .ok_or(Error::UnknownSymbol(symbol_id))
+    }
+
     fn visit_symbol_id<V>(&self, symbol_id: u64, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        match self.get(symbol_id)? {
+            SymbolStr::Data(symbol) => visitor.visit_borrowed_str(symbol),
+            SymbolStr::InList(symbol) => visitor.visit_str(symbol),
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
         match &self.0 {
-            SymbolMapRefPrivate::Temporary(vec) => {
-                let symbol = vec
-                    .get(symbol_id as usize)
-                    .ok_or(Error::UnknownSymbol(symbol_id))?;
-                match symbol {
-                    SymbolStr::Data(symbol) => visitor.visit_borrowed_str(symbol),
-                    SymbolStr::InList(symbol) => visitor.visit_str(symbol),
-                }
-            }
-            SymbolMapRefPrivate::Persistent(vec) => {
-                let symbol = vec
-                    .get(symbol_id as usize)
-                    .ok_or(Error::UnknownSymbol(symbol_id))?;
-                visitor.visit_str(&symbol)
-            }
+            SymbolMapRefPrivate::Temporary(vec) => vec.len(),
+            SymbolMapRefPrivate::Persistent(vec) => vec.len(),
         }
     }
 
-    fn push(&mut self, symbol: &str) {
+    /// Returns the total length of all symbols, in bytes.
+    pub(crate) fn byte_len(&self) -> usize {
+        match &self.0 {
+            SymbolMapRefPrivate::Temporary(vec) => vec.byte_len(),
+            SymbolMapRefPrivate::Persistent(vec) => vec.byte_len(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, symbol: &str) {
         #[allow(clippy::match_same_arms)] // false positive due to lifetimes
         match &mut self.0 {
             SymbolMapRefPrivate::Temporary(vec) => vec.push(symbol),
@@ -1096,7 +1733,7 @@ impl<'a, 'de> SymbolMapRef<'a, 'de> {
         }
     }
 
-    fn push_borrowed(&mut self, symbol: &'de str) {
+    pub(crate) fn push_borrowed(&mut self, symbol: &'de str) {
         match &mut self.0 {
             SymbolMapRefPrivate::Temporary(vec) => vec.push_borrowed(symbol),
             SymbolMapRefPrivate::Persistent(vec) => vec.push(symbol),
@@ -1169,6 +1806,17 @@ impl<'de> SymbolList<'de> {
         self.entries.len()
     }
 
+    /// Returns the total length of all symbols in the list, in bytes.
+    pub(crate) fn byte_len(&self) -> usize {
+        self.entries
+            .iter()
+            .map(|entry| match entry {
+                SymbolListEntry::Buffer(range) => range.len(),
+                SymbolListEntry::Borrowed(str) => str.len(),
+            })
+            .sum()
+    }
+
     /// Returns true if there are no symbols in this list.
     #[inline]
     #[must_use]
@@ -1198,6 +1846,7 @@ impl SymbolMap {
     ///
     /// This should only be used with data generated by using a persistent
     /// [`ser::SymbolMap`](crate::ser::SymbolMap).
+    #[cfg(feature = "std")]
     #[inline]
     pub fn deserializer_for<'de, R>(
         &mut self,
@@ -1224,6 +1873,7 @@ impl SymbolMap {
     ///
     /// This should only be used with data generated by using a persistent
     /// [`ser::SymbolMap`](crate::ser::SymbolMap).
+    #[cfg(feature = "std")]
     pub fn deserialize_from<'de, T>(&mut self, reader: impl Read) -> Result<T>
     where
         T: Deserialize<'de>,
@@ -1232,13 +1882,13 @@ impl SymbolMap {
     }
 
     #[must_use]
-    fn persistent<'de>(&mut self) -> SymbolMapRef<'_, 'de> {
+    pub(crate) fn persistent<'de>(&mut self) -> SymbolMapRef<'_, 'de> {
         SymbolMapRef(SymbolMapRefPrivate::Persistent(self))
     }
 }
 
 impl Serialize for SymbolMap {
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
@@ -1251,7 +1901,7 @@ impl Serialize for SymbolMap {
 }
 
 impl<'de> Deserialize<'de> for SymbolMap {
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
@@ -1264,11 +1914,11 @@ struct SymbolMapVisitor;
 impl<'de> Visitor<'de> for SymbolMapVisitor {
     type Value = SymbolMap;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         formatter.write_str("symbol map")
     }
 
-    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
@@ -1284,6 +1934,7 @@ impl<'de> Visitor<'de> for SymbolMapVisitor {
 }
 
 /// A symbol stored in a [`SymbolList`].
+#[derive(Debug, Clone, Copy)]
 pub enum SymbolStr<'de, 'ephemeral> {
     /// A symbol that has been borrowed from the data being deserialized.
     Data(&'de str),
@@ -1,7 +1,9 @@
-use std::fmt::{Debug, Display};
+use alloc::boxed::Box;
+use alloc::string::{FromUtf8Error, String, ToString};
+use core::fmt::{Debug, Display};
+use core::str::Utf8Error;
+#[cfg(feature = "std")]
 use std::io;
-use std::str::Utf8Error;
-use std::string::FromUtf8Error;
 
 use serde::{de, ser};
 
@@ -24,8 +26,10 @@ pub enum Error {
     /// A numerical value could not be handled without losing precision or truncation.
     ImpreciseCastWouldLoseData,
     /// An IO error occurred.
+    #[cfg(feature = "std")]
     Io(io::Error),
-    /// A sequence of unknown size cannot be serialized.
+    /// A sequence of unknown size cannot be serialized. Sequences of unknown
+    /// size require [`Compatibility::V5`](crate::Compatibility::V5) or later.
     SequenceSizeMustBeKnown,
     /// String data contained invalid UTF-8 characters.
     InvalidUtf8(String),
@@ -35,6 +39,12 @@ pub enum Error {
     UnexpectedKind(Kind, Kind),
     /// A requested symbol id was not found.
     UnknownSymbol(u64),
+    /// A [`Special::BytesReference`](crate::format::Special::BytesReference)
+    /// referred to bytes that were not interned.
+    UnknownBytesReference(u64),
+    /// A [`Special::PackedArray`](crate::format::Special::PackedArray) had an
+    /// unknown element type, or contained a partial element.
+    InvalidPackedArray,
     /// An unsupported byte count for a numeric type was encountered.
     UnsupportedByteCount(Kind, usize),
     /// An atom header was incorrectly formatted.
@@ -43,10 +53,109 @@ pub enum Error {
     TooManyBytesRead,
     /// An unknown [`Special`](crate::format::Special) was encountered.
     UnknownSpecial(UnknownSpecial),
+    /// A container was written with a different number of values than it
+    /// declared.
+    ContainerLengthMismatch {
+        /// The number of values the container declared.
+        expected: u64,
+        /// The number of values written to the container.
+        written: u64,
+    },
+    /// A value or container end was written where the enclosing structure
+    /// could not accept it.
+    UnbalancedContainers,
+    /// The number of atoms read exceeds [`Limits::max_atoms`](crate::Limits::max_atoms).
+    TooManyAtoms,
+    /// The number of symbols read exceeds
+    /// [`Limits::max_symbols`](crate::Limits::max_symbols).
+    TooManySymbols,
+    /// The total length of the symbols read exceeds
+    /// [`Limits::max_symbol_bytes`](crate::Limits::max_symbol_bytes).
+    TooManySymbolBytes,
+    /// A string or bytes value is longer than
+    /// [`Limits::max_bytes_length`](crate::Limits::max_bytes_length).
+    BytesTooLong,
+    /// A sequence or map contains more values than
+    /// [`Limits::max_container_length`](crate::Limits::max_container_length),
+    /// or more than can be represented.
+    ContainerTooLong,
+    /// The payload is longer than
+    /// [`Limits::max_input_bytes`](crate::Limits::max_input_bytes).
+    TooManyInputBytes,
+    /// The requested key or index was not found.
+    NotFound,
+    /// Values were nested more deeply than [`Config::max_depth`](crate::Config::max_depth)
+    /// allows.
+    TooDeeplyNested,
+    /// An error occurred while deserializing the value at a known position.
+    ///
+    /// This variant is only returned when
+    /// [`Config::error_positions`](crate::Config::error_positions) is enabled.
+    Positioned {
+        /// The error that occurred.
+        error: Box<Error>,
+        /// The number of bytes that had been read when the error occurred, if
+        /// known.
+        offset: Option<usize>,
+        /// The path to the value being deserialized, such as
+        /// `.entries[1532].timestamp`. The root value has an empty path.
+        path: String,
+    },
+}
+
+impl Error {
+    /// Returns the number of bytes that had been read when this error
+    /// occurred, if known.
+    ///
+    /// Positions are only tracked when
+    /// [`Config::error_positions`](crate::Config::error_positions) is enabled.
+    #[must_use]
+    pub const fn offset(&self) -> Option<usize> {
+        match self {
+            Error::Positioned { offset, .. } => *offset,
+            _ => None,
+        }
+    }
+
+    /// Returns the path to the value that was being deserialized when this
+    /// error occurred, such as `.entries[1532].timestamp`.
+    ///
+    /// Sequence elements and integer map keys are written as `[index]`, and
+    /// struct fields, enum variants and string map keys are written as
+    /// `.name`. The root value has an empty path. Positions are only tracked
+    /// when [`Config::error_positions`](crate::Config::error_positions) is
+    /// enabled.
+    #[must_use]
+    pub fn path(&self) -> Option<&str> {
+        match self {
+            Error::Positioned { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    /// Returns this error without its position information.
+    ///
+    /// ```rust
+    /// let config = pot::Config::new().error_positions(true);
+    /// let payload = pot::to_vec(&[1_u16, 2, 300]).unwrap();
+    /// let err = config.deserialize::<Vec<u8>>(&payload).unwrap_err();
+    /// assert_eq!(err.path(), Some("[2]"));
+    /// assert!(matches!(
+    ///     err.without_position(),
+    ///     pot::Error::ImpreciseCastWouldLoseData
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn without_position(&self) -> &Error {
+        match self {
+            Error::Positioned { error, .. } => error.without_position(),
+            other => other,
+        }
+    }
 }
 
 impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Error::NotAPot => f.write_str("not a pot: invalid header"),
             Error::IncompatibleVersion => f.write_str("incompatible version"),
@@ -54,6 +163,7 @@ impl Display for Error {
             Error::TrailingBytes => f.write_str("extra data at end of input"),
             Error::Eof => f.write_str("unexpected end of file"),
             Error::ImpreciseCastWouldLoseData => f.write_str("numerical data cannot fit"),
+            #[cfg(feature = "std")]
             Error::Io(io) => write!(f, "io error: {io}"),
             Error::SequenceSizeMustBeKnown => {
                 f.write_str("serializing sequences of unknown size is unsupported")
@@ -65,6 +175,8 @@ impl Display for Error {
                 "encountered atom kind {encountered:?}, expected {expected:?}"
             ),
             Error::UnknownSymbol(sym) => write!(f, "unknown symbol {sym}"),
+            Error::UnknownBytesReference(id) => write!(f, "unknown bytes reference {id}"),
+            Error::InvalidPackedArray => f.write_str("invalid packed array"),
             Error::InvalidAtomHeader => f.write_str("an atom header was incorrectly formatted"),
             Error::TooManyBytesRead => {
                 f.write_str("the deserialized value is larger than the allowed allocation limit")
@@ -73,12 +185,57 @@ impl Display for Error {
                 write!(f, "unexpected {kind:?} byte count ({count})")
             }
             Error::UnknownSpecial(err) => Display::fmt(err, f),
+            Error::ContainerLengthMismatch { expected, written } => write!(
+                f,
+                "container declared {expected} values, but {written} were written"
+            ),
+            Error::UnbalancedContainers => {
+                f.write_str("value written outside of a container that can accept it")
+            }
+            Error::TooManyAtoms => f.write_str("the maximum number of atoms has been exceeded"),
+            Error::TooManySymbols => f.write_str("the maximum number of symbols has been exceeded"),
+            Error::TooManySymbolBytes => {
+                f.write_str("the maximum total length of symbols has been exceeded")
+            }
+            Error::BytesTooLong => {
+                f.write_str("the maximum length of a string or bytes value has been exceeded")
+            }
+            Error::ContainerTooLong => {
+                f.write_str("the maximum length of a sequence or map has been exceeded")
+            }
+            Error::TooManyInputBytes => {
+                f.write_str("the maximum number of input bytes has been exceeded")
+            }
+            Error::NotFound => f.write_str("the requested key or index was not found"),
+            Error::TooDeeplyNested => f.write_str("the maximum nesting depth has been exceeded"),
+            Error::Positioned {
+                error,
+                offset,
+                path,
+            } => {
+                Display::fmt(error, f)?;
+                if !path.is_empty() {
+                    write!(f, " at {path}")?;
+                }
+                if let Some(offset) = offset {
+                    write!(f, " (byte {offset})")?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
-impl std::error::Error for Error {}
+impl ser::StdError for Error {
+    fn source(&self) -> Option<&(dyn ser::StdError + 'static)> {
+        match self {
+            Error::Positioned { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
 
+#[cfg(feature = "std")]
 impl From<io::Error> for Error {
     fn from(err: io::Error) -> Self {
         Self::Io(err)
@@ -0,0 +1,421 @@
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::ops::Deref;
+use core::str;
+#[cfg(feature = "std")]
+use std::io::Read;
+
+use serde::de::Error as _;
+
+use crate::de::{SymbolMapRef, SymbolStr};
+use crate::format::{self, Atom, InternedBytes, Kind, Nucleus, PackedType, CURRENT_VERSION};
+use crate::limits::LimitTracker;
+#[cfg(feature = "std")]
+use crate::reader::IoReader;
+use crate::reader::{self, BufferedBytes, SliceReader};
+use crate::{Error, Limits, Result};
+
+/// A pull-based reader that walks a Pot payload one [`Event`] at a time.
+///
+/// Unlike [`Deserializer`](crate::de::Deserializer), this type does not drive
+/// a serde visitor. Each call to [`next_event()`](Self::next_event) reads at
+/// most one atom, which makes it suitable for inspecting or routing payloads
+/// without decoding them into an in-memory representation. Symbols are tracked
+/// internally and are always reported resolved to their string values.
+///
+/// ```rust
+/// use pot::events::{Event, Reader};
+///
+/// let payload = pot::to_vec(&(1_u8, "hello")).unwrap();
+/// let mut reader = Reader::from_slice(&payload).unwrap();
+/// assert!(matches!(
+///     reader.next_event().unwrap(),
+///     Some(Event::StartSequence(Some(2)))
+/// ));
+/// assert!(matches!(
+///     reader.next_event().unwrap(),
+///     Some(Event::Scalar(_))
+/// ));
+/// match reader.next_event().unwrap() {
+///     Some(Event::Bytes(bytes)) => assert_eq!(&*bytes, b"hello"),
+///     other => unreachable!("unexpected event {other:?}"),
+/// }
+/// assert!(matches!(reader.next_event().unwrap(), Some(Event::End)));
+/// assert!(reader.next_event().unwrap().is_none());
+/// ```
+pub struct Reader<'s, 'de, R: reader::Reader<'de>> {
+    input: R,
+    symbols: SymbolMapRef<'s, 'de>,
+    containers: Vec<Container>,
+    pending_key: Option<Atom<'de>>,
+    finished: bool,
+    atoms_read: usize,
+    remaining_budget: usize,
+    limits: LimitTracker,
+    interned: InternedBytes<'de>,
+    scratch: Vec<u8>,
+}
+
+impl<'de, R: reader::Reader<'de>> Debug for Reader<'_, 'de, R> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Reader")
+            .field("symbols", &self.symbols)
+            .field("containers", &self.containers)
+            .field("pending_key", &self.pending_key)
+            .field("finished", &self.finished)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'de> Reader<'static, 'de, SliceReader<'de>> {
+    /// Returns a new reader for `input`, after validating the Pot header.
+    #[inline]
+    pub fn from_slice(input: &'de [u8]) -> Result<Self> {
+        Self::new(SliceReader::from(input), SymbolMapRef::temporary())
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> Reader<'static, '_, IoReader<R>> {
+    /// Returns a new reader for `input`, after validating the Pot header.
+    #[inline]
+    pub fn from_read(input: R) -> Result<Self> {
+        Self::new(IoReader::new(input), SymbolMapRef::temporary())
+    }
+}
+
+impl<'s, 'de, R: reader::Reader<'de>> Reader<'s, 'de, R> {
+    pub(crate) fn new(mut input: R, symbols: SymbolMapRef<'s, 'de>) -> Result<Self> {
+        let version = format::read_header(&mut input)?;
+        if version > CURRENT_VERSION {
+            return Err(Error::IncompatibleVersion);
+        }
+
+        Ok(Self {
+            input,
+            symbols,
+            containers: Vec::new(),
+            pending_key: None,
+            finished: false,
+            atoms_read: 0,
+            remaining_budget: usize::MAX,
+            limits: LimitTracker::new(Limits::new(), 0),
+            interned: InternedBytes::default(),
+            scratch: Vec::new(),
+        })
+    }
+
+    /// Enforces `limits` while reading and returns self.
+    #[must_use]
+    pub(crate) fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = LimitTracker::new(limits, self.symbols.byte_len());
+        self
+    }
+
+    /// Returns the number of containers that are currently open.
+    ///
+    /// Named values are not counted as containers.
+    #[must_use]
+    pub fn depth(&self) -> usize {
+        self.containers
+            .iter()
+            .filter(|container| !matches!(container, Container::Named { .. }))
+            .count()
+    }
+
+    /// Returns the input being read from.
+    pub(crate) const fn input(&self) -> &R {
+        &self.input
+    }
+
+    /// Returns the number of atoms that have been read.
+    pub(crate) const fn atoms_read(&self) -> usize {
+        self.atoms_read
+    }
+
+    /// Returns the number of symbols that are currently known.
+    pub(crate) fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns the next event, or `None` once the root value has been read
+    /// completely.
+    ///
+    /// After `None` has been returned, no further bytes are read from the
+    /// input.
+    pub fn next_event(&mut self) -> Result<Option<Event<'de, '_>>> {
+        if let Some(atom) = self.pending_key.take() {
+            return self.atom_event(atom).map(Some);
+        }
+
+        match self.containers.last() {
+            None if self.finished => return Ok(None),
+            Some(Container::Sequence { remaining: 0 } | Container::Map { remaining: 0, .. }) => {
+                self.containers.pop();
+                self.value_read();
+                return Ok(Some(Event::End));
+            }
+            _ => {}
+        }
+
+        let atom = self.read_atom()?;
+
+        if let Some(Container::DynamicSequence { length }) = self.containers.last_mut() {
+            if matches!(atom.nucleus, Some(Nucleus::DynamicEnd)) {
+                self.containers.pop();
+                self.value_read();
+                return Ok(Some(Event::End));
+            }
+
+            self.limits.dynamic_element(*length)?;
+            *length += 1;
+        }
+
+        if self.expecting_key() {
+            if let Some(Container::DynamicMap { length, .. }) = self.containers.last_mut() {
+                if matches!(atom.nucleus, Some(Nucleus::DynamicEnd)) {
+                    self.containers.pop();
+                    self.value_read();
+                    return Ok(Some(Event::End));
+                }
+
+                self.limits.dynamic_element(*length)?;
+                *length += 1;
+            }
+
+            self.pending_key = Some(atom);
+            return Ok(Some(Event::Key));
+        }
+
+        self.atom_event(atom).map(Some)
+    }
+
+    fn read_atom(&mut self) -> Result<Atom<'de>> {
+        let limits = &mut self.limits;
+        let atom = format::read_atom_resolved(
+            &mut self.input,
+            &mut self.remaining_budget,
+            &mut self.scratch,
+            &mut self.interned,
+            |kind, arg| limits.atom(kind, arg),
+        )?;
+        self.atoms_read += 1;
+        Ok(atom)
+    }
+
+    fn expecting_key(&self) -> bool {
+        matches!(
+            self.containers.last(),
+            Some(
+                Container::Map {
+                    expecting_key: true,
+                    ..
+                } | Container::DynamicMap {
+                    expecting_key: true,
+                    ..
+                }
+            )
+        )
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn atom_event(&mut self, atom: Atom<'de>) -> Result<Event<'de, '_>> {
+        match atom.kind {
+            Kind::Special => match atom.nucleus {
+                None => {
+                    self.value_read();
+                    Ok(Event::None)
+                }
+                Some(Nucleus::Named) => {
+                    self.containers.push(Container::Named { remaining: 2 });
+                    Ok(Event::Named)
+                }
+                Some(Nucleus::DynamicMap) => {
+                    self.containers.push(Container::DynamicMap {
+                        expecting_key: true,
+                        length: 0,
+                    });
+                    Ok(Event::StartMap(None))
+                }
+                Some(Nucleus::DynamicSequence) => {
+                    self.containers
+                        .push(Container::DynamicSequence { length: 0 });
+                    Ok(Event::StartSequence(None))
+                }
+                Some(Nucleus::DynamicEnd) => Err(Error::custom("unexpected dynamic end")),
+                Some(Nucleus::PackedArray) => {
+                    let element_type = self.read_atom()?;
+                    let elements = self.read_atom()?;
+                    self.value_read();
+                    match format::packed_array_parts(element_type, elements)? {
+                        (element_type, BufferedBytes::Data(bytes)) => {
+                            Ok(Event::PackedArray(element_type, Bytes::Data(bytes)))
+                        }
+                        (element_type, BufferedBytes::Scratch) => Ok(Event::PackedArray(
+                            element_type,
+                            Bytes::Scratch(&self.scratch),
+                        )),
+                    }
+                }
+                Some(nucleus) => {
+                    self.value_read();
+                    Ok(Event::Scalar(nucleus))
+                }
+            },
+            Kind::Int | Kind::UInt | Kind::Float => {
+                self.value_read();
+                Ok(Event::Scalar(
+                    atom.nucleus.expect("read_atom always returns a nucleus"),
+                ))
+            }
+            Kind::Sequence => {
+                self.containers.push(Container::Sequence {
+                    remaining: atom.arg,
+                });
+                Ok(Event::StartSequence(Some(atom.arg as usize)))
+            }
+            Kind::Map => {
+                self.containers.push(Container::Map {
+                    remaining: atom.arg,
+                    expecting_key: true,
+                });
+                Ok(Event::StartMap(Some(atom.arg as usize)))
+            }
+            Kind::Symbol => {
+                self.value_read();
+                let is_id = atom.arg & 0b1 != 0;
+                let arg = atom.arg >> 1;
+                if is_id {
+                    self.symbols.get(arg).map(Event::Symbol)
+                } else {
+                    self.limits.symbol(self.symbols.len(), arg as usize)?;
+                    match self
+                        .input
+                        .buffered_read_bytes(arg as usize, &mut self.scratch)?
+                    {
+                        BufferedBytes::Data(name) => {
+                            let name = str::from_utf8(name)?;
+                            self.symbols.push_borrowed(name);
+                            Ok(Event::Symbol(SymbolStr::Data(name)))
+                        }
+                        BufferedBytes::Scratch => {
+                            let name = str::from_utf8(&self.scratch)?;
+                            self.symbols.push(name);
+                            Ok(Event::Symbol(SymbolStr::InList(name)))
+                        }
+                    }
+                }
+            }
+            Kind::Bytes => {
+                self.value_read();
+                match atom.nucleus {
+                    Some(Nucleus::Bytes(BufferedBytes::Data(bytes))) => {
+                        Ok(Event::Bytes(Bytes::Data(bytes)))
+                    }
+                    Some(Nucleus::Bytes(BufferedBytes::Scratch)) => {
+                        Ok(Event::Bytes(Bytes::Scratch(&self.scratch)))
+                    }
+                    _ => unreachable!("read_atom always returns bytes"),
+                }
+            }
+        }
+    }
+
+    /// Records that a complete value has been read in the current container.
+    fn value_read(&mut self) {
+        loop {
+            match self.containers.last_mut() {
+                None => {
+                    self.finished = true;
+                }
+                Some(Container::Sequence { remaining }) => {
+                    *remaining = remaining.saturating_sub(1);
+                }
+                Some(Container::DynamicSequence { .. }) => {}
+                Some(Container::Map {
+                    remaining,
+                    expecting_key,
+                }) => {
+                    if !*expecting_key {
+                        *remaining = remaining.saturating_sub(1);
+                    }
+                    *expecting_key = !*expecting_key;
+                }
+                Some(Container::DynamicMap { expecting_key, .. }) => {
+                    *expecting_key = !*expecting_key;
+                }
+                Some(Container::Named { remaining }) => {
+                    *remaining -= 1;
+                    if *remaining == 0 {
+                        // A named value is complete once its value has been
+                        // read, which also completes a value in its parent.
+                        self.containers.pop();
+                        continue;
+                    }
+                }
+            }
+            break;
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Container {
+    Sequence { remaining: u64 },
+    Map { remaining: u64, expecting_key: bool },
+    DynamicMap { expecting_key: bool, length: usize },
+    DynamicSequence { length: usize },
+    Named { remaining: u8 },
+}
+
+/// An event produced by [`Reader`].
+#[derive(Debug)]
+pub enum Event<'de, 'a> {
+    /// A sequence. If the number of elements is known, it is contained. Each
+    /// element is reported as its own series of events, and the sequence is
+    /// terminated with [`Event::End`].
+    StartSequence(Option<usize>),
+    /// A map. If the number of entries is known, it is contained. The map is
+    /// terminated with [`Event::End`].
+    StartMap(Option<usize>),
+    /// The next value is a key in the current map. The map's value follows
+    /// immediately after the key's events.
+    Key,
+    /// The end of the most recently started sequence or map.
+    End,
+    /// A named value. The name is reported as the next event, and the named
+    /// value's events follow the name.
+    Named,
+    /// A `None` value.
+    None,
+    /// A boolean, integer, floating point or unit value.
+    Scalar(Nucleus<'de>),
+    /// A symbol, resolved to its string value.
+    Symbol(SymbolStr<'de, 'a>),
+    /// A series of bytes.
+    Bytes(Bytes<'de, 'a>),
+    /// A packed array of numbers. Contains the type of the elements and the
+    /// elements stored in little endian.
+    PackedArray(PackedType, Bytes<'de, 'a>),
+}
+
+/// Bytes contained in an [`Event::Bytes`].
+#[derive(Debug, Clone, Copy)]
+pub enum Bytes<'de, 'a> {
+    /// The bytes have been borrowed from the data being read.
+    Data(&'de [u8]),
+    /// The bytes are stored in the reader's scratch buffer, and are only valid
+    /// until the next event is read.
+    Scratch(&'a [u8]),
+}
+
+impl Deref for Bytes<'_, '_> {
+    type Target = [u8];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Bytes::Data(bytes) | Bytes::Scratch(bytes) => bytes,
+        }
+    }
+}
@@ -1,22 +1,32 @@
-use std::fmt::Display;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display};
+use core::hash::{Hash, Hasher};
+use core::ops::Range;
 
-use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
 use half::f16;
 
 pub(crate) const INITIAL_VERSION: u8 = 0;
 pub(crate) const V4_VERSION: u8 = 1;
-pub(crate) const CURRENT_VERSION: u8 = V4_VERSION;
-
-use crate::reader::{BufferedBytes, Reader};
-use crate::Error;
+pub(crate) const V5_VERSION: u8 = 2;
+pub(crate) const V6_VERSION: u8 = 3;
+pub(crate) const V7_VERSION: u8 = 4;
+pub(crate) const CURRENT_VERSION: u8 = V7_VERSION;
+
+use crate::de::SymbolList;
+use crate::reader::{BufferedBytes, Reader, SliceReader, StrictSliceReader};
+use crate::ser::SymbolMap;
+use crate::writer::Write;
+use crate::{Compatibility, Error};
 /// Writes an atom header into `writer`.
 #[allow(clippy::cast_possible_truncation)]
 #[inline]
-fn write_tiny_atom_header<W: WriteBytesExt>(
-    mut writer: W,
+fn write_tiny_atom_header<W: Write + ?Sized>(
+    writer: &mut W,
     kind: Kind,
     arg: u8,
-) -> std::io::Result<usize> {
+) -> Result<usize, Error> {
     // Kind is the 3 bits.
     let mut first_byte = (kind as u8) << 5;
     if arg > 0 {
@@ -31,11 +41,11 @@ fn write_tiny_atom_header<W: WriteBytesExt>(
 /// Writes an atom header into `writer`.
 #[allow(clippy::cast_possible_truncation)]
 #[inline]
-pub fn write_atom_header<W: WriteBytesExt>(
-    mut writer: W,
+pub fn write_atom_header<W: Write + ?Sized>(
+    writer: &mut W,
     kind: Kind,
     mut arg: u64,
-) -> std::io::Result<usize> {
+) -> Result<usize, Error> {
     if arg < 0x10 {
         write_tiny_atom_header(writer, kind, arg as u8)
     } else {
@@ -123,15 +133,22 @@ pub fn write_atom_header<W: WriteBytesExt>(
 
 /// Reads an atom header (kind and argument).
 #[inline]
-pub fn read_atom_header<R: ReadBytesExt>(reader: &mut R) -> Result<(Kind, u64), Error> {
-    let first_byte = reader.read_u8()?;
+pub fn read_atom_header<'de, R: Reader<'de> + ?Sized>(
+    reader: &mut R,
+) -> Result<(Kind, u64), Error> {
+    let [first_byte] = read_bytes(reader, 1)?;
     let kind = Kind::from_u8(first_byte >> 5)?;
+    if matches!(kind, Kind::Int | Kind::UInt) && first_byte & 0b10000 != 0 {
+        // Integers never need more than four bits to store their byte
+        // length, so the continuation bit marks an inline value instead.
+        return Ok((kind, u64::from(first_byte & 0b11111)));
+    }
     let mut arg = u64::from(first_byte & 0b1111);
     if first_byte & 0b10000 != 0 {
         let mut bytes_remaining = 9;
         let mut offset = 4;
         loop {
-            let byte = reader.read_u8()?;
+            let [byte] = read_bytes(reader, 1)?;
             let data = byte & 0x7f;
             arg |= u64::from(data) << offset;
             offset += 7;
@@ -152,9 +169,16 @@ pub enum Kind {
     Special = 0,
     /// A signed integer. Argument is the byte length, minus one. The following
     /// bytes are the value, stored in little endian.
+    ///
+    /// If the argument has [`INLINE_INTEGER`] set, the value is stored in the
+    /// lower four bits of the argument as a two's complement number, and no
+    /// bytes follow.
     Int = 1,
     /// An unsigned integer. Argument is the byte length, minus one. The
     /// following bytes are the value, stored in little endian.
+    ///
+    /// If the argument has [`INLINE_INTEGER`] set, the value is stored in the
+    /// lower four bits of the argument, and no bytes follow.
     UInt = 2,
     /// A floating point value. Argument is the byte length, minus one. Must be
     /// either 2, 4 or 8 bytes. The following bytes are the value, stored in
@@ -212,12 +236,26 @@ pub enum Special {
     Named = 4,
     /// A sequence of key-value pairs with an unknown length.
     DynamicMap = 5,
-    /// A terminal value for a [`Self::DynamicMap`].
+    /// A terminal value for a [`Self::DynamicMap`] or
+    /// [`Self::DynamicSequence`].
     DynamicEnd = 6,
+    /// A sequence of values with an unknown length.
+    DynamicSequence = 7,
+    /// A marker denoting that the following [`Kind::Bytes`] atom may be
+    /// referred to later using [`Self::BytesReference`]. Values are assigned
+    /// ids sequentially, starting at 0.
+    InternedBytes = 8,
+    /// A reference to a previously interned bytes value. Followed by a
+    /// [`Kind::UInt`] atom containing the id of the value.
+    BytesReference = 9,
+    /// A packed array of numbers. Followed by a [`Kind::UInt`] atom containing
+    /// the [`PackedType::code()`] of the elements, and a [`Kind::Bytes`] atom
+    /// containing the elements stored in little endian.
+    PackedArray = 10,
 }
 
 #[cfg(test)]
-pub(crate) const SPECIAL_COUNT: u64 = Special::Named as u64 + 1;
+pub(crate) const SPECIAL_COUNT: u64 = Special::PackedArray as u64 + 1;
 
 impl TryFrom<u64> for Special {
     type Error = UnknownSpecial;
@@ -232,6 +270,10 @@ impl TryFrom<u64> for Special {
             4 => Ok(Self::Named),
             5 => Ok(Self::DynamicMap),
             6 => Ok(Self::DynamicEnd),
+            7 => Ok(Self::DynamicSequence),
+            8 => Ok(Self::InternedBytes),
+            9 => Ok(Self::BytesReference),
+            10 => Ok(Self::PackedArray),
             _ => Err(UnknownSpecial(value)),
         }
     }
@@ -244,12 +286,87 @@ fn unknown_special() {
     assert!(err.to_string().contains("unknown special"));
 }
 
+/// The type of the elements of a [`Special::PackedArray`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum PackedType {
+    /// Signed 8-bit integers.
+    I8,
+    /// Signed 16-bit integers.
+    I16,
+    /// Signed 32-bit integers.
+    I32,
+    /// Signed 64-bit integers.
+    I64,
+    /// Unsigned 8-bit integers.
+    U8,
+    /// Unsigned 16-bit integers.
+    U16,
+    /// Unsigned 32-bit integers.
+    U32,
+    /// Unsigned 64-bit integers.
+    U64,
+    /// 32-bit floating point numbers.
+    F32,
+    /// 64-bit floating point numbers.
+    F64,
+}
+
+impl PackedType {
+    /// Returns the kind of atom each element would be encoded as if it were
+    /// not packed.
+    #[must_use]
+    pub const fn kind(self) -> Kind {
+        match self {
+            Self::I8 | Self::I16 | Self::I32 | Self::I64 => Kind::Int,
+            Self::U8 | Self::U16 | Self::U32 | Self::U64 => Kind::UInt,
+            Self::F32 | Self::F64 => Kind::Float,
+        }
+    }
+
+    /// Returns the number of bytes each element occupies.
+    #[must_use]
+    pub const fn width(self) -> usize {
+        match self {
+            Self::I8 | Self::U8 => 1,
+            Self::I16 | Self::U16 => 2,
+            Self::I32 | Self::U32 | Self::F32 => 4,
+            Self::I64 | Self::U64 | Self::F64 => 8,
+        }
+    }
+
+    /// Returns the code identifying this type in a packed array. The lowest
+    /// four bits contain the width minus one, and the remaining bits contain
+    /// the [`Kind`].
+    #[must_use]
+    pub const fn code(self) -> u64 {
+        (self.kind() as u64) << 4 | (self.width() as u64 - 1)
+    }
+
+    /// Returns the type identified by `code`, or [`Error::InvalidPackedArray`]
+    /// if `code` is not a known type.
+    pub const fn from_code(code: u64) -> Result<Self, Error> {
+        match code {
+            0x10 => Ok(Self::I8),
+            0x11 => Ok(Self::I16),
+            0x13 => Ok(Self::I32),
+            0x17 => Ok(Self::I64),
+            0x20 => Ok(Self::U8),
+            0x21 => Ok(Self::U16),
+            0x23 => Ok(Self::U32),
+            0x27 => Ok(Self::U64),
+            0x33 => Ok(Self::F32),
+            0x37 => Ok(Self::F64),
+            _ => Err(Error::InvalidPackedArray),
+        }
+    }
+}
+
 /// An unknown [`Special`] was encountered.
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub struct UnknownSpecial(pub u64);
 
 impl Display for UnknownSpecial {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "unknown special id: {}", self.0)
     }
 }
@@ -258,16 +375,16 @@ impl Display for UnknownSpecial {
 /// are 'Pot' (`0x506F74`), and the fourth byte is the version. The first
 /// version of Pot is 0.
 #[inline]
-pub fn write_header<W: WriteBytesExt>(mut writer: W, version: u8) -> std::io::Result<usize> {
-    writer.write_u32::<BigEndian>(0x506F_7400 | u32::from(version))?;
+pub fn write_header<W: Write + ?Sized>(writer: &mut W, version: u8) -> Result<usize, Error> {
+    writer.write_all(&(0x506F_7400 | u32::from(version)).to_be_bytes())?;
     Ok(4)
 }
 
 /// Reads a Pot header. See [`write_header`] for more information. Returns the version number contained within.
 #[allow(clippy::similar_names, clippy::cast_possible_truncation)]
 #[inline]
-pub fn read_header<R: ReadBytesExt>(reader: &mut R) -> Result<u8, Error> {
-    let header = reader.read_u32::<BigEndian>()?;
+pub fn read_header<'de, R: Reader<'de> + ?Sized>(reader: &mut R) -> Result<u8, Error> {
+    let header = u32::from_be_bytes(read_bytes(reader, 4)?);
     if header & 0x506F_7400 == 0x506F_7400 {
         let version = (header & 0xFF) as u8;
         Ok(version)
@@ -275,33 +392,46 @@ pub fn read_header<R: ReadBytesExt>(reader: &mut R) -> Result<u8, Error> {
         Err(Error::IncompatibleVersion)
     }
 }
+
+/// Reads `length` bytes into the beginning of an array of `N` bytes. The
+/// remaining bytes are zero.
+#[inline]
+fn read_bytes<'de, R: Reader<'de> + ?Sized, const N: usize>(
+    reader: &mut R,
+    length: usize,
+) -> Result<[u8; N], Error> {
+    let mut bytes = [0; N];
+    reader.read_into(&mut bytes[..length])?;
+    Ok(bytes)
+}
+
 /// Writes a [`Kind::Special`] atom.
 #[inline]
-pub fn write_special<W: WriteBytesExt>(writer: W, special: Special) -> std::io::Result<usize> {
+pub fn write_special<W: Write + ?Sized>(writer: &mut W, special: Special) -> Result<usize, Error> {
     write_atom_header(writer, Kind::Special, special as u64)
 }
 
 /// Writes a [`Kind::Special`] atom with [`Special::None`].
 #[inline]
-pub fn write_none<W: WriteBytesExt>(writer: W) -> std::io::Result<usize> {
+pub fn write_none<W: Write + ?Sized>(writer: &mut W) -> Result<usize, Error> {
     write_special(writer, Special::None)
 }
 
 /// Writes a [`Kind::Special`] atom with [`Special::Unit`].
 #[inline]
-pub fn write_unit<W: WriteBytesExt>(writer: W) -> std::io::Result<usize> {
+pub fn write_unit<W: Write + ?Sized>(writer: &mut W) -> Result<usize, Error> {
     write_special(writer, Special::Unit)
 }
 
 /// Writes a [`Kind::Special`] atom with [`Special::Named`].
 #[inline]
-pub fn write_named<W: WriteBytesExt>(writer: W) -> std::io::Result<usize> {
+pub fn write_named<W: Write + ?Sized>(writer: &mut W) -> Result<usize, Error> {
     write_special(writer, Special::Named)
 }
 
 /// Writes a [`Kind::Special`] atom with either [`Special::True`] or [`Special::False`].
 #[inline]
-pub fn write_bool<W: WriteBytesExt>(writer: W, boolean: bool) -> std::io::Result<usize> {
+pub fn write_bool<W: Write + ?Sized>(writer: &mut W, boolean: bool) -> Result<usize, Error> {
     write_special(
         writer,
         if boolean {
@@ -312,245 +442,335 @@ pub fn write_bool<W: WriteBytesExt>(writer: W, boolean: bool) -> std::io::Result
     )
 }
 
+/// Writes an atom of `kind` whose argument is the length of `bytes`, minus
+/// one, followed by `bytes`.
+#[allow(clippy::cast_possible_truncation)]
+#[inline]
+fn write_numeric<W: Write + ?Sized>(
+    writer: &mut W,
+    kind: Kind,
+    bytes: &[u8],
+) -> Result<usize, Error> {
+    let header_len = write_tiny_atom_header(writer, kind, bytes.len() as u8 - 1)?;
+    writer.write_all(bytes)?;
+    Ok(bytes.len() + header_len)
+}
+
+/// The bit set in the argument of a [`Kind::Int`] or [`Kind::UInt`] atom
+/// whose value is stored in the atom header.
+///
+/// Inline integers were added in
+/// [`Compatibility::V7`].
+pub const INLINE_INTEGER: u64 = 0b1_0000;
+
+/// The smallest value that [`write_inline_int()`] can write.
+pub const MIN_INLINE_INT: i8 = -8;
+
+/// The largest value that [`write_inline_int()`] can write.
+pub const MAX_INLINE_INT: i8 = 7;
+
+/// The largest value that [`write_inline_uint()`] can write.
+pub const MAX_INLINE_UINT: u8 = 15;
+
+/// Writes a [`Kind::Int`] atom that stores `value` in its header, using a
+/// single byte.
+///
+/// Returns [`Error::ImpreciseCastWouldLoseData`] if `value` is outside of
+/// [`MIN_INLINE_INT`] and [`MAX_INLINE_INT`]. Inline integers can only be read
+/// by versions of Pot that support
+/// [`Compatibility::V7`].
+#[allow(clippy::cast_sign_loss)]
+#[inline]
+pub fn write_inline_int<W: Write + ?Sized>(writer: &mut W, value: i8) -> Result<usize, Error> {
+    if (MIN_INLINE_INT..=MAX_INLINE_INT).contains(&value) {
+        write_inline_integer(writer, Kind::Int, value as u8)
+    } else {
+        Err(Error::ImpreciseCastWouldLoseData)
+    }
+}
+
+/// Writes a [`Kind::UInt`] atom that stores `value` in its header, using a
+/// single byte.
+///
+/// Returns [`Error::ImpreciseCastWouldLoseData`] if `value` is larger than
+/// [`MAX_INLINE_UINT`]. Inline integers can only be read by versions of Pot
+/// that support [`Compatibility::V7`].
+#[inline]
+pub fn write_inline_uint<W: Write + ?Sized>(writer: &mut W, value: u8) -> Result<usize, Error> {
+    if value <= MAX_INLINE_UINT {
+        write_inline_integer(writer, Kind::UInt, value)
+    } else {
+        Err(Error::ImpreciseCastWouldLoseData)
+    }
+}
+
+#[allow(clippy::cast_possible_truncation)]
+#[inline]
+fn write_inline_integer<W: Write + ?Sized>(
+    writer: &mut W,
+    kind: Kind,
+    value: u8,
+) -> Result<usize, Error> {
+    writer.write_all(&[(kind as u8) << 5 | INLINE_INTEGER as u8 | value & 0b1111])?;
+    Ok(1)
+}
+
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i8<W: WriteBytesExt>(mut writer: W, value: i8) -> std::io::Result<usize> {
-    let header_len =
-        write_atom_header(&mut writer, Kind::Int, std::mem::size_of::<i8>() as u64 - 1)?;
-    writer
-        .write_i8(value)
-        .map(|_| std::mem::size_of::<i8>() + header_len)
+pub fn write_i8<W: Write + ?Sized>(writer: &mut W, value: i8) -> Result<usize, Error> {
+    write_numeric(writer, Kind::Int, &value.to_le_bytes())
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i16<W: WriteBytesExt>(mut writer: W, value: i16) -> std::io::Result<usize> {
+pub fn write_i16<W: Write + ?Sized>(writer: &mut W, value: i16) -> Result<usize, Error> {
     if let Ok(value) = i8::try_from(value) {
         write_i8(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Int, 2 - 1)?;
-        writer
-            .write_i16::<LittleEndian>(value)
-            .map(|_| 2 + header_len)
+        write_numeric(writer, Kind::Int, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i24<W: WriteBytesExt>(mut writer: W, value: i32) -> std::io::Result<usize> {
+pub fn write_i24<W: Write + ?Sized>(writer: &mut W, value: i32) -> Result<usize, Error> {
     if let Ok(value) = i16::try_from(value) {
         write_i16(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Int, 3 - 1)?;
-        writer
-            .write_i24::<LittleEndian>(value)
-            .map(|_| 3 + header_len)
+        write_numeric(writer, Kind::Int, &value.to_le_bytes()[..3])
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i32<W: WriteBytesExt>(mut writer: W, value: i32) -> std::io::Result<usize> {
+pub fn write_i32<W: Write + ?Sized>(writer: &mut W, value: i32) -> Result<usize, Error> {
     if value >= -(2_i32.pow(23)) && value < 2_i32.pow(23) {
         write_i24(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Int, 4 - 1)?;
-        writer
-            .write_i32::<LittleEndian>(value)
-            .map(|_| 4 + header_len)
+        write_numeric(writer, Kind::Int, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i48<W: WriteBytesExt>(mut writer: W, value: i64) -> std::io::Result<usize> {
+pub fn write_i48<W: Write + ?Sized>(writer: &mut W, value: i64) -> Result<usize, Error> {
     if let Ok(value) = i32::try_from(value) {
         write_i32(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Int, 6 - 1)?;
-        writer
-            .write_i48::<LittleEndian>(value)
-            .map(|_| 6 + header_len)
+        write_numeric(writer, Kind::Int, &value.to_le_bytes()[..6])
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i64<W: WriteBytesExt>(mut writer: W, value: i64) -> std::io::Result<usize> {
+pub fn write_i64<W: Write + ?Sized>(writer: &mut W, value: i64) -> Result<usize, Error> {
     if value >= -(2_i64.pow(47)) && value < 2_i64.pow(47) {
         write_i48(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Int, 8 - 1)?;
-        writer
-            .write_i64::<LittleEndian>(value)
-            .map(|_| 8 + header_len)
+        write_numeric(writer, Kind::Int, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_i128<W: WriteBytesExt>(mut writer: W, value: i128) -> std::io::Result<usize> {
+pub fn write_i128<W: Write + ?Sized>(writer: &mut W, value: i128) -> Result<usize, Error> {
     if let Ok(value) = i64::try_from(value) {
         write_i64(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Int, 16 - 1)?;
-        writer
-            .write_i128::<LittleEndian>(value)
-            .map(|_| 16 + header_len)
+        write_numeric(writer, Kind::Int, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::UInt`] atom with the given value.
 #[inline]
-pub fn write_u8<W: WriteBytesExt>(mut writer: W, value: u8) -> std::io::Result<usize> {
-    let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 0)?;
-    writer
-        .write_u8(value)
-        .map(|_| std::mem::size_of::<u8>() + header_len)
+pub fn write_u8<W: Write + ?Sized>(writer: &mut W, value: u8) -> Result<usize, Error> {
+    write_numeric(writer, Kind::UInt, &[value])
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_u16<W: WriteBytesExt>(mut writer: W, value: u16) -> std::io::Result<usize> {
+pub fn write_u16<W: Write + ?Sized>(writer: &mut W, value: u16) -> Result<usize, Error> {
     if let Ok(value) = u8::try_from(value) {
         write_u8(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 1)?;
-        writer
-            .write_u16::<LittleEndian>(value)
-            .map(|_| std::mem::size_of::<u16>() + header_len)
+        write_numeric(writer, Kind::UInt, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_u24<W: WriteBytesExt>(mut writer: W, value: u32) -> std::io::Result<usize> {
+pub fn write_u24<W: Write + ?Sized>(writer: &mut W, value: u32) -> Result<usize, Error> {
     if let Ok(value) = u16::try_from(value) {
         write_u16(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 2)?;
-        writer
-            .write_u24::<LittleEndian>(value)
-            .map(|_| 3 + header_len)
+        write_numeric(writer, Kind::UInt, &value.to_le_bytes()[..3])
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_u32<W: WriteBytesExt>(mut writer: W, value: u32) -> std::io::Result<usize> {
+pub fn write_u32<W: Write + ?Sized>(writer: &mut W, value: u32) -> Result<usize, Error> {
     if value < 2_u32.pow(24) {
         write_u24(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 3)?;
-        writer
-            .write_u32::<LittleEndian>(value)
-            .map(|_| std::mem::size_of::<u32>() + header_len)
+        write_numeric(writer, Kind::UInt, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_u48<W: WriteBytesExt>(mut writer: W, value: u64) -> std::io::Result<usize> {
+pub fn write_u48<W: Write + ?Sized>(writer: &mut W, value: u64) -> Result<usize, Error> {
     if let Ok(value) = u32::try_from(value) {
         write_u32(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 5)?;
-        writer
-            .write_u48::<LittleEndian>(value)
-            .map(|_| 6 + header_len)
+        write_numeric(writer, Kind::UInt, &value.to_le_bytes()[..6])
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_u64<W: WriteBytesExt>(mut writer: W, value: u64) -> std::io::Result<usize> {
+pub fn write_u64<W: Write + ?Sized>(writer: &mut W, value: u64) -> Result<usize, Error> {
     if value < 2_u64.pow(48) {
         write_u48(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 7)?;
-        writer
-            .write_u64::<LittleEndian>(value)
-            .map(|_| std::mem::size_of::<u64>() + header_len)
+        write_numeric(writer, Kind::UInt, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Int`] atom with the given value. Will encode in a smaller format if possible.
 #[inline]
-pub fn write_u128<W: WriteBytesExt>(mut writer: W, value: u128) -> std::io::Result<usize> {
+pub fn write_u128<W: Write + ?Sized>(writer: &mut W, value: u128) -> Result<usize, Error> {
     if let Ok(value) = u64::try_from(value) {
         write_u64(writer, value)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::UInt, 15)?;
-        writer
-            .write_u128::<LittleEndian>(value)
-            .map(|_| std::mem::size_of::<u128>() + header_len)
+        write_numeric(writer, Kind::UInt, &value.to_le_bytes())
     }
 }
 
 /// Writes an [`Kind::Float`] atom with the given value.
 #[inline]
-#[allow(clippy::cast_possible_truncation, clippy::float_cmp)]
-pub fn write_f32<W: WriteBytesExt>(mut writer: W, value: f32) -> std::io::Result<usize> {
+#[allow(clippy::float_cmp)]
+pub fn write_f32<W: Write + ?Sized>(writer: &mut W, value: f32) -> Result<usize, Error> {
     let as_f16 = f16::from_f32(value);
     if as_f16.to_f32() == value {
-        let header_len = write_tiny_atom_header(
-            &mut writer,
-            Kind::Float,
-            std::mem::size_of::<u16>() as u8 - 1,
-        )?;
-        writer
-            .write_u16::<LittleEndian>(as_f16.to_bits())
-            .map(|_| std::mem::size_of::<u16>() + header_len)
+        write_numeric(writer, Kind::Float, &as_f16.to_bits().to_le_bytes())
     } else {
-        let header_len = write_tiny_atom_header(
-            &mut writer,
-            Kind::Float,
-            std::mem::size_of::<f32>() as u8 - 1,
-        )?;
-        writer
-            .write_f32::<LittleEndian>(value)
-            .map(|_| std::mem::size_of::<f32>() + header_len)
+        write_numeric(writer, Kind::Float, &value.to_le_bytes())
     }
 }
 
-fn read_f16<R: ReadBytesExt>(reader: &mut R) -> std::io::Result<f32> {
-    let value = f16::from_bits(reader.read_u16::<LittleEndian>()?);
-    Ok(value.to_f32())
-}
-
 /// Writes an [`Kind::Float`] atom with the given value.
 #[allow(clippy::cast_possible_truncation, clippy::float_cmp)]
 #[inline]
-pub fn write_f64<W: WriteBytesExt>(mut writer: W, value: f64) -> std::io::Result<usize> {
+pub fn write_f64<W: Write + ?Sized>(writer: &mut W, value: f64) -> Result<usize, Error> {
     let as_f32 = value as f32;
     if f64::from(as_f32) == value {
         write_f32(writer, as_f32)
     } else {
-        let header_len = write_tiny_atom_header(&mut writer, Kind::Float, 7)?;
-        writer
-            .write_f64::<LittleEndian>(value)
-            .map(|_| std::mem::size_of::<f64>() + header_len)
+        write_numeric(writer, Kind::Float, &value.to_le_bytes())
     }
 }
 
+/// Writes a [`Kind::Float`] atom containing NaN, using the smallest encoding
+/// possible. All NaN values written by this function are encoded identically.
+#[inline]
+pub(crate) fn write_canonical_nan<W: Write + ?Sized>(writer: &mut W) -> Result<usize, Error> {
+    write_numeric(writer, Kind::Float, &f16::NAN.to_bits().to_le_bytes())
+}
+
 /// Writes an [`Kind::Bytes`] atom with the bytes of the string.
 #[inline]
-pub fn write_str<W: WriteBytesExt>(writer: W, value: &str) -> std::io::Result<usize> {
+pub fn write_str<W: Write + ?Sized>(writer: &mut W, value: &str) -> Result<usize, Error> {
     write_bytes(writer, value.as_bytes())
 }
 
+/// Writes a [`Special::PackedArray`] containing `bytes`, which must contain
+/// elements of `element_type` stored in little endian.
+///
+/// Returns [`Error::InvalidPackedArray`] if the length of `bytes` is not a
+/// multiple of the element width.
+#[inline]
+pub fn write_packed_array<W: Write + ?Sized>(
+    writer: &mut W,
+    element_type: PackedType,
+    bytes: &[u8],
+) -> Result<usize, Error> {
+    if bytes.len() % element_type.width() != 0 {
+        return Err(Error::InvalidPackedArray);
+    }
+    let mut written = write_special(writer, Special::PackedArray)?;
+    written += write_u64(writer, element_type.code())?;
+    written += write_bytes(writer, bytes)?;
+    Ok(written)
+}
+
 /// Writes an [`Kind::Bytes`] atom with the given value.
 #[inline]
-pub fn write_bytes<W: WriteBytesExt>(mut writer: W, value: &[u8]) -> std::io::Result<usize> {
-    let header_len = write_atom_header(&mut writer, Kind::Bytes, value.len() as u64)?;
+pub fn write_bytes<W: Write + ?Sized>(writer: &mut W, value: &[u8]) -> Result<usize, Error> {
+    let header_len = write_atom_header(writer, Kind::Bytes, value.len() as u64)?;
     writer.write_all(value)?;
     Ok(value.len() + header_len)
 }
 
 /// An integer type that can safely convert between other number types using compile-time evaluation.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+///
+/// Integers are compared, ordered and hashed by their numeric values,
+/// regardless of the width they are stored with.
+#[derive(Clone, Copy, Debug)]
 pub struct Integer(pub(crate) InnerInteger);
 
+impl Integer {
+    /// Returns a representation of this integer that is ordered numerically.
+    /// Negative values are widened to `i128` and converted to their two's
+    /// complement representation, which preserves their relative order.
+    #[allow(clippy::cast_sign_loss, clippy::cast_lossless)]
+    const fn numeric_key(&self) -> (bool, u128) {
+        let signed = match self.0 {
+            InnerInteger::I8(value) => value as i128,
+            InnerInteger::I16(value) => value as i128,
+            InnerInteger::I32(value) => value as i128,
+            InnerInteger::I64(value) => value as i128,
+            InnerInteger::I128(value) => value,
+            InnerInteger::U8(value) => return (true, value as u128),
+            InnerInteger::U16(value) => return (true, value as u128),
+            InnerInteger::U32(value) => return (true, value as u128),
+            InnerInteger::U64(value) => return (true, value as u128),
+            InnerInteger::U128(value) => return (true, value),
+        };
+        (signed >= 0, signed as u128)
+    }
+}
+
+impl PartialEq for Integer {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.numeric_key() == other.numeric_key()
+    }
+}
+
+impl Eq for Integer {}
+
+impl PartialOrd for Integer {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Integer {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numeric_key().cmp(&other.numeric_key())
+    }
+}
+
+impl Hash for Integer {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.numeric_key().hash(state);
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub(crate) enum InnerInteger {
     /// An i8 value.
@@ -576,7 +796,7 @@ pub(crate) enum InnerInteger {
 }
 
 impl Display for Integer {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self.0 {
             InnerInteger::I8(value) => Display::fmt(value, f),
             InnerInteger::I16(value) => Display::fmt(value, f),
@@ -902,7 +1122,7 @@ impl Integer {
 
     /// Writes this value using the smallest form possible.
     #[inline]
-    pub fn write_to<W: WriteBytesExt>(&self, writer: W) -> std::io::Result<usize> {
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
         match self.0 {
             InnerInteger::I8(value) => write_i8(writer, value),
             InnerInteger::I16(value) => write_i16(writer, value),
@@ -917,33 +1137,111 @@ impl Integer {
         }
     }
 
+    /// Writes this value using the smallest form supported by
+    /// `compatibility`.
+    ///
+    /// With [`Compatibility::V7`] or later, small values are stored in the
+    /// atom header. See [`write_inline_int()`] and [`write_inline_uint()`].
+    #[inline]
+    pub fn write_to_with_compatibility<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        compatibility: Compatibility,
+    ) -> Result<usize, Error> {
+        if compatibility >= Compatibility::V7 {
+            match self.0 {
+                InnerInteger::I8(_)
+                | InnerInteger::I16(_)
+                | InnerInteger::I32(_)
+                | InnerInteger::I64(_)
+                | InnerInteger::I128(_) => {
+                    if let Ok(value) = self.as_i8() {
+                        if (MIN_INLINE_INT..=MAX_INLINE_INT).contains(&value) {
+                            return write_inline_int(writer, value);
+                        }
+                    }
+                }
+                _ => {
+                    if let Ok(value) = self.as_u8() {
+                        if value <= MAX_INLINE_UINT {
+                            return write_inline_uint(writer, value);
+                        }
+                    }
+                }
+            }
+        }
+        self.write_to(writer)
+    }
+
     /// Reads an integer based on the atom header (`kind` and `byte_len`).
-    /// `byte_len` should be the argument from the atom header directly.
+    /// `byte_len` should be the argument from the atom header, plus one.
+    ///
+    /// If the argument has [`INLINE_INTEGER`] set, the value is read from the
+    /// argument and no bytes are read from `reader`.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
     #[inline]
-    pub fn read_from<R: ReadBytesExt>(
+    pub fn read_from<'de, R: Reader<'de> + ?Sized>(
         kind: Kind,
         byte_len: usize,
         reader: &mut R,
     ) -> Result<Self, Error> {
+        if byte_len
+            .checked_sub(1)
+            .is_some_and(|arg| is_inline_integer(kind, arg as u64))
+        {
+            let value = (byte_len - 1) as u8 & 0b1111;
+            return Ok(Integer(if kind == Kind::Int {
+                // Shifting the value back down sign-extends it.
+                InnerInteger::I8((value << 4) as i8 >> 4)
+            } else {
+                InnerInteger::U8(value)
+            }));
+        }
+
         match kind {
             Kind::Int => match byte_len {
-                1 => Ok(InnerInteger::I8(reader.read_i8()?)),
-                2 => Ok(InnerInteger::I16(reader.read_i16::<LittleEndian>()?)),
-                3 => Ok(InnerInteger::I32(reader.read_i24::<LittleEndian>()?)),
-                4 => Ok(InnerInteger::I32(reader.read_i32::<LittleEndian>()?)),
-                6 => Ok(InnerInteger::I64(reader.read_i48::<LittleEndian>()?)),
-                8 => Ok(InnerInteger::I64(reader.read_i64::<LittleEndian>()?)),
-                16 => Ok(InnerInteger::I128(reader.read_i128::<LittleEndian>()?)),
+                1 => Ok(InnerInteger::I8(i8::from_le_bytes(read_bytes(reader, 1)?))),
+                2 => Ok(InnerInteger::I16(i16::from_le_bytes(read_bytes(
+                    reader, 2,
+                )?))),
+                // Shifting the value back down sign-extends it.
+                3 => Ok(InnerInteger::I32(
+                    i32::from_le_bytes(read_bytes(reader, 3)?) << 8 >> 8,
+                )),
+                4 => Ok(InnerInteger::I32(i32::from_le_bytes(read_bytes(
+                    reader, 4,
+                )?))),
+                6 => Ok(InnerInteger::I64(
+                    i64::from_le_bytes(read_bytes(reader, 6)?) << 16 >> 16,
+                )),
+                8 => Ok(InnerInteger::I64(i64::from_le_bytes(read_bytes(
+                    reader, 8,
+                )?))),
+                16 => Ok(InnerInteger::I128(i128::from_le_bytes(read_bytes(
+                    reader, 16,
+                )?))),
                 count => Err(Error::UnsupportedByteCount(kind, count)),
             },
             Kind::UInt => match byte_len {
-                1 => Ok(InnerInteger::U8(reader.read_u8()?)),
-                2 => Ok(InnerInteger::U16(reader.read_u16::<LittleEndian>()?)),
-                3 => Ok(InnerInteger::U32(reader.read_u24::<LittleEndian>()?)),
-                4 => Ok(InnerInteger::U32(reader.read_u32::<LittleEndian>()?)),
-                6 => Ok(InnerInteger::U64(reader.read_u48::<LittleEndian>()?)),
-                8 => Ok(InnerInteger::U64(reader.read_u64::<LittleEndian>()?)),
-                16 => Ok(InnerInteger::U128(reader.read_u128::<LittleEndian>()?)),
+                1 => Ok(InnerInteger::U8(u8::from_le_bytes(read_bytes(reader, 1)?))),
+                2 => Ok(InnerInteger::U16(u16::from_le_bytes(read_bytes(
+                    reader, 2,
+                )?))),
+                3 => Ok(InnerInteger::U32(u32::from_le_bytes(read_bytes(
+                    reader, 3,
+                )?))),
+                4 => Ok(InnerInteger::U32(u32::from_le_bytes(read_bytes(
+                    reader, 4,
+                )?))),
+                6 => Ok(InnerInteger::U64(u64::from_le_bytes(read_bytes(
+                    reader, 6,
+                )?))),
+                8 => Ok(InnerInteger::U64(u64::from_le_bytes(read_bytes(
+                    reader, 8,
+                )?))),
+                16 => Ok(InnerInteger::U128(u128::from_le_bytes(read_bytes(
+                    reader, 16,
+                )?))),
                 count => Err(Error::UnsupportedByteCount(kind, count)),
             },
             _ => Err(Error::UnexpectedKind(kind, Kind::Int)),
@@ -1042,14 +1340,27 @@ impl_from_unsigned_integer!(i64, i32, u32, I64);
 impl_from_unsigned_integer!(i128, i64, u64, I128);
 
 /// Reads an atom.
-#[allow(clippy::cast_possible_truncation)]
 #[inline]
 pub fn read_atom<'de, R: Reader<'de>>(
     reader: &mut R,
     remaining_budget: &mut usize,
     scratch: &mut Vec<u8>,
+) -> Result<Atom<'de>, Error> {
+    read_atom_checked(reader, remaining_budget, scratch, |_, _| Ok(()))
+}
+
+/// Reads an atom, invoking `check` with the atom's kind and argument before
+/// any of its contents are read.
+#[allow(clippy::cast_possible_truncation)]
+#[inline]
+pub(crate) fn read_atom_checked<'de, R: Reader<'de>>(
+    reader: &mut R,
+    remaining_budget: &mut usize,
+    scratch: &mut Vec<u8>,
+    check: impl FnOnce(Kind, u64) -> Result<(), Error>,
 ) -> Result<Atom<'de>, Error> {
     let (kind, arg) = read_atom_header(reader)?;
+    check(kind, arg)?;
     Ok(match kind {
         Kind::Sequence | Kind::Map | Kind::Symbol => Atom {
             kind,
@@ -1067,6 +1378,10 @@ pub fn read_atom<'de, R: Reader<'de>>(
                 Special::Named => Some(Nucleus::Named),
                 Special::DynamicMap => Some(Nucleus::DynamicMap),
                 Special::DynamicEnd => Some(Nucleus::DynamicEnd),
+                Special::DynamicSequence => Some(Nucleus::DynamicSequence),
+                Special::InternedBytes => Some(Nucleus::InternedBytes),
+                Special::BytesReference => Some(Nucleus::BytesReference),
+                Special::PackedArray => Some(Nucleus::PackedArray),
             },
         },
         Kind::Int | Kind::UInt => {
@@ -1100,16 +1415,294 @@ pub fn read_atom<'de, R: Reader<'de>>(
     })
 }
 
+/// Reads an atom like [`read_atom_checked()`], resolving
+/// [`Special::InternedBytes`] and [`Special::BytesReference`] into the
+/// [`Kind::Bytes`] atoms they refer to.
+///
+/// Each resolved reference counts against `remaining_budget`, as the bytes
+/// will usually be copied again by the value being deserialized.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn read_atom_resolved<'de, R: Reader<'de>>(
+    reader: &mut R,
+    remaining_budget: &mut usize,
+    scratch: &mut Vec<u8>,
+    interned: &mut InternedBytes<'de>,
+    mut check: impl FnMut(Kind, u64) -> Result<(), Error>,
+) -> Result<Atom<'de>, Error> {
+    let atom = read_atom_checked(reader, remaining_budget, scratch, &mut check)?;
+    match atom.nucleus {
+        Some(Nucleus::InternedBytes) => {
+            let atom = read_atom_checked(reader, remaining_budget, scratch, &mut check)?;
+            let Some(Nucleus::Bytes(bytes)) = &atom.nucleus else {
+                return Err(Error::UnexpectedKind(atom.kind, Kind::Bytes));
+            };
+            interned.push(bytes, scratch, remaining_budget)?;
+            Ok(atom)
+        }
+        Some(Nucleus::BytesReference) => {
+            let id = read_atom_checked(reader, remaining_budget, scratch, &mut check)?;
+            let (Kind::UInt, Some(Nucleus::Integer(id))) = (id.kind, id.nucleus) else {
+                return Err(Error::UnexpectedKind(id.kind, Kind::UInt));
+            };
+            let (length, bytes) = interned.get(id.as_u64()?, scratch)?;
+            update_budget(remaining_budget, length)?;
+            Ok(Atom {
+                kind: Kind::Bytes,
+                arg: length as u64,
+                nucleus: Some(Nucleus::Bytes(bytes)),
+            })
+        }
+        _ => Ok(atom),
+    }
+}
+
+/// Validates the two atoms following a [`Special::PackedArray`], returning the
+/// element type and the elements.
+pub(crate) fn packed_array_parts<'de>(
+    element_type: Atom<'de>,
+    elements: Atom<'de>,
+) -> Result<(PackedType, BufferedBytes<'de>), Error> {
+    let (Kind::UInt, Some(Nucleus::Integer(code))) = (element_type.kind, element_type.nucleus)
+    else {
+        return Err(Error::UnexpectedKind(element_type.kind, Kind::UInt));
+    };
+    let element_type = PackedType::from_code(code.as_u64()?)?;
+    let Some(Nucleus::Bytes(bytes)) = elements.nucleus else {
+        return Err(Error::UnexpectedKind(elements.kind, Kind::Bytes));
+    };
+    if elements.arg % element_type.width() as u64 == 0 {
+        Ok((element_type, bytes))
+    } else {
+        Err(Error::InvalidPackedArray)
+    }
+}
+
+/// Bytes values that have been interned using [`Special::InternedBytes`].
+#[derive(Debug, Default)]
+pub(crate) struct InternedBytes<'de> {
+    entries: Vec<InternedEntry<'de>>,
+    buffer: Vec<u8>,
+}
+
+#[derive(Debug)]
+enum InternedEntry<'de> {
+    Borrowed(&'de [u8]),
+    Buffered(Range<usize>),
+}
+
+impl<'de> InternedBytes<'de> {
+    /// Stores `bytes` as the next interned value. Bytes that can't be
+    /// borrowed from the input are copied, and count against
+    /// `remaining_budget`.
+    fn push(
+        &mut self,
+        bytes: &BufferedBytes<'de>,
+        scratch: &[u8],
+        remaining_budget: &mut usize,
+    ) -> Result<(), Error> {
+        let entry = match bytes {
+            BufferedBytes::Data(bytes) => InternedEntry::Borrowed(bytes),
+            BufferedBytes::Scratch => {
+                update_budget(remaining_budget, scratch.len())?;
+                let start = self.buffer.len();
+                self.buffer.extend_from_slice(scratch);
+                InternedEntry::Buffered(start..self.buffer.len())
+            }
+        };
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Returns the length and contents of the interned value `id`. Values
+    /// that can't be borrowed are copied into `scratch`.
+    fn get(&self, id: u64, scratch: &mut Vec<u8>) -> Result<(usize, BufferedBytes<'de>), Error> {
+        match usize::try_from(id).ok().and_then(|id| self.entries.get(id)) {
+            Some(InternedEntry::Borrowed(bytes)) => Ok((bytes.len(), BufferedBytes::Data(bytes))),
+            Some(InternedEntry::Buffered(range)) => {
+                scratch.clear();
+                scratch.extend_from_slice(&self.buffer[range.clone()]);
+                Ok((range.len(), BufferedBytes::Scratch))
+            }
+            None => Err(Error::UnknownBytesReference(id)),
+        }
+    }
+}
+
+/// Reads past exactly one complete value from `reader`, including all values
+/// contained within it, and returns the range of bytes it occupied.
+///
+/// The returned range is relative to the slice `reader` was created from.
+/// Values are not decoded, but symbols defined within the value are added to
+/// `symbols` so that later values referring to them can still be read.
+///
+/// ```rust
+/// use pot::de::SymbolList;
+/// use pot::format::{self, skip_value};
+/// use pot::reader::SliceReader;
+///
+/// let payload = pot::to_vec(&(vec![1, 2, 3], "hello")).unwrap();
+/// let mut reader = SliceReader::from(&payload[..]);
+/// format::read_header(&mut reader).unwrap();
+/// let (_, count) = format::read_atom_header(&mut reader).unwrap();
+/// assert_eq!(count, 2);
+///
+/// let mut symbols = SymbolList::new();
+/// let numbers = skip_value(&mut reader, &mut symbols).unwrap();
+/// let numbers: Vec<u8> = pot::from_slice(&[&payload[..4], &payload[numbers]].concat()).unwrap();
+/// assert_eq!(numbers, [1, 2, 3]);
+/// ```
+pub fn skip_value(
+    reader: &mut SliceReader<'_>,
+    symbols: &mut SymbolList<'_>,
+) -> Result<Range<usize>, Error> {
+    skip_value_with(reader, |symbol| match symbol {
+        SkippedSymbol::Defined { name, .. } => {
+            symbols.push(name);
+            Ok(())
+        }
+        SkippedSymbol::Reference { id, .. } if id < symbols.len() as u64 => Ok(()),
+        SkippedSymbol::Reference { id, .. } => Err(Error::UnknownSymbol(id)),
+        SkippedSymbol::InternedBytes { .. } => Ok(()),
+    })
+}
+
+/// A symbol encountered by [`skip_value_with()`].
+pub(crate) enum SkippedSymbol<'de> {
+    /// A new symbol was defined by the atom at `offset`.
+    Defined { offset: usize, name: &'de str },
+    /// The atom at `offset` refers to a previously defined symbol.
+    Reference { offset: usize, id: u64 },
+    /// The [`Special::InternedBytes`] atom at `offset` interned `bytes`.
+    InternedBytes { offset: usize, bytes: &'de [u8] },
+}
+
+/// Reads past exactly one complete value, invoking `on_symbol` for each
+/// symbol atom and interned bytes value encountered. Offsets are relative to
+/// the slice `reader` was created from.
+#[allow(clippy::cast_possible_truncation)]
+pub(crate) fn skip_value_with<'de>(
+    reader: &mut SliceReader<'de>,
+    mut on_symbol: impl FnMut(SkippedSymbol<'de>) -> Result<(), Error>,
+) -> Result<Range<usize>, Error> {
+    enum Remaining {
+        Values(u64),
+        UntilEnd,
+    }
+
+    let start = reader.position();
+    let mut reader = StrictSliceReader(reader);
+    let mut scratch = Vec::new();
+    let mut containers = vec![Remaining::Values(1)];
+    // The offset of the `Special::InternedBytes` atom preceding the next atom.
+    let mut pending_intern = None;
+    while let Some(container) = containers.last_mut() {
+        if matches!(container, Remaining::Values(0)) {
+            containers.pop();
+            continue;
+        }
+
+        let offset = reader.0.position();
+        let (kind, arg) = read_atom_header(&mut reader)?;
+        let interned_at = pending_intern.take();
+        match container {
+            Remaining::Values(remaining) => *remaining -= 1,
+            Remaining::UntilEnd => {
+                if kind == Kind::Special && arg == Special::DynamicEnd as u64 {
+                    containers.pop();
+                    continue;
+                }
+            }
+        }
+
+        match kind {
+            Kind::Special => match Special::try_from(arg)? {
+                Special::None | Special::Unit | Special::False | Special::True => {}
+                // A named value is a symbol followed by a value, and packed
+                // arrays are followed by the element type and the elements.
+                Special::Named | Special::PackedArray => containers.push(Remaining::Values(2)),
+                // Interned bytes are followed by the bytes, and references
+                // are followed by the id of the bytes.
+                Special::InternedBytes => {
+                    pending_intern = Some(offset);
+                    containers.push(Remaining::Values(1));
+                }
+                Special::BytesReference => containers.push(Remaining::Values(1)),
+                Special::DynamicMap | Special::DynamicSequence => {
+                    containers.push(Remaining::UntilEnd);
+                }
+                Special::DynamicEnd => {
+                    return Err(serde::de::Error::custom("unexpected dynamic end"))
+                }
+            },
+            Kind::Int | Kind::UInt | Kind::Float => {
+                reader.buffered_read_bytes(numeric_length(kind, arg) as usize, &mut scratch)?;
+            }
+            Kind::Bytes => {
+                let bytes = reader.buffered_read_bytes(arg as usize, &mut scratch)?;
+                if let Some(offset) = interned_at {
+                    let BufferedBytes::Data(bytes) = bytes else {
+                        unreachable!("slice readers always borrow")
+                    };
+                    on_symbol(SkippedSymbol::InternedBytes { offset, bytes })?;
+                }
+            }
+            Kind::Sequence => containers.push(Remaining::Values(arg)),
+            Kind::Map => containers.push(Remaining::Values(
+                arg.checked_mul(2).ok_or(Error::InvalidAtomHeader)?,
+            )),
+            Kind::Symbol => {
+                let is_id = arg & 0b1 != 0;
+                let arg = arg >> 1;
+                if is_id {
+                    on_symbol(SkippedSymbol::Reference { offset, id: arg })?;
+                } else {
+                    let BufferedBytes::Data(name) =
+                        reader.buffered_read_bytes(arg as usize, &mut scratch)?
+                    else {
+                        unreachable!("slice readers always borrow")
+                    };
+                    on_symbol(SkippedSymbol::Defined {
+                        offset,
+                        name: core::str::from_utf8(name)?,
+                    })?;
+                }
+            }
+        }
+    }
+
+    Ok(start..reader.0.position())
+}
+
 #[inline]
 pub(crate) const fn in_memory_int_size(encoded_length: usize) -> usize {
     // Some integers are stored more compact than we can represent them in memory
     match encoded_length {
         3 => 4,
         6 => 8,
+        // Inline integers are read as a single byte.
+        17..=32 => 1,
         other => other,
     }
 }
 
+/// Returns true if an atom of `kind` with `arg` is an integer whose value is
+/// stored in the atom header.
+#[inline]
+pub(crate) const fn is_inline_integer(kind: Kind, arg: u64) -> bool {
+    matches!(kind, Kind::Int | Kind::UInt) && arg & INLINE_INTEGER != 0 && arg < 0b10_0000
+}
+
+/// Returns the number of bytes that follow the header of a numeric atom of
+/// `kind` with `arg`.
+#[inline]
+pub(crate) const fn numeric_length(kind: Kind, arg: u64) -> u64 {
+    if is_inline_integer(kind, arg) {
+        0
+    } else {
+        arg.saturating_add(1)
+    }
+}
+
 #[inline]
 pub(crate) fn update_budget(budget: &mut usize, read_amount: usize) -> Result<(), Error> {
     if let Some(remaining) = budget.checked_sub(read_amount) {
@@ -1132,9 +1725,58 @@ pub struct Atom<'de> {
 }
 
 /// A floating point number that can safely convert between other number types using compile-time evaluation when possible.
-#[derive(Debug, Copy, Clone, PartialEq)]
+///
+/// Floats are compared, ordered and hashed by their values, regardless of the
+/// width they are stored with. To provide a total ordering, `0.0` and `-0.0`
+/// are considered equal, all NaN values are considered equal to each other,
+/// and NaN is ordered after all other values.
+#[derive(Debug, Copy, Clone)]
 pub struct Float(pub(crate) InnerFloat);
 
+impl PartialEq for Float {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Float {}
+
+impl PartialOrd for Float {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Float {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (left, right) = (self.as_f64(), other.as_f64());
+        match (left.is_nan(), right.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => left.partial_cmp(&right).unwrap_or(Ordering::Equal),
+        }
+    }
+}
+
+impl Hash for Float {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let value = self.as_f64();
+        let bits = if value.is_nan() {
+            f64::NAN.to_bits()
+        } else if value == 0. {
+            0_f64.to_bits()
+        } else {
+            value.to_bits()
+        };
+        bits.hash(state);
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub(crate) enum InnerFloat {
     /// An f64 value.
@@ -1157,20 +1799,8 @@ impl From<f64> for Float {
     }
 }
 
-impl PartialEq for InnerFloat {
-    #[inline]
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (InnerFloat::F64(left), InnerFloat::F64(right)) => left == right,
-            (InnerFloat::F32(left), InnerFloat::F32(right)) => left == right,
-            (InnerFloat::F64(left), InnerFloat::F32(right)) => *left == f64::from(*right),
-            (InnerFloat::F32(left), InnerFloat::F64(right)) => f64::from(*left) == *right,
-        }
-    }
-}
-
 impl Display for Float {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match &self.0 {
             InnerFloat::F32(value) => Display::fmt(value, f),
             InnerFloat::F64(value) => Display::fmt(value, f),
@@ -1184,8 +1814,8 @@ impl Float {
     #[inline]
     pub fn is_zero(&self) -> bool {
         match self.0 {
-            InnerFloat::F32(value) => value.abs() <= f32::EPSILON,
-            InnerFloat::F64(value) => value.abs() <= f64::EPSILON,
+            InnerFloat::F32(value) => (-f32::EPSILON..=f32::EPSILON).contains(&value),
+            InnerFloat::F64(value) => (-f64::EPSILON..=f64::EPSILON).contains(&value),
         }
     }
 
@@ -1217,21 +1847,25 @@ impl Float {
     }
 
     /// Returns this number as an [`Integer`], if the stored value has no fractional part.
-    #[allow(clippy::cast_possible_truncation)]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
     #[inline]
     pub fn as_integer(&self) -> Result<Integer, Error> {
         match self.0 {
             InnerFloat::F64(value) => {
-                if value.fract().abs() < f64::EPSILON {
+                let integer = value as i64;
+                // `f64::fract()` requires `std`, so the fraction is measured
+                // against the truncated value instead.
+                if (-f64::EPSILON..f64::EPSILON).contains(&(value - integer as f64)) {
                     // no fraction, safe to convert
-                    Ok(Integer::from(value as i64))
+                    Ok(Integer::from(integer))
                 } else {
                     Err(Error::ImpreciseCastWouldLoseData)
                 }
             }
             InnerFloat::F32(value) => {
-                if value.fract().abs() < f32::EPSILON {
-                    Ok(Integer::from(value as i32))
+                let integer = value as i32;
+                if (-f32::EPSILON..f32::EPSILON).contains(&(value - integer as f32)) {
+                    Ok(Integer::from(integer))
                 } else {
                     Err(Error::ImpreciseCastWouldLoseData)
                 }
@@ -1241,7 +1875,7 @@ impl Float {
 
     /// Writes this value using the smallest form possible.
     #[inline]
-    pub fn write_to<W: WriteBytesExt>(&self, writer: W) -> std::io::Result<usize> {
+    pub fn write_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<usize, Error> {
         match self.0 {
             InnerFloat::F64(float) => write_f64(writer, float),
             InnerFloat::F32(float) => write_f32(writer, float),
@@ -1251,16 +1885,18 @@ impl Float {
     /// Reads a floating point number given the atom `kind` and `byte_len`.
     /// `byte_len` should be the exact argument from the atom header.
     #[inline]
-    pub fn read_from<R: ReadBytesExt>(
+    pub fn read_from<'de, R: Reader<'de> + ?Sized>(
         kind: Kind,
         byte_len: usize,
         reader: &mut R,
     ) -> Result<Self, Error> {
         if Kind::Float == kind {
             match byte_len {
-                2 => Ok(Self::from(read_f16(reader)?)),
-                4 => Ok(Self::from(reader.read_f32::<LittleEndian>()?)),
-                8 => Ok(Self::from(reader.read_f64::<LittleEndian>()?)),
+                2 => Ok(Self::from(
+                    f16::from_le_bytes(read_bytes(reader, 2)?).to_f32(),
+                )),
+                4 => Ok(Self::from(f32::from_le_bytes(read_bytes(reader, 4)?))),
+                8 => Ok(Self::from(f64::from_le_bytes(read_bytes(reader, 8)?))),
                 count => Err(Error::UnsupportedByteCount(Kind::Float, count)),
             }
         } else {
@@ -1286,8 +1922,381 @@ pub enum Nucleus<'de> {
     Named,
     /// A marker denoting a map with unknown length is next in the file.
     DynamicMap,
-    /// A marker denoting the end of a map with unknown length.
+    /// A marker denoting the end of a map or sequence with unknown length.
     DynamicEnd,
+    /// A marker denoting a sequence with unknown length is next in the file.
+    DynamicSequence,
+    /// A marker denoting that the next atom contains bytes that may be
+    /// referred to later.
+    InternedBytes,
+    /// A marker denoting that the next atom contains the id of previously
+    /// interned bytes.
+    BytesReference,
+    /// A marker denoting that the next two atoms contain the element type
+    /// and elements of a packed array.
+    PackedArray,
+}
+
+/// A low-level writer that produces valid Pot payloads.
+///
+/// Unlike the free functions in this module, this type keeps track of the
+/// containers that have been started, and returns an error if a container
+/// receives more or fewer values than it declared. Symbols are assigned ids
+/// automatically: the first time a symbol is written its full name is
+/// emitted, and subsequent writes refer to it by id.
+///
+/// ```rust
+/// use pot::format::Writer;
+///
+/// let mut writer = Writer::new(Vec::new()).unwrap();
+/// writer.begin_map(2).unwrap();
+/// writer.symbol("id").unwrap();
+/// writer.write_u64(42).unwrap();
+/// writer.symbol("tags").unwrap();
+/// writer.begin_sequence(1).unwrap();
+/// writer.write_str("admin").unwrap();
+/// writer.end().unwrap();
+/// writer.end().unwrap();
+/// let payload = writer.finish().unwrap();
+///
+/// let value: pot::Value<'_> = pot::from_slice(&payload).unwrap();
+/// assert_eq!(value.to_string(), "{id: 42, tags: [admin]}");
+/// ```
+pub struct Writer<W: Write> {
+    output: W,
+    compatibility: Compatibility,
+    symbols: SymbolMap,
+    containers: Vec<WriterContainer>,
+    root_written: bool,
+}
+
+impl<W: Write> Debug for Writer<W> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Writer")
+            .field("compatibility", &self.compatibility)
+            .field("symbols", &self.symbols)
+            .field("containers", &self.containers)
+            .field("root_written", &self.root_written)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug)]
+enum WriterContainer {
+    Sequence {
+        expected: u64,
+        written: u64,
+    },
+    /// A map of a known length. `expected` and `written` count keys and values
+    /// individually.
+    Map {
+        expected: u64,
+        written: u64,
+    },
+    DynamicMap {
+        expecting_key: bool,
+    },
+    DynamicSequence,
+    Named,
+}
+
+impl WriterContainer {
+    /// Returns [`Error::ContainerLengthMismatch`] if this container declared a
+    /// length other than `written` values.
+    fn check_length(&self, written: u64) -> Result<(), Error> {
+        match self {
+            WriterContainer::Sequence { expected, .. } if *expected != written => {
+                Err(Error::ContainerLengthMismatch {
+                    expected: *expected,
+                    written,
+                })
+            }
+            WriterContainer::Map { expected, .. } if *expected != written => {
+                // Report entries rather than values, counting a key without a
+                // value as an entry.
+                Err(Error::ContainerLengthMismatch {
+                    expected: *expected / 2,
+                    written: written / 2 + written % 2,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<W: Write> Writer<W> {
+    /// Returns a new writer that writes a Pot header and a single value into
+    /// `output`.
+    #[inline]
+    pub fn new(output: W) -> Result<Self, Error> {
+        Self::new_with_compatibility(output, Compatibility::default())
+    }
+
+    /// Returns a new writer that writes a Pot header and a single value into
+    /// `output` using the format specified by `compatibility`.
+    #[inline]
+    pub fn new_with_compatibility(
+        mut output: W,
+        compatibility: Compatibility,
+    ) -> Result<Self, Error> {
+        write_header(&mut output, compatibility.header_version())?;
+        Ok(Self {
+            output,
+            compatibility,
+            symbols: SymbolMap::new(),
+            containers: Vec::new(),
+            root_written: false,
+        })
+    }
+
+    /// Begins a sequence of `len` values. Each value written after this call
+    /// is an element of the sequence until [`end()`](Self::end) is called.
+    pub fn begin_sequence(&mut self, len: usize) -> Result<(), Error> {
+        self.begin_value()?;
+        write_atom_header(&mut self.output, Kind::Sequence, len as u64)?;
+        self.containers.push(WriterContainer::Sequence {
+            expected: len as u64,
+            written: 0,
+        });
+        Ok(())
+    }
+
+    /// Begins a sequence with an unknown number of values. Each value written
+    /// after this call is an element of the sequence until
+    /// [`end()`](Self::end) is called.
+    ///
+    /// Returns [`Error::SequenceSizeMustBeKnown`] if this writer was created
+    /// with a [`Compatibility`] older than [`Compatibility::V5`].
+    pub fn begin_dynamic_sequence(&mut self) -> Result<(), Error> {
+        if self.compatibility < Compatibility::V5 {
+            return Err(Error::SequenceSizeMustBeKnown);
+        }
+        self.begin_value()?;
+        write_special(&mut self.output, Special::DynamicSequence)?;
+        self.containers.push(WriterContainer::DynamicSequence);
+        Ok(())
+    }
+
+    /// Begins a map of `len` entries. Values written after this call
+    /// alternate between keys and values until [`end()`](Self::end) is called.
+    ///
+    /// Returns [`Error::ContainerTooLong`] if the number of keys and values in
+    /// `len` entries cannot be represented.
+    pub fn begin_map(&mut self, len: usize) -> Result<(), Error> {
+        let expected = (len as u64).checked_mul(2).ok_or(Error::ContainerTooLong)?;
+        self.begin_value()?;
+        write_atom_header(&mut self.output, Kind::Map, len as u64)?;
+        self.containers.push(WriterContainer::Map {
+            expected,
+            written: 0,
+        });
+        Ok(())
+    }
+
+    /// Begins a map with an unknown number of entries. Values written after
+    /// this call alternate between keys and values until
+    /// [`end()`](Self::end) is called.
+    pub fn begin_dynamic_map(&mut self) -> Result<(), Error> {
+        self.begin_value()?;
+        write_special(&mut self.output, Special::DynamicMap)?;
+        self.containers.push(WriterContainer::DynamicMap {
+            expecting_key: true,
+        });
+        Ok(())
+    }
+
+    /// Ends the most recently begun container.
+    ///
+    /// Returns [`Error::ContainerLengthMismatch`] if a sequence or map
+    /// received fewer values than it declared.
+    pub fn end(&mut self) -> Result<(), Error> {
+        match self.containers.last() {
+            Some(
+                container @ (WriterContainer::Sequence { written, .. }
+                | WriterContainer::Map { written, .. }),
+            ) => {
+                container.check_length(*written)?;
+                self.containers.pop();
+                self.value_written();
+                Ok(())
+            }
+            Some(
+                WriterContainer::DynamicMap {
+                    expecting_key: true,
+                }
+                | WriterContainer::DynamicSequence,
+            ) => {
+                write_special(&mut self.output, Special::DynamicEnd)?;
+                self.containers.pop();
+                self.value_written();
+                Ok(())
+            }
+            Some(
+                WriterContainer::DynamicMap {
+                    expecting_key: false,
+                }
+                | WriterContainer::Named,
+            )
+            | None => Err(Error::UnbalancedContainers),
+        }
+    }
+
+    /// Writes a named value. The next value written is associated with
+    /// `name`.
+    pub fn named(&mut self, name: &str) -> Result<(), Error> {
+        self.begin_value()?;
+        write_named(&mut self.output)?;
+        self.write_symbol(name)?;
+        self.containers.push(WriterContainer::Named);
+        Ok(())
+    }
+
+    /// Writes `symbol` as a value. Symbols are written in full the first time
+    /// they are encountered, and are referred to by id afterwards.
+    pub fn symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        self.begin_value()?;
+        self.write_symbol(symbol)?;
+        self.value_written();
+        Ok(())
+    }
+
+    fn write_symbol(&mut self, symbol: &str) -> Result<(), Error> {
+        let registered = self.symbols.find_entry_by_str(symbol);
+        if registered.new {
+            write_atom_header(&mut self.output, Kind::Symbol, (symbol.len() as u64) << 1)?;
+            self.output.write_all(symbol.as_bytes())?;
+        } else {
+            write_atom_header(
+                &mut self.output,
+                Kind::Symbol,
+                u64::from((registered.id << 1) | 1),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Writes a `None` value.
+    pub fn write_none(&mut self) -> Result<(), Error> {
+        self.write_with(|output| write_none(output))
+    }
+
+    /// Writes a unit value.
+    pub fn write_unit(&mut self) -> Result<(), Error> {
+        self.write_with(|output| write_unit(output))
+    }
+
+    /// Writes a boolean value.
+    pub fn write_bool(&mut self, value: bool) -> Result<(), Error> {
+        self.write_with(|output| write_bool(output, value))
+    }
+
+    /// Writes an integer value using the smallest form possible.
+    pub fn write_integer(&mut self, value: impl Into<Integer>) -> Result<(), Error> {
+        let value = value.into();
+        let compatibility = self.compatibility;
+        self.write_with(|output| value.write_to_with_compatibility(output, compatibility))
+    }
+
+    /// Writes an `i64` value using the smallest form possible.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn write_i64(&mut self, value: i64) -> Result<(), Error> {
+        let inline = self.compatibility >= Compatibility::V7
+            && (i64::from(MIN_INLINE_INT)..=i64::from(MAX_INLINE_INT)).contains(&value);
+        self.write_with(|output| {
+            if inline {
+                write_inline_int(output, value as i8)
+            } else {
+                write_i64(output, value)
+            }
+        })
+    }
+
+    /// Writes a `u64` value using the smallest form possible.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn write_u64(&mut self, value: u64) -> Result<(), Error> {
+        let inline = self.compatibility >= Compatibility::V7 && value <= u64::from(MAX_INLINE_UINT);
+        self.write_with(|output| {
+            if inline {
+                write_inline_uint(output, value as u8)
+            } else {
+                write_u64(output, value)
+            }
+        })
+    }
+
+    /// Writes a floating point value using the smallest form possible.
+    pub fn write_float(&mut self, value: impl Into<Float>) -> Result<(), Error> {
+        let value = value.into();
+        self.write_with(|output| value.write_to(output))
+    }
+
+    /// Writes a string value.
+    pub fn write_str(&mut self, value: &str) -> Result<(), Error> {
+        self.write_with(|output| write_str(output, value))
+    }
+
+    /// Writes a byte value.
+    pub fn write_bytes(&mut self, value: &[u8]) -> Result<(), Error> {
+        self.write_with(|output| write_bytes(output, value))
+    }
+
+    fn write_with(
+        &mut self,
+        write: impl FnOnce(&mut W) -> Result<usize, Error>,
+    ) -> Result<(), Error> {
+        self.begin_value()?;
+        write(&mut self.output)?;
+        self.value_written();
+        Ok(())
+    }
+
+    /// Verifies that another value can be written.
+    fn begin_value(&mut self) -> Result<(), Error> {
+        match self.containers.last() {
+            Some(
+                container @ (WriterContainer::Sequence { expected, written }
+                | WriterContainer::Map { expected, written }),
+            ) if expected == written => container.check_length(*written + 1),
+            None if self.root_written => Err(Error::UnbalancedContainers),
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that a complete value has been written.
+    fn value_written(&mut self) {
+        loop {
+            match self.containers.last_mut() {
+                Some(
+                    WriterContainer::Sequence { written, .. }
+                    | WriterContainer::Map { written, .. },
+                ) => {
+                    *written += 1;
+                }
+                Some(WriterContainer::DynamicMap { expecting_key }) => {
+                    *expecting_key = !*expecting_key;
+                }
+                Some(WriterContainer::DynamicSequence) => {}
+                Some(WriterContainer::Named) => {
+                    // The named value is complete, which completes a value in
+                    // the parent container.
+                    self.containers.pop();
+                    continue;
+                }
+                None => self.root_written = true,
+            }
+            break;
+        }
+    }
+
+    /// Verifies that a complete value has been written, and returns the
+    /// underlying output.
+    pub fn finish(self) -> Result<W, Error> {
+        if self.root_written && self.containers.is_empty() {
+            Ok(self.output)
+        } else {
+            Err(Error::UnbalancedContainers)
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,154 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::format::{numeric_length, read_atom_header, read_header, Kind, Special};
+use crate::Error;
+
+/// Tracks the structure of a payload as its bytes arrive, locating the end of
+/// a complete value without decoding it.
+///
+/// The framer never looks past the end of the value, which allows the bytes
+/// of a value to be read from a stream without consuming any bytes that
+/// follow it.
+#[derive(Debug)]
+pub(crate) struct Framer {
+    containers: Vec<Remaining>,
+    offset: usize,
+    header_read: bool,
+    known_symbols: u64,
+}
+
+#[derive(Debug)]
+enum Remaining {
+    Values(u64),
+    UntilEnd,
+}
+
+/// The result of [`Framer::advance()`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Progress {
+    /// The value is complete, ending at this offset.
+    Complete(usize),
+    /// At least this many more bytes are needed before the framer can make
+    /// progress.
+    NeedMore(usize),
+}
+
+impl Framer {
+    /// Returns a framer for a payload that begins with a Pot header, which
+    /// may refer to `known_symbols` symbols defined by earlier payloads.
+    pub(crate) fn with_known_symbols(known_symbols: usize) -> Self {
+        Self {
+            containers: vec![Remaining::Values(1)],
+            offset: 0,
+            header_read: false,
+            known_symbols: known_symbols as u64,
+        }
+    }
+
+    /// Advances through the complete atoms in `buffer`, which must contain all
+    /// bytes previously passed to this function.
+    #[allow(clippy::cast_possible_truncation)]
+    pub(crate) fn advance(&mut self, buffer: &[u8]) -> Result<Progress, Error> {
+        if !self.header_read {
+            let Some(mut header) = buffer.get(..4) else {
+                return Ok(Progress::NeedMore(4 - buffer.len()));
+            };
+            read_header(&mut header)?;
+            self.header_read = true;
+            self.offset = 4;
+        }
+
+        while let Some(container) = self.containers.last_mut() {
+            if matches!(container, Remaining::Values(0)) {
+                self.containers.pop();
+                continue;
+            }
+
+            let available = &buffer[self.offset..];
+            let Some(header_length) = atom_header_length(available) else {
+                return Ok(Progress::NeedMore(1));
+            };
+            let (kind, arg) = read_atom_header(&mut &available[..header_length])?;
+            let payload_length = match kind {
+                Kind::Int | Kind::UInt | Kind::Float => numeric_length(kind, arg),
+                Kind::Bytes => arg,
+                Kind::Symbol if arg & 0b1 == 0 => arg >> 1,
+                Kind::Special | Kind::Sequence | Kind::Map | Kind::Symbol => 0,
+            };
+            let atom_length = usize::try_from(payload_length)
+                .ok()
+                .and_then(|length| length.checked_add(header_length))
+                .ok_or(Error::InvalidAtomHeader)?;
+            if available.len() < atom_length {
+                return Ok(Progress::NeedMore(atom_length - available.len()));
+            }
+            self.offset += atom_length;
+
+            match container {
+                Remaining::Values(remaining) => *remaining -= 1,
+                Remaining::UntilEnd => {
+                    if kind == Kind::Special && arg == Special::DynamicEnd as u64 {
+                        self.containers.pop();
+                        continue;
+                    }
+                }
+            }
+
+            match kind {
+                Kind::Special => match Special::try_from(arg)? {
+                    Special::None | Special::Unit | Special::False | Special::True => {}
+                    // A named value is a symbol followed by a value, and
+                    // packed arrays are followed by the element type and the
+                    // elements.
+                    Special::Named | Special::PackedArray => {
+                        self.containers.push(Remaining::Values(2));
+                    }
+                    // Interned bytes are followed by the bytes, and
+                    // references are followed by the id of the bytes.
+                    Special::InternedBytes | Special::BytesReference => {
+                        self.containers.push(Remaining::Values(1));
+                    }
+                    Special::DynamicMap | Special::DynamicSequence => {
+                        self.containers.push(Remaining::UntilEnd);
+                    }
+                    Special::DynamicEnd => {
+                        return Err(serde::de::Error::custom("unexpected dynamic end"))
+                    }
+                },
+                Kind::Sequence => self.containers.push(Remaining::Values(arg)),
+                Kind::Map => self.containers.push(Remaining::Values(
+                    arg.checked_mul(2).ok_or(Error::InvalidAtomHeader)?,
+                )),
+                Kind::Symbol if arg & 0b1 == 0 => self.known_symbols += 1,
+                Kind::Symbol if arg >> 1 >= self.known_symbols => {
+                    return Err(Error::UnknownSymbol(arg >> 1))
+                }
+                Kind::Int | Kind::UInt | Kind::Float | Kind::Symbol | Kind::Bytes => {}
+            }
+        }
+
+        Ok(Progress::Complete(self.offset))
+    }
+}
+
+/// Returns the length of the atom header at the start of `bytes`, or `None`
+/// if `bytes` does not contain the entire header.
+fn atom_header_length(bytes: &[u8]) -> Option<usize> {
+    let first_byte = bytes.first()?;
+    // Integers with the continuation bit set store their value in the header.
+    if first_byte & 0b10000 == 0
+        || matches!(Kind::from_u8(first_byte >> 5), Ok(Kind::Int | Kind::UInt))
+    {
+        return Some(1);
+    }
+
+    // The argument continues for as long as the high bit of each byte is set,
+    // up to a maximum of 9 additional bytes.
+    bytes[1..]
+        .iter()
+        .take(9)
+        .enumerate()
+        .find(|(index, byte)| *byte & 0x80 == 0 || *index == 8)
+        .map(|(index, _)| index + 2)
+}
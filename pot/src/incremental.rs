@@ -0,0 +1,170 @@
+use alloc::vec::Vec;
+
+use serde::de::DeserializeOwned;
+
+use crate::de::SymbolMap;
+use crate::framing::{Framer, Progress};
+use crate::stream::deserialize_payload;
+use crate::{Config, Error, Result};
+
+/// A push-style decoder that is fed bytes as they arrive.
+///
+/// Each call to [`feed()`](Self::feed) resumes where the previous call
+/// stopped: the container stack, the number of symbols defined and the state
+/// of any partially received atom are kept between calls, so bytes are never
+/// parsed twice while a payload is incomplete. Once a complete payload has been
+/// received, it is deserialized.
+///
+/// A truncated payload is reported as [`Status::NeedMore`], while invalid data
+/// is reported as an error as soon as it is received. After an error has been
+/// returned, the decoder should not be fed any more bytes.
+///
+/// Payloads serialized with [`Compatibility::Full`](crate::Compatibility::Full)
+/// that contain enum variants without associated data cannot be located
+/// reliably, and should be serialized with
+/// [`Compatibility::V4`](crate::Compatibility::V4) or later.
+///
+/// ```rust
+/// use pot::incremental::{Decoder, Status};
+///
+/// let payload = pot::to_vec(&(1_u32, "hello")).unwrap();
+/// let mut decoder = Decoder::new();
+/// let (first, second) = payload.split_at(payload.len() - 2);
+/// assert_eq!(
+///     decoder.feed::<(u32, String)>(first).unwrap(),
+///     Status::NeedMore(2)
+/// );
+/// assert_eq!(
+///     decoder.feed::<(u32, String)>(second).unwrap(),
+///     Status::Complete((1, String::from("hello")))
+/// );
+/// ```
+#[derive(Debug)]
+pub struct Decoder {
+    framer: Option<Framer>,
+    buffer: Vec<u8>,
+    symbols: Option<SymbolMap>,
+    config: Config,
+}
+
+/// The result of feeding bytes to a [`Decoder`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Status<T> {
+    /// At least this many more bytes are needed before the payload can be
+    /// complete.
+    NeedMore(usize),
+    /// A complete payload was received and deserialized.
+    Complete(T),
+}
+
+impl Default for Decoder {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Decoder {
+    /// Returns a new decoder using the default [`Config`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_config(&Config::default())
+    }
+
+    /// Returns a new decoder using the options from `config`.
+    ///
+    /// The [allocation budget](Config::allocation_budget) applies to each
+    /// payload individually, including the bytes buffered for it.
+    #[must_use]
+    pub fn with_config(config: &Config) -> Self {
+        Self {
+            framer: None,
+            buffer: Vec::new(),
+            symbols: None,
+            config: config.clone(),
+        }
+    }
+
+    /// Uses `symbols` as a persistent symbol map shared by every payload
+    /// decoded and returns self.
+    ///
+    /// This should only be used with data generated by using a persistent
+    /// [`ser::SymbolMap`](crate::ser::SymbolMap).
+    #[must_use]
+    pub fn with_symbol_map(mut self, symbols: SymbolMap) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    /// Returns the persistent symbol map, if one is being used.
+    #[must_use]
+    pub const fn symbol_map(&self) -> Option<&SymbolMap> {
+        self.symbols.as_ref()
+    }
+
+    /// Returns the persistent symbol map, if one was being used.
+    #[must_use]
+    pub fn into_symbol_map(self) -> Option<SymbolMap> {
+        self.symbols
+    }
+
+    /// Returns the number of bytes that have been fed but not yet returned as
+    /// part of a complete value.
+    #[must_use]
+    pub fn buffered_len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Appends `bytes` to the current payload and returns the decoded value
+    /// if it is now complete.
+    ///
+    /// `bytes` may extend past the end of the current payload. The extra
+    /// bytes are kept as the beginning of the next payload, and calling this
+    /// function with an empty slice continues decoding them.
+    pub fn feed<T>(&mut self, bytes: &[u8]) -> Result<Status<T>>
+    where
+        T: DeserializeOwned,
+    {
+        self.buffer.extend_from_slice(bytes);
+        let result = self.decode();
+        if result.is_err() {
+            self.framer = None;
+            self.buffer.clear();
+        }
+        result
+    }
+
+    fn decode<T>(&mut self) -> Result<Status<T>>
+    where
+        T: DeserializeOwned,
+    {
+        let known_symbols = self.symbols.as_ref().map_or(0, SymbolMap::len);
+        let framer = self
+            .framer
+            .get_or_insert_with(|| Framer::with_known_symbols(known_symbols));
+        match framer.advance(&self.buffer)? {
+            Progress::NeedMore(needed) => {
+                let length = self.buffer.len().saturating_add(needed);
+                if length > self.config.allocation_budget {
+                    return Err(Error::TooManyBytesRead);
+                }
+                self.config.limits.check_input_length(length)?;
+                Ok(Status::NeedMore(needed))
+            }
+            Progress::Complete(length) => {
+                self.framer = None;
+                if length > self.config.allocation_budget {
+                    return Err(Error::TooManyBytesRead);
+                }
+                self.config.limits.check_input_length(length)?;
+                let value = deserialize_payload(
+                    &self.buffer[..length],
+                    self.symbols.as_mut(),
+                    &self.config,
+                )?;
+                self.buffer.drain(..length);
+                Ok(Status::Complete(value))
+            }
+        }
+    }
+}
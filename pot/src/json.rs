@@ -0,0 +1,802 @@
+use std::fmt;
+use std::io::{Read, Write};
+use std::str;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::de::{self, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::ser::{Formatter, PrettyFormatter};
+
+use crate::de::DEFAULT_MAX_DEPTH;
+use crate::format::{
+    self, read_atom, read_atom_header, read_atom_resolved, Atom, Float, Integer, InternedBytes,
+    Kind, Nucleus, Special, Writer, CURRENT_VERSION,
+};
+use crate::packed::PackedArray;
+use crate::reader::{BufferedBytes, Reader, SliceReader, StrictSliceReader};
+use crate::{Compatibility, Error, Result, Value};
+
+/// The key of an object containing a value of [`Kind::Bytes`] that is not
+/// valid UTF-8, encoded using standard base64.
+const BYTES_MARKER: &str = "$bytes";
+/// The key of an object representing [`Nucleus::Unit`]. The marker's value is
+/// `null`.
+const UNIT_MARKER: &str = "$unit";
+/// The key of an object containing a named value, such as an enum variant
+/// with associated data. The marker's value is an object with a single entry
+/// whose key is the name.
+const NAMED_MARKER: &str = "$named";
+/// The key of an object containing a map whose keys cannot be represented as
+/// JSON object keys. The marker's value is an array of `[key, value]` arrays.
+const MAP_MARKER: &str = "$map";
+/// The key of an object containing an integer that does not fit in an `i64`
+/// or `u64`, encoded as a decimal string.
+const INT_MARKER: &str = "$int";
+/// The key of an object containing a float that JSON cannot represent: `NaN`,
+/// `inf` or `-inf`.
+const FLOAT_MARKER: &str = "$float";
+
+const MARKERS: [&str; 6] = [
+    BYTES_MARKER,
+    UNIT_MARKER,
+    NAMED_MARKER,
+    MAP_MARKER,
+    INT_MARKER,
+    FLOAT_MARKER,
+];
+
+/// Writes the Pot payload `pot` to `writer` as pretty-printed JSON.
+///
+/// Values that JSON cannot represent directly are written as objects with a
+/// single entry whose key is a marker beginning with `$`:
+///
+/// | Pot value | JSON |
+/// |-----------|------|
+/// | `None` | `null` |
+/// | Unit | `{"$unit": null}` |
+/// | Bytes that are not UTF-8 | `{"$bytes": "<base64>"}` |
+/// | Named value (enum variant) | `{"$named": {"Name": value}}` |
+/// | Integers outside of `i64` and `u64` | `{"$int": "<decimal>"}` |
+/// | `NaN` and infinite floats | `{"$float": "NaN"}`, `"inf"`, or `"-inf"` |
+/// | Map with a key that is not a string | `{"$map": [[key, value], ...]}` |
+///
+/// Symbols and bytes containing UTF-8 are both written as strings. Maps
+/// containing a single entry whose key begins with `$` are written using
+/// `$map` so that they are not mistaken for markers.
+///
+/// Payloads serialized with [`Compatibility::Full`] encode enum variants
+/// without associated data in a way that cannot be distinguished from other
+/// named values, and may not be converted correctly. Values interned with
+/// [`Config::intern_bytes`](crate::Config::intern_bytes) are written in full
+/// wherever they are referenced.
+///
+/// Values nested more than 128 levels deep, the default limit of
+/// [`Config::max_depth`](crate::Config::max_depth), return
+/// [`Error::TooDeeplyNested`].
+///
+/// ```rust
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let payload = pot::to_vec(&Point { x: 1, y: -2 }).unwrap();
+/// let mut json = Vec::new();
+/// pot::json::to_json_writer(&payload, &mut json).unwrap();
+/// assert_eq!(
+///     String::from_utf8(json).unwrap(),
+///     "{\n  \"x\": 1,\n  \"y\": -2\n}"
+/// );
+/// ```
+pub fn to_json_writer<W: Write>(pot: &[u8], writer: W) -> Result<()> {
+    let mut reader = SliceReader::from(pot);
+    let version = format::read_header(&mut StrictSliceReader(&mut reader))?;
+    if version > CURRENT_VERSION {
+        return Err(Error::IncompatibleVersion);
+    }
+
+    let mut scanner = MapScanner {
+        reader: SliceReader::from(reader.data),
+        symbols: Vec::new(),
+        interned: InternedBytes::default(),
+        objects: Vec::new(),
+        remaining_depth: DEFAULT_MAX_DEPTH,
+    };
+    scanner.scan_element()?;
+
+    let mut transcoder = PotToJson {
+        reader,
+        symbols: Vec::new(),
+        interned: InternedBytes::default(),
+        objects: scanner.objects.into_iter(),
+        output: writer,
+        formatter: PrettyFormatter::new(),
+        remaining_depth: DEFAULT_MAX_DEPTH,
+    };
+    transcoder.write_value()?;
+    if transcoder.reader.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::TrailingBytes)
+    }
+}
+
+/// Reads JSON from `reader` and returns it as a Pot payload.
+///
+/// This is the inverse of [`to_json_writer()`], and recognizes the same
+/// markers. JSON strings are written as bytes, except for object keys and
+/// names of named values, which are written as symbols. Because the lengths
+/// of JSON arrays and objects are not known until they have been read, they
+/// are written as sequences and maps of unknown length, which requires
+/// [`Compatibility::V5`].
+///
+/// ```rust
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize, Debug, PartialEq)]
+/// enum Shape {
+///     Square { size: u32 },
+///     Empty,
+/// }
+///
+/// let payload =
+///     pot::json::from_json_reader(&br#"[{"$named": {"Square": {"size": 2}}}, "Empty"]"#[..])
+///         .unwrap();
+/// let shapes: Vec<Shape> = pot::from_slice(&payload).unwrap();
+/// assert_eq!(shapes, vec![Shape::Square { size: 2 }, Shape::Empty]);
+/// ```
+pub fn from_json_reader<R: Read>(reader: R) -> Result<Vec<u8>> {
+    let mut writer = Writer::new_with_compatibility(Vec::new(), Compatibility::V5)?;
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    JsonValue(&mut writer)
+        .deserialize(&mut deserializer)
+        .map_err(json_error)?;
+    deserializer.end().map_err(json_error)?;
+    writer.finish()
+}
+
+fn json_error(err: serde_json::Error) -> Error {
+    if err.is_io() {
+        Error::Io(err.into())
+    } else {
+        Error::Message(err.to_string())
+    }
+}
+
+/// Scans a payload before it is converted, determining which maps can be
+/// written as JSON objects.
+///
+/// A map can only be written as an object if all of its keys are strings,
+/// which isn't known until all of its entries have been read. Scanning the
+/// payload once up front allows the conversion itself to be a single pass.
+struct MapScanner<'a> {
+    reader: SliceReader<'a>,
+    symbols: Vec<&'a str>,
+    interned: InternedBytes<'a>,
+    /// Whether each map can be written as a JSON object, in the order the
+    /// maps begin.
+    objects: Vec<bool>,
+    remaining_depth: usize,
+}
+
+/// A value read by [`MapScanner`].
+enum Scanned<'a> {
+    /// A symbol, or bytes containing UTF-8.
+    Str(&'a str),
+    /// [`Special::DynamicEnd`].
+    End,
+    Other,
+}
+
+impl<'a> MapScanner<'a> {
+    fn scan_value(&mut self) -> Result<Scanned<'a>> {
+        let mut budget = usize::MAX;
+        let atom = read_atom_resolved(
+            &mut StrictSliceReader(&mut self.reader),
+            &mut budget,
+            &mut Vec::new(),
+            &mut self.interned,
+            |_, _| Ok(()),
+        )?;
+        match (atom.kind, atom.nucleus) {
+            (Kind::Sequence, _) => self.nested(|this| {
+                for _ in 0..atom.arg {
+                    this.scan_element()?;
+                }
+                Ok(())
+            })?,
+            (Kind::Map, _) => self.nested(|this| this.scan_map(Some(atom.arg)))?,
+            (Kind::Symbol, _) => {
+                return read_symbol(&mut self.reader, &mut self.symbols, atom.arg).map(Scanned::Str)
+            }
+            (_, Some(Nucleus::Bytes(BufferedBytes::Data(bytes)))) => {
+                return Ok(str::from_utf8(bytes).map_or(Scanned::Other, Scanned::Str));
+            }
+            (_, Some(Nucleus::Named)) => self.nested(|this| {
+                // Named values are followed by a name and a value.
+                this.scan_element()?;
+                this.scan_element()?;
+                Ok(())
+            })?,
+            (_, Some(Nucleus::PackedArray)) => {
+                // Packed arrays are followed by their element type and
+                // elements.
+                self.scan_element()?;
+                self.scan_element()?;
+            }
+            (_, Some(Nucleus::DynamicMap)) => self.nested(|this| this.scan_map(None))?,
+            (_, Some(Nucleus::DynamicSequence)) => self.nested(|this| {
+                while !matches!(this.scan_value()?, Scanned::End) {}
+                Ok(())
+            })?,
+            (_, Some(Nucleus::DynamicEnd)) => return Ok(Scanned::End),
+            _ => {}
+        }
+        Ok(Scanned::Other)
+    }
+
+    /// Invokes `scan` one level deeper, returning
+    /// [`Error::TooDeeplyNested`] if the maximum depth would be exceeded.
+    fn nested(&mut self, scan: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.remaining_depth = self
+            .remaining_depth
+            .checked_sub(1)
+            .ok_or(Error::TooDeeplyNested)?;
+        let result = scan(self);
+        self.remaining_depth += 1;
+        result
+    }
+
+    /// Scans a value that must not be [`Special::DynamicEnd`].
+    fn scan_element(&mut self) -> Result<Scanned<'a>> {
+        match self.scan_value()? {
+            Scanned::End => Err(de::Error::custom("unexpected dynamic end")),
+            other => Ok(other),
+        }
+    }
+
+    fn scan_map(&mut self, mut remaining: Option<u64>) -> Result<()> {
+        let index = self.objects.len();
+        self.objects.push(false);
+
+        let mut entries = 0_u64;
+        let mut keys_are_strings = true;
+        let mut first_key = None;
+        loop {
+            match &mut remaining {
+                Some(0) => break,
+                Some(remaining) => *remaining -= 1,
+                None => {}
+            }
+
+            match self.scan_value()? {
+                Scanned::End if remaining.is_none() => break,
+                Scanned::End => return Err(de::Error::custom("unexpected dynamic end")),
+                Scanned::Str(key) => {
+                    first_key.get_or_insert(key);
+                }
+                Scanned::Other => keys_are_strings = false,
+            }
+            entries += 1;
+            self.scan_element()?;
+        }
+
+        self.objects[index] = keys_are_strings
+            && !(entries == 1 && first_key.is_some_and(|key| key.starts_with('$')));
+        Ok(())
+    }
+}
+
+struct PotToJson<'a, W> {
+    reader: SliceReader<'a>,
+    symbols: Vec<&'a str>,
+    interned: InternedBytes<'a>,
+    objects: std::vec::IntoIter<bool>,
+    output: W,
+    formatter: PrettyFormatter<'static>,
+    remaining_depth: usize,
+}
+
+impl<'a, W: Write> PotToJson<'a, W> {
+    /// Reads the next atom, resolving interned bytes.
+    fn read_atom(&mut self) -> Result<Atom<'a>> {
+        let mut budget = usize::MAX;
+        read_atom_resolved(
+            &mut StrictSliceReader(&mut self.reader),
+            &mut budget,
+            &mut Vec::new(),
+            &mut self.interned,
+            |_, _| Ok(()),
+        )
+    }
+
+    fn write_value(&mut self) -> Result<()> {
+        let atom = self.read_atom()?;
+        match (atom.kind, atom.nucleus) {
+            (Kind::Sequence, _) => self.nested(|this| this.write_sequence(Some(atom.arg))),
+            (Kind::Map, _) => self.nested(|this| this.write_map(Some(atom.arg))),
+            (Kind::Symbol, _) => {
+                let symbol = read_symbol(&mut self.reader, &mut self.symbols, atom.arg)?;
+                self.write_str(symbol)
+            }
+            (_, None) => Ok(self.formatter.write_null(&mut self.output)?),
+            (_, Some(Nucleus::Unit)) => self.write_marker(UNIT_MARKER, |this| {
+                Ok(this.formatter.write_null(&mut this.output)?)
+            }),
+            (_, Some(Nucleus::Boolean(value))) => {
+                Ok(self.formatter.write_bool(&mut self.output, value)?)
+            }
+            (_, Some(Nucleus::Integer(value))) => self.write_integer(value),
+            (_, Some(Nucleus::Float(value))) => self.write_float(value),
+            (_, Some(Nucleus::Bytes(BufferedBytes::Data(bytes)))) => {
+                if let Ok(string) = str::from_utf8(bytes) {
+                    self.write_str(string)
+                } else {
+                    self.write_marker(BYTES_MARKER, |this| this.write_str(&BASE64.encode(bytes)))
+                }
+            }
+            (_, Some(Nucleus::Bytes(BufferedBytes::Scratch))) => {
+                unreachable!("slice readers always borrow")
+            }
+            (_, Some(Nucleus::Named)) => self.nested(|this| {
+                this.write_marker(NAMED_MARKER, |this| {
+                    this.formatter.begin_object(&mut this.output)?;
+                    this.formatter.begin_object_key(&mut this.output, true)?;
+                    let name = this.read_key()?;
+                    this.write_str(name)?;
+                    this.formatter.end_object_key(&mut this.output)?;
+                    this.formatter.begin_object_value(&mut this.output)?;
+                    this.write_value()?;
+                    this.formatter.end_object_value(&mut this.output)?;
+                    Ok(this.formatter.end_object(&mut this.output)?)
+                })
+            }),
+            (_, Some(Nucleus::DynamicMap)) => self.nested(|this| this.write_map(None)),
+            (_, Some(Nucleus::DynamicSequence)) => self.nested(|this| this.write_sequence(None)),
+            (_, Some(Nucleus::DynamicEnd)) => Err(de::Error::custom("unexpected dynamic end")),
+            (_, Some(Nucleus::PackedArray)) => self.write_packed_array(),
+            (_, Some(Nucleus::InternedBytes | Nucleus::BytesReference)) => {
+                unreachable!("interned bytes are resolved when read")
+            }
+        }
+    }
+
+    /// Invokes `write` one level deeper, returning
+    /// [`Error::TooDeeplyNested`] if the maximum depth would be exceeded.
+    fn nested(&mut self, write: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.remaining_depth = self
+            .remaining_depth
+            .checked_sub(1)
+            .ok_or(Error::TooDeeplyNested)?;
+        let result = write(self);
+        self.remaining_depth += 1;
+        result
+    }
+
+    fn write_str(&mut self, value: &str) -> Result<()> {
+        serde_json::to_writer(&mut self.output, value).map_err(json_error)
+    }
+
+    fn write_integer(&mut self, value: Integer) -> Result<()> {
+        if let Ok(value) = value.as_i64() {
+            Ok(self.formatter.write_i64(&mut self.output, value)?)
+        } else if let Ok(value) = value.as_u64() {
+            Ok(self.formatter.write_u64(&mut self.output, value)?)
+        } else {
+            self.write_marker(INT_MARKER, |this| this.write_str(&value.to_string()))
+        }
+    }
+
+    fn write_float(&mut self, value: Float) -> Result<()> {
+        let as_f64 = value.as_f64();
+        if as_f64.is_finite() {
+            // Floats are always written with the precision of an f64, because
+            // the shortest representation of an f32 is not necessarily the
+            // same value when parsed as an f64.
+            Ok(self.formatter.write_f64(&mut self.output, as_f64)?)
+        } else {
+            let name = if as_f64.is_nan() {
+                "NaN"
+            } else if as_f64.is_sign_positive() {
+                "inf"
+            } else {
+                "-inf"
+            };
+            self.write_marker(FLOAT_MARKER, |this| this.write_str(name))
+        }
+    }
+
+    /// Writes an object containing a single entry whose key is `marker`.
+    fn write_marker(
+        &mut self,
+        marker: &str,
+        write_value: impl FnOnce(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        self.formatter.begin_object(&mut self.output)?;
+        self.formatter.begin_object_key(&mut self.output, true)?;
+        self.write_str(marker)?;
+        self.formatter.end_object_key(&mut self.output)?;
+        self.formatter.begin_object_value(&mut self.output)?;
+        write_value(self)?;
+        self.formatter.end_object_value(&mut self.output)?;
+        Ok(self.formatter.end_object(&mut self.output)?)
+    }
+
+    /// Returns true and consumes the atom if the next atom is
+    /// [`Special::DynamicEnd`].
+    fn at_end(&mut self, remaining: &mut Option<u64>) -> Result<bool> {
+        match remaining {
+            Some(0) => Ok(true),
+            Some(remaining) => {
+                *remaining -= 1;
+                Ok(false)
+            }
+            None => {
+                if is_dynamic_end(&mut SliceReader::from(self.reader.data))? {
+                    read_atom_header(&mut StrictSliceReader(&mut self.reader))?;
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+        }
+    }
+
+    fn write_sequence(&mut self, mut remaining: Option<u64>) -> Result<()> {
+        self.formatter.begin_array(&mut self.output)?;
+        let mut first = true;
+        while !self.at_end(&mut remaining)? {
+            self.formatter.begin_array_value(&mut self.output, first)?;
+            self.write_value()?;
+            self.formatter.end_array_value(&mut self.output)?;
+            first = false;
+        }
+        Ok(self.formatter.end_array(&mut self.output)?)
+    }
+
+    fn write_packed_array(&mut self) -> Result<()> {
+        let mut reader = StrictSliceReader(&mut self.reader);
+        let mut budget = usize::MAX;
+        let element_type = read_atom(&mut reader, &mut budget, &mut Vec::new())?;
+        let elements = read_atom(&mut reader, &mut budget, &mut Vec::new())?;
+        let (element_type, BufferedBytes::Data(bytes)) =
+            format::packed_array_parts(element_type, elements)?
+        else {
+            unreachable!("slice readers always borrow")
+        };
+
+        self.formatter.begin_array(&mut self.output)?;
+        for (index, value) in PackedArray::from_bytes(element_type, bytes)?
+            .values()
+            .enumerate()
+        {
+            self.formatter
+                .begin_array_value(&mut self.output, index == 0)?;
+            match value {
+                Value::Integer(value) => self.write_integer(value)?,
+                Value::Float(value) => self.write_float(value)?,
+                _ => unreachable!("packed arrays only contain numbers"),
+            }
+            self.formatter.end_array_value(&mut self.output)?;
+        }
+        Ok(self.formatter.end_array(&mut self.output)?)
+    }
+
+    fn write_map(&mut self, mut remaining: Option<u64>) -> Result<()> {
+        let is_object = self.objects.next().expect("every map is scanned");
+        if is_object {
+            self.formatter.begin_object(&mut self.output)?;
+            let mut first = true;
+            while !self.at_end(&mut remaining)? {
+                self.formatter.begin_object_key(&mut self.output, first)?;
+                let key = self.read_key()?;
+                self.write_str(key)?;
+                self.formatter.end_object_key(&mut self.output)?;
+                self.formatter.begin_object_value(&mut self.output)?;
+                self.write_value()?;
+                self.formatter.end_object_value(&mut self.output)?;
+                first = false;
+            }
+            Ok(self.formatter.end_object(&mut self.output)?)
+        } else {
+            self.write_marker(MAP_MARKER, |this| {
+                this.formatter.begin_array(&mut this.output)?;
+                let mut first = true;
+                while !this.at_end(&mut remaining)? {
+                    this.formatter.begin_array_value(&mut this.output, first)?;
+                    this.formatter.begin_array(&mut this.output)?;
+                    for first in [true, false] {
+                        this.formatter.begin_array_value(&mut this.output, first)?;
+                        this.write_value()?;
+                        this.formatter.end_array_value(&mut this.output)?;
+                    }
+                    this.formatter.end_array(&mut this.output)?;
+                    this.formatter.end_array_value(&mut this.output)?;
+                    first = false;
+                }
+                Ok(this.formatter.end_array(&mut this.output)?)
+            })
+        }
+    }
+
+    /// Reads a map key or name, which must be either a symbol or bytes
+    /// containing UTF-8.
+    fn read_key(&mut self) -> Result<&'a str> {
+        let atom = self.read_atom()?;
+        match (atom.kind, atom.nucleus) {
+            (Kind::Symbol, _) => read_symbol(&mut self.reader, &mut self.symbols, atom.arg),
+            (_, Some(Nucleus::Bytes(BufferedBytes::Data(bytes)))) => Ok(str::from_utf8(bytes)?),
+            (kind, _) => Err(Error::UnexpectedKind(kind, Kind::Symbol)),
+        }
+    }
+}
+
+/// Reads the symbol described by a [`Kind::Symbol`] atom's `arg`, defining it
+/// in `symbols` if it is new.
+#[allow(clippy::cast_possible_truncation)]
+fn read_symbol<'a>(
+    reader: &mut SliceReader<'a>,
+    symbols: &mut Vec<&'a str>,
+    arg: u64,
+) -> Result<&'a str> {
+    let id = arg >> 1;
+    if arg & 0b1 == 0 {
+        let name = read_str(reader, id as usize)?;
+        symbols.push(name);
+        Ok(name)
+    } else {
+        symbols
+            .get(id as usize)
+            .copied()
+            .ok_or(Error::UnknownSymbol(id))
+    }
+}
+
+fn read_str<'a>(reader: &mut SliceReader<'a>, length: usize) -> Result<&'a str> {
+    let BufferedBytes::Data(bytes) = reader.buffered_read_bytes(length, &mut Vec::new())? else {
+        unreachable!("slice readers always borrow")
+    };
+    Ok(str::from_utf8(bytes)?)
+}
+
+fn is_dynamic_end(reader: &mut SliceReader<'_>) -> Result<bool> {
+    let (kind, arg) = read_atom_header(&mut StrictSliceReader(reader))?;
+    Ok(kind == Kind::Special && arg == Special::DynamicEnd as u64)
+}
+
+/// Writes the JSON value being deserialized into a Pot [`Writer`].
+struct JsonValue<'w>(&'w mut Writer<Vec<u8>>);
+
+impl<'de> DeserializeSeed<'de> for JsonValue<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonValue<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.0.write_none().map_err(E::custom)
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        self.0.write_bool(v).map_err(E::custom)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.0.write_i64(v).map_err(E::custom)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.0.write_u64(v).map_err(E::custom)
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+        self.0.write_float(v).map_err(E::custom)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        self.0.write_str(v).map_err(E::custom)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.0.begin_dynamic_sequence().map_err(de::Error::custom)?;
+        while seq.next_element_seed(JsonValue(&mut *self.0))?.is_some() {}
+        self.0.end().map_err(de::Error::custom)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let Some(first_key) = map.next_key::<String>()? else {
+            self.0.begin_map(0).map_err(de::Error::custom)?;
+            return self.0.end().map_err(de::Error::custom);
+        };
+
+        if MARKERS.contains(&first_key.as_str()) {
+            self.visit_marker(&first_key, &mut map)?;
+            if map.next_key::<IgnoredAny>()?.is_some() {
+                return Err(de::Error::custom(format!(
+                    "{first_key} must be the only entry in its object"
+                )));
+            }
+            return Ok(());
+        }
+
+        self.0.begin_dynamic_map().map_err(de::Error::custom)?;
+        let mut key = Some(first_key);
+        while let Some(current) = key {
+            self.0.symbol(&current).map_err(de::Error::custom)?;
+            map.next_value_seed(JsonValue(&mut *self.0))?;
+            key = map.next_key::<String>()?;
+        }
+        self.0.end().map_err(de::Error::custom)
+    }
+}
+
+impl JsonValue<'_> {
+    fn visit_marker<'de, A>(self, marker: &str, map: &mut A) -> Result<(), A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        match marker {
+            UNIT_MARKER => {
+                map.next_value::<()>()?;
+                self.0.write_unit().map_err(de::Error::custom)
+            }
+            BYTES_MARKER => {
+                let encoded = map.next_value::<String>()?;
+                let bytes = BASE64.decode(encoded).map_err(de::Error::custom)?;
+                self.0.write_bytes(&bytes).map_err(de::Error::custom)
+            }
+            INT_MARKER => {
+                let value = map.next_value::<String>()?;
+                let value = if let Ok(value) = value.parse::<i128>() {
+                    Integer::from(value)
+                } else {
+                    Integer::from(value.parse::<u128>().map_err(de::Error::custom)?)
+                };
+                self.0.write_integer(value).map_err(de::Error::custom)
+            }
+            FLOAT_MARKER => {
+                let value = match map.next_value::<String>()?.as_str() {
+                    "NaN" => f64::NAN,
+                    "inf" => f64::INFINITY,
+                    "-inf" => f64::NEG_INFINITY,
+                    other => {
+                        return Err(de::Error::invalid_value(
+                            de::Unexpected::Str(other),
+                            &"NaN, inf or -inf",
+                        ))
+                    }
+                };
+                self.0.write_float(value).map_err(de::Error::custom)
+            }
+            NAMED_MARKER => map.next_value_seed(JsonNamed(self.0)),
+            MAP_MARKER => map.next_value_seed(JsonEntries(self.0)),
+            _ => unreachable!("only markers are passed to visit_marker"),
+        }
+    }
+}
+
+/// Writes an object containing a single entry as a named value.
+struct JsonNamed<'w>(&'w mut Writer<Vec<u8>>);
+
+impl<'de> DeserializeSeed<'de> for JsonNamed<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonNamed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an object containing a single entry")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let name = map
+            .next_key::<String>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        self.0.named(&name).map_err(de::Error::custom)?;
+        map.next_value_seed(JsonValue(&mut *self.0))?;
+        if map.next_key::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        Ok(())
+    }
+}
+
+/// Writes an array of `[key, value]` arrays as a map.
+struct JsonEntries<'w>(&'w mut Writer<Vec<u8>>);
+
+impl<'de> DeserializeSeed<'de> for JsonEntries<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonEntries<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("an array of [key, value] arrays")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        self.0.begin_dynamic_map().map_err(de::Error::custom)?;
+        while seq.next_element_seed(JsonEntry(&mut *self.0))?.is_some() {}
+        self.0.end().map_err(de::Error::custom)
+    }
+}
+
+/// Writes a `[key, value]` array as a map entry.
+struct JsonEntry<'w>(&'w mut Writer<Vec<u8>>);
+
+impl<'de> DeserializeSeed<'de> for JsonEntry<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for JsonEntry<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a [key, value] array")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        for index in 0..2 {
+            if seq.next_element_seed(JsonValue(&mut *self.0))?.is_none() {
+                return Err(de::Error::invalid_length(index, &self));
+            }
+        }
+        if seq.next_element::<IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(3, &self));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,431 @@
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt::Debug;
+use core::str;
+
+use crate::format::{
+    self, skip_value_with, Atom, Float, Integer, Kind, Nucleus, PackedType, SkippedSymbol, Special,
+    CURRENT_VERSION,
+};
+use crate::packed::PackedNumber;
+use crate::reader::{BufferedBytes, SliceReader, StrictSliceReader};
+use crate::{Error, Result};
+
+/// A view of a value within a Pot payload that is only decoded as far as
+/// needed.
+///
+/// Navigating with [`get()`](Self::get) skips over sibling values without
+/// decoding them, and strings and bytes are borrowed from the payload.
+/// Interned bytes are resolved, and the elements of packed arrays can be
+/// accessed by index.
+///
+/// ```rust
+/// use pot::lazy::Document;
+/// use serde_derive::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct User<'a> {
+///     name: &'a str,
+///     roles: Vec<&'a str>,
+/// }
+///
+/// let payload = pot::to_vec(&User {
+///     name: "ecton",
+///     roles: vec!["admin", "maintainer"],
+/// })
+/// .unwrap();
+///
+/// let doc = Document::new(&payload).unwrap();
+/// assert_eq!(doc.get("name").unwrap().as_str().unwrap(), "ecton");
+/// assert_eq!(
+///     doc.get("roles").unwrap().get(1).unwrap().as_str().unwrap(),
+///     "maintainer"
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Document<'a> {
+    data: &'a [u8],
+    offset: usize,
+    /// Set when this value is an element of a packed array, in which case
+    /// `offset` is the position of the element's bytes.
+    element_type: Option<PackedType>,
+    definitions: Rc<RefCell<Definitions<'a>>>,
+}
+
+impl Debug for Document<'_> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("Document")
+            .field("offset", &self.offset)
+            .field("kind", &self.kind())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Document<'a> {
+    /// Returns a view of the root value of `pot`, after validating the Pot
+    /// header.
+    pub fn new(pot: &'a [u8]) -> Result<Self> {
+        let mut reader = SliceReader::from(pot);
+        let version = format::read_header(&mut StrictSliceReader(&mut reader))?;
+        if version > CURRENT_VERSION {
+            return Err(Error::IncompatibleVersion);
+        }
+
+        Ok(Self {
+            data: pot,
+            offset: reader.position(),
+            element_type: None,
+            definitions: Rc::default(),
+        })
+    }
+
+    fn at(&self, offset: usize) -> Self {
+        Self {
+            data: self.data,
+            offset,
+            element_type: None,
+            definitions: self.definitions.clone(),
+        }
+    }
+
+    fn reader_at(&self, offset: usize) -> SliceReader<'a> {
+        SliceReader::from(&self.data[offset..])
+    }
+
+    /// Returns the kind of this value, or an error if the atom header is
+    /// invalid.
+    pub fn kind(&self) -> Result<Kind> {
+        if let Some(element_type) = self.element_type {
+            return Ok(element_type.kind());
+        }
+        let mut reader = self.reader_at(self.offset);
+        format::read_atom_header(&mut StrictSliceReader(&mut reader)).map(|(kind, _)| kind)
+    }
+
+    /// Returns the value contained in this value for `key`.
+    ///
+    /// Strings look up the value of a map entry whose key is a matching
+    /// string or symbol. An enum variant is treated as a map containing a
+    /// single entry. Indices look up an element of a sequence or packed array.
+    ///
+    /// Returns [`Error::NotFound`] if the map does not contain the key or the
+    /// index is out of bounds.
+    pub fn get<'k>(&self, key: impl Into<Key<'k>>) -> Result<Self> {
+        match key.into() {
+            Key::Field(field) => self.get_field(field),
+            Key::Index(index) => self.get_index(index),
+        }
+    }
+
+    fn get_field(&self, field: &str) -> Result<Self> {
+        let (kind, arg, reader) = self.read_container_header(Kind::Map)?;
+        let mut remaining = match (kind, Special::try_from(arg)) {
+            (Kind::Map, _) => Some(arg),
+            (Kind::Special, Ok(Special::DynamicMap)) => None,
+            (Kind::Special, Ok(Special::Named)) => Some(1),
+            _ => return Err(Error::UnexpectedKind(kind, Kind::Map)),
+        };
+
+        let mut offset = self.offset + reader.position();
+        loop {
+            match &mut remaining {
+                Some(0) => return Err(Error::NotFound),
+                Some(remaining) => *remaining -= 1,
+                None if self.at(offset).is_dynamic_end()? => return Err(Error::NotFound),
+                None => {}
+            }
+
+            let key = self.at(offset);
+            offset = self.skip(offset)?;
+            if key.key_matches(field)? {
+                return Ok(self.at(offset));
+            }
+            offset = self.skip(offset)?;
+        }
+    }
+
+    fn get_index(&self, index: usize) -> Result<Self> {
+        let (kind, arg, mut reader) = self.read_container_header(Kind::Sequence)?;
+        let mut offset = self.offset + reader.position();
+        match (kind, Special::try_from(arg)) {
+            (Kind::Sequence, _) => {
+                if index as u64 >= arg {
+                    return Err(Error::NotFound);
+                }
+                for _ in 0..index {
+                    offset = self.skip(offset)?;
+                }
+            }
+            (Kind::Special, Ok(Special::DynamicSequence)) => {
+                for _ in 0..index {
+                    if self.at(offset).is_dynamic_end()? {
+                        return Err(Error::NotFound);
+                    }
+                    offset = self.skip(offset)?;
+                }
+                if self.at(offset).is_dynamic_end()? {
+                    return Err(Error::NotFound);
+                }
+            }
+            (Kind::Special, Ok(Special::PackedArray)) => {
+                let (element_type, elements) = read_packed_array(&mut reader)?;
+                let start = index
+                    .checked_mul(element_type.width())
+                    .filter(|start| *start < elements.len())
+                    .ok_or(Error::NotFound)?;
+                return Ok(Self {
+                    data: self.data,
+                    offset: self.offset + reader.position() - elements.len() + start,
+                    element_type: Some(element_type),
+                    definitions: self.definitions.clone(),
+                });
+            }
+            _ => return Err(Error::UnexpectedKind(kind, Kind::Sequence)),
+        }
+
+        Ok(self.at(offset))
+    }
+
+    /// Reads the atom header of this value, returning an error if this value
+    /// is an element of a packed array rather than a container of the
+    /// `expected` kind.
+    fn read_container_header(&self, expected: Kind) -> Result<(Kind, u64, SliceReader<'a>)> {
+        if let Some(element_type) = self.element_type {
+            return Err(Error::UnexpectedKind(element_type.kind(), expected));
+        }
+        let mut reader = self.reader_at(self.offset);
+        let (kind, arg) = format::read_atom_header(&mut StrictSliceReader(&mut reader))?;
+        Ok((kind, arg, reader))
+    }
+
+    /// Skips the value at `offset`, returning the offset of the next value.
+    fn skip(&self, offset: usize) -> Result<usize> {
+        let mut reader = self.reader_at(offset);
+        let mut definitions = self.definitions.borrow_mut();
+        skip_value_with(&mut reader, |symbol| match symbol {
+            SkippedSymbol::Defined {
+                offset: symbol_offset,
+                name,
+            } => {
+                definitions.define(offset + symbol_offset, name);
+                Ok(())
+            }
+            SkippedSymbol::Reference {
+                offset: symbol_offset,
+                id,
+            } => definitions.resolve(id, offset + symbol_offset).map(|_| ()),
+            SkippedSymbol::InternedBytes {
+                offset: bytes_offset,
+                bytes,
+            } => {
+                definitions.define_bytes(offset + bytes_offset, bytes);
+                Ok(())
+            }
+        })?;
+        Ok(offset + reader.position())
+    }
+
+    fn is_dynamic_end(&self) -> Result<bool> {
+        let mut reader = self.reader_at(self.offset);
+        let (kind, arg) = format::read_atom_header(&mut StrictSliceReader(&mut reader))?;
+        Ok(kind == Kind::Special && arg == Special::DynamicEnd as u64)
+    }
+
+    fn key_matches(&self, field: &str) -> Result<bool> {
+        match self.kind()? {
+            Kind::Symbol | Kind::Bytes | Kind::Special => {
+                Ok(self.as_str().is_ok_and(|key| key == field))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn read_nucleus(&self) -> Result<(Kind, Option<Nucleus<'a>>)> {
+        if let Some(element_type) = self.element_type {
+            let bytes = &self.data[self.offset..self.offset + element_type.width()];
+            let number = PackedNumber::from_le_slice(element_type, bytes);
+            return Ok((element_type.kind(), Some(number.into_nucleus())));
+        }
+        let atom = read_atom(&mut self.reader_at(self.offset))?;
+        Ok((atom.kind, atom.nucleus))
+    }
+
+    /// Returns true if this value is `None`.
+    pub fn is_none(&self) -> Result<bool> {
+        let (kind, nucleus) = self.read_nucleus()?;
+        Ok(kind == Kind::Special && nucleus.is_none())
+    }
+
+    /// Returns this value as a boolean.
+    pub fn as_bool(&self) -> Result<bool> {
+        match self.read_nucleus()? {
+            (_, Some(Nucleus::Boolean(value))) => Ok(value),
+            (kind, _) => Err(Error::UnexpectedKind(kind, Kind::Special)),
+        }
+    }
+
+    /// Returns this value as an [`Integer`].
+    pub fn as_integer(&self) -> Result<Integer> {
+        match self.read_nucleus()? {
+            (_, Some(Nucleus::Integer(value))) => Ok(value),
+            (kind, _) => Err(Error::UnexpectedKind(kind, Kind::Int)),
+        }
+    }
+
+    /// Returns this value as a [`Float`].
+    pub fn as_float(&self) -> Result<Float> {
+        match self.read_nucleus()? {
+            (_, Some(Nucleus::Float(value))) => Ok(value),
+            (kind, _) => Err(Error::UnexpectedKind(kind, Kind::Float)),
+        }
+    }
+
+    /// Returns this value as a byte slice borrowed from the payload. Interned
+    /// bytes are resolved, and packed arrays of `u8`s can be read as bytes.
+    pub fn as_bytes(&self) -> Result<&'a [u8]> {
+        if let Some(element_type) = self.element_type {
+            return Err(Error::UnexpectedKind(element_type.kind(), Kind::Bytes));
+        }
+        let mut reader = self.reader_at(self.offset);
+        let atom = read_atom(&mut reader)?;
+        match atom.nucleus {
+            Some(Nucleus::Bytes(BufferedBytes::Data(bytes))) => Ok(bytes),
+            Some(Nucleus::InternedBytes) => {
+                let atom = read_atom(&mut reader)?;
+                let Some(Nucleus::Bytes(BufferedBytes::Data(bytes))) = atom.nucleus else {
+                    return Err(Error::UnexpectedKind(atom.kind, Kind::Bytes));
+                };
+                self.definitions
+                    .borrow_mut()
+                    .define_bytes(self.offset, bytes);
+                Ok(bytes)
+            }
+            Some(Nucleus::BytesReference) => {
+                let id = read_atom(&mut reader)?;
+                let (Kind::UInt, Some(Nucleus::Integer(id))) = (id.kind, id.nucleus) else {
+                    return Err(Error::UnexpectedKind(id.kind, Kind::UInt));
+                };
+                self.definitions
+                    .borrow()
+                    .resolve_bytes(id.as_u64()?, self.offset)
+            }
+            Some(Nucleus::PackedArray) => match read_packed_array(&mut reader)? {
+                (PackedType::U8, elements) => Ok(elements),
+                _ => Err(Error::UnexpectedKind(atom.kind, Kind::Bytes)),
+            },
+            _ => Err(Error::UnexpectedKind(atom.kind, Kind::Bytes)),
+        }
+    }
+
+    /// Returns this value as a string borrowed from the payload. Both byte
+    /// values containing UTF-8 and symbols can be read as strings.
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn as_str(&self) -> Result<&'a str> {
+        if self.element_type.is_some() {
+            return Ok(str::from_utf8(self.as_bytes()?)?);
+        }
+        let mut reader = self.reader_at(self.offset);
+        let (kind, arg) = format::read_atom_header(&mut StrictSliceReader(&mut reader))?;
+        if kind == Kind::Symbol {
+            if arg & 0b1 == 0 {
+                let name =
+                    str::from_utf8(reader.data.get(..(arg >> 1) as usize).ok_or(Error::Eof)?)?;
+                self.definitions.borrow_mut().define(self.offset, name);
+                Ok(name)
+            } else {
+                self.definitions.borrow().resolve(arg >> 1, self.offset)
+            }
+        } else {
+            Ok(str::from_utf8(self.as_bytes()?)?)
+        }
+    }
+}
+
+/// A key used to look up a value in a [`Document`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Key<'k> {
+    /// A map entry whose key is this string.
+    Field(&'k str),
+    /// A sequence element at this index.
+    Index(usize),
+}
+
+impl<'k> From<&'k str> for Key<'k> {
+    #[inline]
+    fn from(field: &'k str) -> Self {
+        Self::Field(field)
+    }
+}
+
+impl From<usize> for Key<'_> {
+    #[inline]
+    fn from(index: usize) -> Self {
+        Self::Index(index)
+    }
+}
+
+/// Reads the atom at the start of `reader`.
+fn read_atom<'a>(reader: &mut SliceReader<'a>) -> Result<Atom<'a>> {
+    let mut budget = usize::MAX;
+    format::read_atom(&mut StrictSliceReader(reader), &mut budget, &mut Vec::new())
+}
+
+/// Reads the element type and elements of a packed array whose
+/// [`Special::PackedArray`] atom has already been read from `reader`.
+fn read_packed_array<'a>(reader: &mut SliceReader<'a>) -> Result<(PackedType, &'a [u8])> {
+    let element_type = read_atom(reader)?;
+    let elements = read_atom(reader)?;
+    let (element_type, BufferedBytes::Data(elements)) =
+        format::packed_array_parts(element_type, elements)?
+    else {
+        unreachable!("slice readers always borrow")
+    };
+    Ok((element_type, elements))
+}
+
+/// The symbols and interned bytes that have been defined in the portions of a
+/// payload scanned so far, ordered by the offset of their definitions.
+///
+/// Values are always reached by scanning forward from the start of the
+/// payload, which guarantees that everything defined before a value has been
+/// recorded by the time the value is read.
+#[derive(Default)]
+struct Definitions<'a> {
+    symbols: Vec<(usize, &'a str)>,
+    bytes: Vec<(usize, &'a [u8])>,
+}
+
+impl<'a> Definitions<'a> {
+    fn define(&mut self, offset: usize, name: &'a str) {
+        insert_definition(&mut self.symbols, offset, name);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn resolve(&self, id: u64, offset: usize) -> Result<&'a str> {
+        match self.symbols.get(id as usize) {
+            Some((defined_at, name)) if *defined_at < offset => Ok(name),
+            _ => Err(Error::UnknownSymbol(id)),
+        }
+    }
+
+    fn define_bytes(&mut self, offset: usize, bytes: &'a [u8]) {
+        insert_definition(&mut self.bytes, offset, bytes);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn resolve_bytes(&self, id: u64, offset: usize) -> Result<&'a [u8]> {
+        match self.bytes.get(id as usize) {
+            Some((defined_at, bytes)) if *defined_at < offset => Ok(bytes),
+            _ => Err(Error::UnknownBytesReference(id)),
+        }
+    }
+}
+
+/// Inserts `value`, defined at `offset`, into `defined` unless it has already
+/// been recorded.
+fn insert_definition<T>(defined: &mut Vec<(usize, T)>, offset: usize, value: T) {
+    if let Err(index) = defined.binary_search_by_key(&offset, |(offset, _)| *offset) {
+        defined.insert(index, (offset, value));
+    }
+}
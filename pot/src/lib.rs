@@ -1,4 +1,5 @@
 #![doc = include_str!("../crate-docs.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
 #![forbid(unsafe_code)]
 #![warn(
     clippy::cargo,
@@ -15,29 +16,59 @@
     clippy::module_name_repetitions,
 )]
 
+extern crate alloc;
+
+#[cfg(feature = "async")]
+mod async_io;
 /// Types for deserializing pots.
 pub mod de;
 mod error;
+/// Pull-based reading of Pot payloads.
+pub mod events;
 /// Low-level interface for reading and writing the pot format.
 pub mod format;
+mod framing;
+/// Incremental decoding of Pot payloads as their bytes arrive.
+pub mod incremental;
+/// Conversion between Pot and JSON.
+#[cfg(feature = "json")]
+pub mod json;
+/// Navigation of Pot payloads without decoding them fully.
+pub mod lazy;
+mod limits;
+/// Compact encoding of sequences of numbers.
+pub mod packed;
 /// Types for reading data.
 pub mod reader;
 /// Types for serializing pots.
 pub mod ser;
+mod stream;
+/// A human-readable notation for [`Value`]s.
+pub mod text;
+mod validate;
 mod value;
+/// Types for writing data.
+pub mod writer;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
 use std::io::Read;
 
-use byteorder::WriteBytesExt;
-
 pub use self::error::Error;
+pub use self::limits::Limits;
+pub use self::stream::StreamDeserializer;
+pub use self::validate::{validate, Summary};
 pub use self::value::{OwnedValue, Value, ValueError, ValueIter};
 /// A result alias that returns [`Error`].
-pub type Result<T, E = Error> = std::result::Result<T, E>;
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+#[cfg(feature = "std")]
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "std")]
 use crate::de::SymbolMapRef;
+#[cfg(feature = "std")]
 use crate::reader::IoReader;
+use crate::writer::Write;
 
 /// Serialize `value` using Pot into a `Vec<u8>`.
 ///
@@ -66,7 +97,7 @@ where
 pub fn to_writer<T, W>(value: &T, writer: W) -> Result<()>
 where
     T: Serialize,
-    W: WriteBytesExt,
+    W: Write,
 {
     Config::default().serialize_into(value, writer)
 }
@@ -94,6 +125,7 @@ where
 /// let deserialized = pot::from_reader::<String, _>(&serialized[..]).unwrap();
 /// assert_eq!(deserialized, "hello world");
 /// ```
+#[cfg(feature = "std")]
 #[inline]
 pub fn from_reader<T, R>(reader: R) -> Result<T>
 where
@@ -106,9 +138,18 @@ where
 /// Serialization and deserialization configuration.
 #[must_use]
 #[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Config {
     allocation_budget: usize,
+    max_depth: usize,
+    limits: Limits,
+    error_positions: bool,
+    lenient: bool,
     compatibility: Compatibility,
+    canonical: bool,
+    intern_bytes: Option<usize>,
+    compact_bytes: bool,
+    struct_representation: StructRepr,
 }
 
 impl Default for Config {
@@ -123,7 +164,15 @@ impl Config {
     pub const fn new() -> Self {
         Self {
             allocation_budget: usize::MAX,
+            max_depth: de::DEFAULT_MAX_DEPTH,
+            limits: Limits::new(),
+            error_positions: false,
+            lenient: false,
             compatibility: Compatibility::const_default(),
+            canonical: false,
+            intern_bytes: None,
+            compact_bytes: false,
+            struct_representation: StructRepr::Named,
         }
     }
     /// Sets the maximum number of bytes able to be allocated. This is not
@@ -139,22 +188,316 @@ impl Config {
         self
     }
 
+    /// Sets the maximum number of nested sequences, maps, named values and
+    /// enum variants allowed while deserializing and returns self.
+    ///
+    /// Deserialization recurses once for each level of nesting, so payloads
+    /// from untrusted sources could otherwise overflow the stack. Exceeding
+    /// this limit returns [`Error::TooDeeplyNested`].
+    ///
+    /// The default maximum depth is 128. This default also applies when
+    /// deserializing using [`de::SymbolMap`].
+    #[inline]
+    pub const fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets the [`Limits`] enforced while deserializing and returns self.
+    ///
+    /// These are the same limits accepted by [`validate()`]. Each limit is
+    /// checked before the data it guards is read or allocated, and exceeding
+    /// a limit returns the [`Error`] variant documented on its setter. When
+    /// deserializing a stream of payloads, the limits apply to each payload
+    /// individually.
+    ///
+    /// ```rust
+    /// let limits = pot::Limits::new().max_bytes_length(4);
+    /// let config = pot::Config::new().limits(limits);
+    /// let payload = pot::to_vec(&"hello").unwrap();
+    /// assert!(matches!(
+    ///     config.deserialize::<String>(&payload),
+    ///     Err(pot::Error::BytesTooLong)
+    /// ));
+    /// ```
+    #[inline]
+    pub const fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Sets whether deserialization errors include the position at which they
+    /// occurred and returns self.
+    ///
+    /// When enabled, every error that occurs while deserializing a value is
+    /// returned as [`Error::Positioned`], which records the number of bytes
+    /// read and the path to the value being deserialized, such as
+    /// `.entries[1532].timestamp`. These are available through
+    /// [`Error::offset()`] and [`Error::path()`], and the original error
+    /// through [`Error::without_position()`].
+    ///
+    /// The path is only built once an error has occurred, so enabling this has
+    /// little cost when deserializing succeeds. This is disabled by default.
+    ///
+    /// ```rust
+    /// use serde_derive::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Entry {
+    ///     timestamp: u16,
+    /// }
+    ///
+    /// #[derive(Serialize, Deserialize)]
+    /// struct Log {
+    ///     entries: Vec<Entry>,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct NarrowEntry {
+    ///     timestamp: u8,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct NarrowLog {
+    ///     entries: Vec<NarrowEntry>,
+    /// }
+    ///
+    /// let log = Log {
+    ///     entries: vec![Entry { timestamp: 1 }, Entry { timestamp: 1000 }],
+    /// };
+    /// let payload = pot::to_vec(&log).unwrap();
+    /// let err = pot::Config::new()
+    ///     .error_positions(true)
+    ///     .deserialize::<NarrowLog>(&payload)
+    ///     .unwrap_err();
+    /// assert_eq!(err.path(), Some(".entries[1].timestamp"));
+    /// assert_eq!(err.offset(), Some(payload.len()));
+    /// ```
+    #[inline]
+    pub const fn error_positions(mut self, error_positions: bool) -> Self {
+        self.error_positions = error_positions;
+        self
+    }
+
+    /// Sets whether deserialization recovers from problems with individual
+    /// values and returns self.
+    ///
+    /// When enabled, the following problems are recovered from rather than
+    /// returned as errors:
+    ///
+    /// - A value of the wrong kind, such as a string where an integer is
+    ///   expected, is skipped and the default value of the expected type is
+    ///   used. Sequences and maps are replaced by empty ones.
+    /// - An integer or float that cannot be converted to the expected type
+    ///   without losing data is replaced by the default value.
+    /// - An unknown [`Special`](format::Special) is treated as `None`.
+    /// - Strings containing invalid UTF-8 are converted lossily.
+    ///
+    /// Each recovered problem is recorded as a [`de::Diagnostic`], which are
+    /// returned by
+    /// [`deserialize_with_diagnostics()`](Self::deserialize_with_diagnostics)
+    /// and
+    /// [`deserialize_from_with_diagnostics()`](Self::deserialize_from_with_diagnostics).
+    /// Other functions discard the diagnostics. Malformed payloads, such as
+    /// truncated data or invalid atom headers, still return errors.
+    ///
+    /// ```rust
+    /// use serde_derive::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Stored {
+    ///     id: u64,
+    ///     count: &'static str,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug, PartialEq)]
+    /// struct Record {
+    ///     id: u8,
+    ///     count: u32,
+    /// }
+    ///
+    /// let payload = pot::to_vec(&Stored {
+    ///     id: 1000,
+    ///     count: "many",
+    /// })
+    /// .unwrap();
+    /// let (record, diagnostics) = pot::Config::new()
+    ///     .lenient(true)
+    ///     .deserialize_with_diagnostics::<Record>(&payload)
+    ///     .unwrap();
+    /// assert_eq!(record, Record { id: 0, count: 0 });
+    /// assert_eq!(diagnostics.len(), 2);
+    /// ```
+    #[inline]
+    pub const fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+
     /// Sets the compatibility mode for serializing and returns self.
     pub const fn compatibility(mut self, compatibilty: Compatibility) -> Self {
         self.compatibility = compatibilty;
         self
     }
 
+    /// Sets whether values are serialized canonically and returns self.
+    ///
+    /// When enabled, values that are equal always serialize to identical
+    /// bytes:
+    ///
+    /// - Map entries are sorted by their encoded keys, regardless of the
+    ///   iteration order of the map being serialized.
+    /// - Maps and sequences of unknown length are written with their lengths.
+    /// - Integers and floats are written using the smallest lossless encoding,
+    ///   and all NaN values are encoded identically.
+    /// - Symbol ids are assigned in the order that symbols appear in the
+    ///   output.
+    ///
+    /// Struct fields are written in their declaration order. Canonical
+    /// serialization is slower than the default, as map entries must be
+    /// buffered in order to be sorted.
+    pub const fn canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Sets the minimum length of strings and bytes values that are interned
+    /// while serializing, and returns self.
+    ///
+    /// When set, the first occurrence of each string or bytes value that is
+    /// at least `min_length` bytes long is stored, and later occurrences of
+    /// the same value are written as a short reference to it. This can
+    /// greatly reduce the size of payloads that repeat large values, such as
+    /// identifiers or file paths. References are resolved transparently when
+    /// deserializing.
+    ///
+    /// Interning requires [`Compatibility::V6`] or later, and has no effect
+    /// with earlier compatibility settings or when
+    /// [`canonical()`](Self::canonical) serialization is enabled. The default
+    /// is `None`, which disables interning.
+    ///
+    /// ```rust
+    /// use pot::{Compatibility, Config};
+    ///
+    /// let paths = vec!["/usr/share/pot/example.txt"; 16];
+    /// let config = Config::new()
+    ///     .compatibility(Compatibility::V6)
+    ///     .intern_bytes(Some(8));
+    /// let interned = config.serialize(&paths).unwrap();
+    /// assert!(interned.len() < pot::to_vec(&paths).unwrap().len() / 4);
+    ///
+    /// let decoded: Vec<String> = config.deserialize(&interned).unwrap();
+    /// assert_eq!(decoded, paths);
+    /// ```
+    pub const fn intern_bytes(mut self, min_length: Option<usize>) -> Self {
+        self.intern_bytes = min_length;
+        self
+    }
+
+    /// Sets whether sequences of `u8`s are serialized as bytes, and returns
+    /// self.
+    ///
+    /// Serde serializes types like `Vec<u8>` and `[u8; N]` as sequences,
+    /// which Pot encodes using at least two bytes per element. When enabled,
+    /// non-empty sequences and tuples whose elements are all `u8`s are
+    /// written as a single bytes value instead, as if they had been
+    /// serialized using `serde_bytes`. Sequences containing any other values
+    /// are written normally.
+    ///
+    /// When enabled, deserializing a sequence or tuple also accepts bytes in
+    /// place of a sequence of `u8`s. Strings are encoded as bytes too, so
+    /// they are only accepted as sequences when this is enabled.
+    /// Deserializing from a [`Value`] always accepts bytes in place of a
+    /// sequence of `u8`s.
+    /// Deserializing a [`Value`] from a payload produces [`Value::Bytes`] for
+    /// these sequences, or [`Value::String`] if the bytes happen to be valid
+    /// UTF-8.
+    ///
+    /// Compact bytes require [`Compatibility::V6`] or later, and have no
+    /// effect with earlier compatibility settings. The default is `false`.
+    ///
+    /// ```rust
+    /// use pot::{Compatibility, Config};
+    ///
+    /// let data = vec![42_u8; 1024];
+    /// let config = Config::new()
+    ///     .compatibility(Compatibility::V6)
+    ///     .compact_bytes(true);
+    /// let compact = config.serialize(&data).unwrap();
+    /// assert!(compact.len() < 1024 + 8);
+    /// assert!(pot::to_vec(&data).unwrap().len() >= 2048);
+    ///
+    /// let decoded: Vec<u8> = config.deserialize(&compact).unwrap();
+    /// assert_eq!(decoded, data);
+    /// ```
+    pub const fn compact_bytes(mut self, compact_bytes: bool) -> Self {
+        self.compact_bytes = compact_bytes;
+        self
+    }
+
+    /// Sets how structs are serialized and returns self.
+    ///
+    /// With [`StructRepr::Positional`] and [`StructRepr::Numbered`], the names
+    /// of struct fields are not written. This produces much smaller payloads,
+    /// but requires the serializing and deserializing types to agree on the
+    /// order or ids of their fields. See [`StructRepr`] for more information.
+    /// The default is [`StructRepr::Named`].
+    ///
+    /// ```rust
+    /// use pot::{Config, StructRepr};
+    /// use serde_derive::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    /// struct Point {
+    ///     x: i32,
+    ///     y: i32,
+    /// }
+    ///
+    /// let config = Config::new().struct_representation(StructRepr::Positional);
+    /// let positional = config.serialize(&Point { x: 1, y: 2 }).unwrap();
+    /// assert!(positional.len() < pot::to_vec(&Point { x: 1, y: 2 }).unwrap().len());
+    ///
+    /// let decoded: Point = config.deserialize(&positional).unwrap();
+    /// assert_eq!(decoded, Point { x: 1, y: 2 });
+    /// ```
+    pub const fn struct_representation(mut self, representation: StructRepr) -> Self {
+        self.struct_representation = representation;
+        self
+    }
+
     /// Deserializes a value from a slice using the configured options.
     #[inline]
     pub fn deserialize<'de, T>(&self, serialized: &'de [u8]) -> Result<T>
     where
         T: Deserialize<'de>,
     {
-        let mut deserializer = de::Deserializer::from_slice(serialized, self.allocation_budget)?;
-        let t = T::deserialize(&mut deserializer)?;
+        self.deserialize_with_diagnostics(serialized)
+            .map(|(value, _)| value)
+    }
+
+    /// Deserializes a value from a slice using the configured options,
+    /// returning the problems recovered from in [lenient mode](Self::lenient).
+    ///
+    /// When lenient mode is disabled, the returned diagnostics are always
+    /// empty.
+    #[inline]
+    pub fn deserialize_with_diagnostics<'de, T>(
+        &self,
+        serialized: &'de [u8],
+    ) -> Result<(T, Vec<de::Diagnostic>)>
+    where
+        T: Deserialize<'de>,
+    {
+        self.limits.check_input_length(serialized.len())?;
+        let mut deserializer = de::Deserializer::from_slice(serialized, self.allocation_budget)?
+            .with_max_depth(self.max_depth)
+            .with_limits(self.limits)
+            .with_error_positions(self.error_positions)
+            .with_compact_bytes(self.compact_bytes)
+            .with_lenient(self.lenient);
+        let t = T::deserialize(&mut deserializer).map_err(|err| deserializer.locate(err, None))?;
         if deserializer.end_of_input() {
-            Ok(t)
+            Ok((t, deserializer.take_diagnostics()))
         } else {
             Err(Error::TrailingBytes)
         }
@@ -162,17 +505,104 @@ impl Config {
 
     /// Deserializes a value from a [`Read`] implementer using the configured
     /// options.
+    #[cfg(feature = "std")]
     #[inline]
     pub fn deserialize_from<T, R: Read>(&self, reader: R) -> Result<T>
     where
         T: DeserializeOwned,
     {
-        let mut deserializer = de::Deserializer::from_read(
-            IoReader::new(reader),
+        self.deserialize_from_with_diagnostics(reader)
+            .map(|(value, _)| value)
+    }
+
+    /// Deserializes a value from a [`Read`] implementer using the configured
+    /// options, returning the problems recovered from in [lenient
+    /// mode](Self::lenient).
+    ///
+    /// When lenient mode is disabled, the returned diagnostics are always
+    /// empty.
+    #[cfg(feature = "std")]
+    #[inline]
+    pub fn deserialize_from_with_diagnostics<T, R: Read>(
+        &self,
+        reader: R,
+    ) -> Result<(T, Vec<de::Diagnostic>)>
+    where
+        T: DeserializeOwned,
+    {
+        let mut deserializer = de::Deserializer::new(
+            IoReader::new(reader).with_max_input_bytes(self.limits.max_input_bytes),
             SymbolMapRef::temporary(),
             self.allocation_budget,
-        )?;
-        T::deserialize(&mut deserializer)
+        )?
+        .with_max_depth(self.max_depth)
+        .with_limits(self.limits)
+        .with_error_positions(self.error_positions)
+        .with_compact_bytes(self.compact_bytes)
+        .with_lenient(self.lenient);
+        let t = T::deserialize(&mut deserializer).map_err(|err| deserializer.locate(err, None))?;
+        Ok((t, deserializer.take_diagnostics()))
+    }
+
+    /// Deserializes a value from an [`AsyncRead`](futures_io::AsyncRead)
+    /// implementer using the configured options.
+    ///
+    /// The payload is read atom by atom until the value is complete, so no
+    /// bytes after the value are consumed and the stream does not need to be
+    /// closed. The encoded value is buffered before it is deserialized, and
+    /// the buffer counts towards the [allocation
+    /// budget](Self::allocation_budget). Because reads are never larger than
+    /// the remainder of the current atom, wrapping unbuffered readers in a
+    /// buffered reader is recommended.
+    ///
+    /// Payloads serialized with [`Compatibility::Full`] that contain enum
+    /// variants without associated data cannot be located reliably, and
+    /// should be serialized with [`Compatibility::V4`] or later.
+    ///
+    /// This function requires the `async` feature.
+    #[cfg(feature = "async")]
+    pub async fn deserialize_from_async<T, R>(&self, mut reader: R) -> Result<T>
+    where
+        T: DeserializeOwned,
+        R: futures_io::AsyncRead + Unpin,
+    {
+        let payload = async_io::read_payload(&mut reader, self).await?;
+        self.deserialize(&payload)
+    }
+
+    /// Returns an iterator that deserializes a series of back-to-back Pot
+    /// payloads from a slice using the configured options.
+    ///
+    /// The [allocation budget](Self::allocation_budget) applies to each
+    /// payload individually.
+    #[must_use]
+    pub fn deserialize_stream<'de, T>(
+        &self,
+        serialized: &'de [u8],
+    ) -> StreamDeserializer<reader::SliceReader<'de>, T>
+    where
+        T: Deserialize<'de>,
+    {
+        StreamDeserializer::new(reader::SliceReader::from(serialized), self.clone())
+    }
+
+    /// Returns an iterator that deserializes a series of back-to-back Pot
+    /// payloads from a [`Read`] implementer using the configured options.
+    ///
+    /// Each payload is read into memory before it is deserialized, and no
+    /// bytes after a payload are read until the next item is requested. The
+    /// [allocation budget](Self::allocation_budget) applies to each payload
+    /// individually, including the buffered payload itself.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn deserialize_stream_from<T, R: Read>(
+        &self,
+        reader: R,
+    ) -> StreamDeserializer<IoReader<R>, T>
+    where
+        T: DeserializeOwned,
+    {
+        StreamDeserializer::new(IoReader::new(reader), self.clone())
     }
 
     /// Serializes a value to a `Vec` using the configured options.
@@ -189,22 +619,43 @@ impl Config {
     pub fn serialize_into<T, W>(&self, value: &T, writer: W) -> Result<()>
     where
         T: Serialize,
-        W: WriteBytesExt,
+        W: Write,
     {
-        let mut serializer = ser::Serializer::new_with_compatibility(writer, self.compatibility)?;
+        let mut serializer = ser::Serializer::new_with_compatibility(writer, self.compatibility)?
+            .with_canonical(self.canonical)
+            .with_interned_bytes(self.intern_bytes)
+            .with_compact_bytes(self.compact_bytes)
+            .with_struct_representation(self.struct_representation);
         value.serialize(&mut serializer)
     }
+
+    /// Serializes a value to an [`AsyncWrite`](futures_io::AsyncWrite)
+    /// implementer using the configured options.
+    ///
+    /// The value is serialized into memory before being written. Like
+    /// [`serialize_into()`](Self::serialize_into), `writer` is not flushed.
+    ///
+    /// This function requires the `async` feature.
+    #[cfg(feature = "async")]
+    pub async fn serialize_into_async<T, W>(&self, value: &T, mut writer: W) -> Result<()>
+    where
+        T: Serialize,
+        W: futures_io::AsyncWrite + Unpin,
+    {
+        let payload = self.serialize(value)?;
+        async_io::write_all(&mut writer, &payload).await
+    }
 }
 
 /// Compatibility settings for Pot.
-#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Clone, Copy)]
 #[non_exhaustive]
 pub enum Compatibility {
     /// Serializes data that is compatible with all versions of Pot
     /// deserializers.
     ///
-    /// This format does not support [`Value`](crate::Value) deserialization of
-    /// enum variants without associated data. See [`V5`](Self::V5) for more
+    /// This format does not support [`Value`] deserialization of
+    /// enum variants without associated data. See [`V4`](Self::V4) for more
     /// information.
     Full,
     /// Serializes data in the default format
@@ -217,12 +668,49 @@ pub enum Compatibility {
     /// This will be the default compatibility setting in `v4.0` and later. All
     /// versions after `v3.0.1` are able to read this updated format.
     V4,
+    /// Serializes data using all features of [`V4`](Self::V4), and adds
+    /// support for sequences whose length is not known when serialization
+    /// begins.
+    ///
+    /// With earlier compatibility settings, serializing a sequence of unknown
+    /// length returns [`Error::SequenceSizeMustBeKnown`]. Payloads in this
+    /// format can only be read by versions of Pot that support it.
+    V5,
+    /// Serializes data using all features of [`V5`](Self::V5), and adds
+    /// support for referring to previously written strings and bytes values,
+    /// for writing sequences of `u8`s as bytes, and for packed arrays of
+    /// numbers.
+    ///
+    /// This compatibility setting is required by [`Config::intern_bytes`],
+    /// [`Config::compact_bytes`] and [`Packed`](packed::Packed). Payloads in
+    /// this format can only be read by versions of Pot that support it.
+    V6,
+    /// Serializes data using all features of [`V6`](Self::V6), and stores
+    /// small integers in a single byte.
+    ///
+    /// Signed integers from -8 through 7 and unsigned integers from 0 through
+    /// 15 are stored in the atom header rather than in the bytes following
+    /// it. Payloads in this format can only be read by versions of Pot that
+    /// support it.
+    V7,
 }
 
 impl Compatibility {
     const fn const_default() -> Self {
         Self::Full
     }
+
+    /// Returns the version written in the header of payloads serialized with
+    /// this compatibility setting.
+    pub(crate) const fn header_version(self) -> u8 {
+        match self {
+            Compatibility::Full => format::INITIAL_VERSION,
+            Compatibility::V4 => format::V4_VERSION,
+            Compatibility::V5 => format::V5_VERSION,
+            Compatibility::V6 => format::V6_VERSION,
+            Compatibility::V7 => format::V7_VERSION,
+        }
+    }
 }
 
 impl Default for Compatibility {
@@ -231,5 +719,79 @@ impl Default for Compatibility {
     }
 }
 
+/// How structs and struct variants are serialized.
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy)]
+#[non_exhaustive]
+pub enum StructRepr {
+    /// Structs are serialized as maps of field names to values.
+    ///
+    /// Field names are written as symbols, so each name is only written in
+    /// full once per payload.
+    #[default]
+    Named,
+    /// Structs are serialized as sequences of their field values, in the
+    /// order the fields are declared.
+    ///
+    /// Only trailing fields may be skipped while serializing, such as by
+    /// using `#[serde(skip_serializing_if = "Option::is_none")]`. When
+    /// deserializing, fields missing from the end of the sequence are
+    /// deserialized as `None` if they are optional, or use their default
+    /// value if they have `#[serde(default)]`. Values beyond the last field
+    /// are ignored.
+    ///
+    /// Structs serialized this way are deserialized automatically, regardless
+    /// of the [`Config`] used. Older versions of Pot are unable to
+    /// deserialize them.
+    Positional,
+    /// Structs are serialized as maps of numeric field ids to values.
+    ///
+    /// Fields renamed to `#` followed by a number, such as
+    /// `#[serde(rename = "#3")]`, use that number as their id. All other
+    /// fields use their index in the struct, counting the fields that are
+    /// serialized or skipped with `skip_serializing_if`. Explicit ids allow
+    /// fields to be renamed and reordered without affecting previously
+    /// serialized data, similar to the field numbers of Protocol Buffers.
+    /// Each field's id must be unique within its struct.
+    ///
+    /// Structs serialized this way are deserialized automatically, regardless
+    /// of the [`Config`] used. Ids that don't belong to any field are
+    /// deserialized as the field name `#` followed by the id, which serde
+    /// ignores unless `#[serde(deny_unknown_fields)]` is used. Older versions
+    /// of Pot are unable to deserialize them.
+    ///
+    /// ```rust
+    /// use pot::{Config, StructRepr};
+    /// use serde_derive::{Deserialize, Serialize};
+    ///
+    /// #[derive(Serialize)]
+    /// struct Stored {
+    ///     #[serde(rename = "#1")]
+    ///     id: u64,
+    ///     #[serde(rename = "#2")]
+    ///     name: &'static str,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug, PartialEq)]
+    /// struct Renamed {
+    ///     #[serde(rename = "#2")]
+    ///     title: String,
+    ///     #[serde(rename = "#1")]
+    ///     key: u64,
+    /// }
+    ///
+    /// let config = Config::new().struct_representation(StructRepr::Numbered);
+    /// let payload = config.serialize(&Stored { id: 1, name: "pot" }).unwrap();
+    /// let decoded: Renamed = config.deserialize(&payload).unwrap();
+    /// assert_eq!(
+    ///     decoded,
+    ///     Renamed {
+    ///         title: String::from("pot"),
+    ///         key: 1
+    ///     }
+    /// );
+    /// ```
+    Numbered,
+}
+
 #[cfg(test)]
 mod tests;
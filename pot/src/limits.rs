@@ -0,0 +1,185 @@
+use crate::format::Kind;
+use crate::Error;
+
+/// Limits on the amount of data accepted when reading a Pot payload.
+///
+/// Every limit defaults to being unlimited. Limits are intended to protect
+/// against hostile or malformed payloads from untrusted sources.
+///
+/// ```rust
+/// let limits = pot::Limits::new().max_atoms(1_000).max_symbols(64);
+/// let payload = pot::to_vec(&["hello", "world"]).unwrap();
+/// let summary = pot::validate(&payload, &limits).unwrap();
+/// assert_eq!(summary.atoms, 3);
+/// ```
+#[must_use]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[allow(clippy::struct_field_names)]
+pub struct Limits {
+    pub(crate) max_atoms: usize,
+    pub(crate) max_symbols: usize,
+    pub(crate) max_symbol_bytes: usize,
+    pub(crate) max_bytes_length: usize,
+    pub(crate) max_container_length: usize,
+    pub(crate) max_input_bytes: usize,
+}
+
+impl Default for Limits {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Limits {
+    /// Returns limits that allow any payload.
+    pub const fn new() -> Self {
+        Self {
+            max_atoms: usize::MAX,
+            max_symbols: usize::MAX,
+            max_symbol_bytes: usize::MAX,
+            max_bytes_length: usize::MAX,
+            max_container_length: usize::MAX,
+            max_input_bytes: usize::MAX,
+        }
+    }
+
+    /// Sets the maximum number of atoms a payload may contain.
+    ///
+    /// Exceeding this limit returns [`Error::TooManyAtoms`](crate::Error::TooManyAtoms).
+    #[inline]
+    pub const fn max_atoms(mut self, max_atoms: usize) -> Self {
+        self.max_atoms = max_atoms;
+        self
+    }
+
+    /// Sets the maximum number of unique symbols a payload may define.
+    ///
+    /// Exceeding this limit returns
+    /// [`Error::TooManySymbols`](crate::Error::TooManySymbols).
+    #[inline]
+    pub const fn max_symbols(mut self, max_symbols: usize) -> Self {
+        self.max_symbols = max_symbols;
+        self
+    }
+
+    /// Sets the maximum total length, in bytes, of all symbols known while
+    /// reading a payload. When a persistent
+    /// [`de::SymbolMap`](crate::de::SymbolMap) is used, this includes symbols
+    /// defined by earlier payloads.
+    ///
+    /// Exceeding this limit returns
+    /// [`Error::TooManySymbolBytes`](crate::Error::TooManySymbolBytes).
+    #[inline]
+    pub const fn max_symbol_bytes(mut self, max_symbol_bytes: usize) -> Self {
+        self.max_symbol_bytes = max_symbol_bytes;
+        self
+    }
+
+    /// Sets the maximum length, in bytes, of a single string or bytes value.
+    ///
+    /// Exceeding this limit returns
+    /// [`Error::BytesTooLong`](crate::Error::BytesTooLong).
+    #[inline]
+    pub const fn max_bytes_length(mut self, max_bytes_length: usize) -> Self {
+        self.max_bytes_length = max_bytes_length;
+        self
+    }
+
+    /// Sets the maximum number of elements in a single sequence or entries in
+    /// a single map.
+    ///
+    /// Exceeding this limit returns
+    /// [`Error::ContainerTooLong`](crate::Error::ContainerTooLong).
+    #[inline]
+    pub const fn max_container_length(mut self, max_container_length: usize) -> Self {
+        self.max_container_length = max_container_length;
+        self
+    }
+
+    /// Sets the maximum number of bytes a payload may occupy, including its
+    /// header.
+    ///
+    /// When reading from a [`Read`](std::io::Read) implementer, reading stops
+    /// as soon as this many bytes have been read. Exceeding this limit returns
+    /// [`Error::TooManyInputBytes`](crate::Error::TooManyInputBytes).
+    #[inline]
+    pub const fn max_input_bytes(mut self, max_input_bytes: usize) -> Self {
+        self.max_input_bytes = max_input_bytes;
+        self
+    }
+
+    /// Returns an error if a payload of `length` bytes exceeds
+    /// [`max_input_bytes`](Self::max_input_bytes).
+    pub(crate) const fn check_input_length(&self, length: usize) -> Result<(), Error> {
+        if length > self.max_input_bytes {
+            Err(Error::TooManyInputBytes)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Enforces [`Limits`] while a payload is being read. Each check is performed
+/// before the data it guards is read.
+#[derive(Debug)]
+pub(crate) struct LimitTracker {
+    limits: Limits,
+    atoms: usize,
+    symbol_bytes: usize,
+}
+
+impl LimitTracker {
+    /// Returns a tracker for `limits`, where `symbol_bytes` is the total
+    /// length of the symbols already known.
+    pub(crate) const fn new(limits: Limits, symbol_bytes: usize) -> Self {
+        Self {
+            limits,
+            atoms: 0,
+            symbol_bytes,
+        }
+    }
+
+    /// Records that an atom with `kind` and `arg` has been read, before its
+    /// contents are read.
+    pub(crate) fn atom(&mut self, kind: Kind, arg: u64) -> Result<(), Error> {
+        self.atoms += 1;
+        if self.atoms > self.limits.max_atoms {
+            return Err(Error::TooManyAtoms);
+        }
+
+        let length = usize::try_from(arg).unwrap_or(usize::MAX);
+        match kind {
+            Kind::Bytes if length > self.limits.max_bytes_length => Err(Error::BytesTooLong),
+            Kind::Sequence | Kind::Map if length > self.limits.max_container_length => {
+                Err(Error::ContainerTooLong)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records that the element at `index` of a container of unknown length
+    /// is about to be read.
+    pub(crate) const fn dynamic_element(&self, index: usize) -> Result<(), Error> {
+        if index >= self.limits.max_container_length {
+            Err(Error::ContainerTooLong)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Records that a new symbol of `length` bytes is about to be read, when
+    /// `known_symbols` symbols are already known.
+    pub(crate) fn symbol(&mut self, known_symbols: usize, length: usize) -> Result<(), Error> {
+        if known_symbols >= self.limits.max_symbols {
+            return Err(Error::TooManySymbols);
+        }
+
+        self.symbol_bytes = self.symbol_bytes.saturating_add(length);
+        if self.symbol_bytes > self.limits.max_symbol_bytes {
+            Err(Error::TooManySymbolBytes)
+        } else {
+            Ok(())
+        }
+    }
+}
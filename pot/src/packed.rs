@@ -0,0 +1,883 @@
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::FusedIterator;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::slice::ChunksExact;
+
+use serde::de::value::{BorrowedBytesDeserializer, BytesDeserializer};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use self::sealed::Sealed;
+use crate::format::{Float, Integer, Nucleus, PackedType};
+use crate::{Error, Value};
+
+/// The newtype name used to request a packed array from the serializer, and
+/// to request an entire packed array from a deserializer.
+pub(crate) const PACKED_ARRAY_NAME: &str = "$pot::packed";
+
+/// A sequence of numbers that is serialized as a packed array.
+///
+/// With [`Compatibility::V6`](crate::Compatibility::V6) or later, the elements
+/// are written as a single [`Special::PackedArray`](crate::format::Special::PackedArray)
+/// containing their type followed by their bytes in little endian, rather than
+/// as one atom per element. Decoding an array whose element type matches `T`
+/// is a single copy. With older compatibility levels, a regular sequence is
+/// written.
+///
+/// Every element is stored using the full width of `T`. Arrays of small
+/// integers or of floats that are exactly representable with fewer bits may be
+/// larger when packed, as Pot normally stores those values in fewer bytes.
+///
+/// Packed arrays can be deserialized as any sequence type, and `Packed<T>` can
+/// be deserialized from any sequence of numbers that fit in `T`.
+///
+/// ```rust
+/// use pot::packed::Packed;
+/// use pot::{Compatibility, Config};
+///
+/// let config = Config::new().compatibility(Compatibility::V6);
+/// let embedding = Packed((0..128).map(|i| i as f32 / 3.).collect::<Vec<_>>());
+/// let packed = config.serialize(&embedding).unwrap();
+/// assert!(packed.len() < config.serialize(&embedding.0).unwrap().len());
+///
+/// let decoded: Packed<f32> = config.deserialize(&packed).unwrap();
+/// assert_eq!(decoded, embedding);
+/// let decoded: Vec<f64> = config.deserialize(&packed).unwrap();
+/// assert_eq!(decoded[3], f64::from(1_f32));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Packed<T>(pub Vec<T>);
+
+impl<T> Deref for Packed<T> {
+    type Target = Vec<T>;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> DerefMut for Packed<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Vec<T>> for Packed<T> {
+    #[inline]
+    fn from(elements: Vec<T>) -> Self {
+        Self(elements)
+    }
+}
+
+impl<T> From<Packed<T>> for Vec<T> {
+    #[inline]
+    fn from(packed: Packed<T>) -> Self {
+        packed.0
+    }
+}
+
+impl<T> Serialize for Packed<T>
+where
+    T: PackedElement,
+{
+    #[inline]
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(PACKED_ARRAY_NAME, &self.0)
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Packed<T>
+where
+    T: PackedElement,
+{
+    #[inline]
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(PackedVisitor(PhantomData))
+    }
+}
+
+struct PackedVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for PackedVisitor<T>
+where
+    T: PackedElement,
+{
+    type Value = Packed<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a sequence of numbers")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut elements = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        match seq.next_element_seed(PackedSeed::<T>::new())? {
+            Some(PackedOrElement::Packed(array)) => {
+                if let Some(slice) = array.as_slice::<T>() {
+                    return Ok(Packed(slice.to_vec()));
+                }
+
+                // The elements are a different type, so each one is converted
+                // individually.
+                let mut converted =
+                    PackedElements::<A::Error>::new(array.element_type, array.as_bytes());
+                while let Some(element) = converted.next_element()? {
+                    elements.push(element);
+                }
+            }
+            Some(PackedOrElement::Element(first)) => {
+                elements.push(first);
+                while let Some(element) = seq.next_element()? {
+                    elements.push(element);
+                }
+            }
+            None => {}
+        }
+        Ok(Packed(elements))
+    }
+}
+
+/// A number that can be stored in a packed array.
+///
+/// This trait is sealed and is implemented for `i8`, `i16`, `i32`, `i64`,
+/// `u8`, `u16`, `u32`, `u64`, `f32` and `f64`.
+pub trait PackedElement: sealed::Sealed + Copy + Serialize + DeserializeOwned {
+    /// The element type of packed arrays containing this type.
+    const ELEMENT_TYPE: PackedType;
+}
+
+mod sealed {
+    use alloc::vec::Vec;
+
+    pub trait Sealed {
+        fn from_le_slice(bytes: &[u8]) -> Self;
+        fn extend_le(self, bytes: &mut Vec<u8>);
+    }
+}
+
+macro_rules! impl_packed_element {
+    ($primitive:ident, $element_type:ident) => {
+        impl sealed::Sealed for $primitive {
+            #[inline]
+            fn from_le_slice(bytes: &[u8]) -> Self {
+                let mut le = [0; core::mem::size_of::<$primitive>()];
+                le.copy_from_slice(bytes);
+                $primitive::from_le_bytes(le)
+            }
+
+            #[inline]
+            fn extend_le(self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.to_le_bytes());
+            }
+        }
+
+        impl PackedElement for $primitive {
+            const ELEMENT_TYPE: PackedType = PackedType::$element_type;
+        }
+    };
+}
+
+impl_packed_element!(i8, I8);
+impl_packed_element!(i16, I16);
+impl_packed_element!(i32, I32);
+impl_packed_element!(i64, I64);
+impl_packed_element!(u8, U8);
+impl_packed_element!(u16, U16);
+impl_packed_element!(u32, U32);
+impl_packed_element!(u64, U64);
+impl_packed_element!(f32, F32);
+impl_packed_element!(f64, F64);
+
+/// A packed array of numbers, as contained in [`Value::Packed`].
+///
+/// ```rust
+/// use pot::packed::Packed;
+/// use pot::{Compatibility, Config, Value};
+///
+/// let config = Config::new().compatibility(Compatibility::V6);
+/// let payload = config.serialize(&Packed(vec![1_u32, 2, 3])).unwrap();
+/// let value: Value<'_> = config.deserialize(&payload).unwrap();
+/// let elements = value.as_packed::<u32>().unwrap();
+/// assert_eq!(elements.len(), 3);
+/// assert_eq!(elements.get(1), Some(2));
+/// assert_eq!(elements.to_vec(), [1, 2, 3]);
+/// assert!(value.as_packed::<i32>().is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PackedArray<'a> {
+    element_type: PackedType,
+    bytes: Cow<'a, [u8]>,
+}
+
+impl<'a> PackedArray<'a> {
+    /// Returns a packed array containing a copy of `elements`.
+    #[must_use]
+    pub fn new<T: PackedElement>(elements: &[T]) -> Self {
+        let mut bytes = Vec::with_capacity(elements.len() * T::ELEMENT_TYPE.width());
+        for element in elements {
+            element.extend_le(&mut bytes);
+        }
+        Self {
+            element_type: T::ELEMENT_TYPE,
+            bytes: Cow::Owned(bytes),
+        }
+    }
+
+    /// Returns a packed array of `element_type` elements stored in little
+    /// endian in `bytes`.
+    ///
+    /// Returns [`Error::InvalidPackedArray`] if the length of `bytes` is not a
+    /// multiple of the element width.
+    pub fn from_bytes(
+        element_type: PackedType,
+        bytes: impl Into<Cow<'a, [u8]>>,
+    ) -> Result<Self, Error> {
+        let bytes = bytes.into();
+        if bytes.len() % element_type.width() == 0 {
+            Ok(Self {
+                element_type,
+                bytes,
+            })
+        } else {
+            Err(Error::InvalidPackedArray)
+        }
+    }
+
+    /// Returns the type of the elements in this array.
+    #[must_use]
+    pub const fn element_type(&self) -> PackedType {
+        self.element_type
+    }
+
+    /// Returns the elements of this array stored in little endian.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the number of elements in this array.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len() / self.element_type.width()
+    }
+
+    /// Returns true if this array contains no elements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns a view of the elements as `T`, or `None` if the elements of
+    /// this array are not `T`.
+    #[must_use]
+    pub fn as_slice<T: PackedElement>(&self) -> Option<PackedSlice<'_, T>> {
+        (self.element_type == T::ELEMENT_TYPE).then(|| PackedSlice {
+            bytes: &self.bytes,
+            _element: PhantomData,
+        })
+    }
+
+    /// Returns an iterator over the elements of this array as [`Value`]s.
+    #[must_use]
+    pub fn values(&self) -> PackedValues<'_> {
+        PackedValues {
+            element_type: self.element_type,
+            chunks: self.bytes.chunks_exact(self.element_type.width()),
+        }
+    }
+
+    /// Converts `self` to a `'static` lifetime by cloning the elements if
+    /// they are borrowed.
+    #[must_use]
+    pub fn into_static(self) -> PackedArray<'static> {
+        PackedArray {
+            element_type: self.element_type,
+            bytes: Cow::Owned(self.bytes.into_owned()),
+        }
+    }
+
+    /// Converts `self` to a `'static` lifetime by cloning the elements.
+    #[must_use]
+    pub fn to_static(&self) -> PackedArray<'static> {
+        PackedArray {
+            element_type: self.element_type,
+            bytes: Cow::Owned(self.bytes.to_vec()),
+        }
+    }
+
+    pub(crate) fn numbers(&self) -> impl Iterator<Item = PackedNumber> + '_ {
+        self.bytes
+            .chunks_exact(self.element_type.width())
+            .map(|bytes| PackedNumber::from_le_slice(self.element_type, bytes))
+    }
+}
+
+impl<T: PackedElement> From<&[T]> for PackedArray<'_> {
+    #[inline]
+    fn from(elements: &[T]) -> Self {
+        Self::new(elements)
+    }
+}
+
+impl Serialize for PackedArray<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_newtype_struct(PACKED_ARRAY_NAME, &PackedNumbers(self))
+    }
+}
+
+/// Serializes the elements of a [`PackedArray`] as a sequence.
+struct PackedNumbers<'a, 'b>(&'a PackedArray<'b>);
+
+impl Serialize for PackedNumbers<'_, '_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+        for number in self.0.numbers() {
+            seq.serialize_element(&number)?;
+        }
+        seq.end()
+    }
+}
+
+/// A view of the elements of a [`PackedArray`] as `T`.
+#[derive(Debug)]
+pub struct PackedSlice<'a, T> {
+    bytes: &'a [u8],
+    _element: PhantomData<T>,
+}
+
+impl<T> Clone for PackedSlice<'_, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for PackedSlice<'_, T> {}
+
+impl<'a, T: PackedElement> PackedSlice<'a, T> {
+    /// Returns the number of elements.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.bytes.len() / T::ELEMENT_TYPE.width()
+    }
+
+    /// Returns true if there are no elements.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the element at `index`, or `None` if `index` is out of bounds.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<T> {
+        let width = T::ELEMENT_TYPE.width();
+        let start = index.checked_mul(width)?;
+        self.bytes.get(start..start + width).map(T::from_le_slice)
+    }
+
+    /// Returns an iterator over the elements.
+    #[must_use]
+    pub fn iter(&self) -> PackedIter<'a, T> {
+        PackedIter {
+            chunks: self.bytes.chunks_exact(T::ELEMENT_TYPE.width()),
+            _element: PhantomData,
+        }
+    }
+
+    /// Returns the elements as a `Vec`.
+    #[must_use]
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
+    }
+
+    /// Returns the elements stored in little endian.
+    #[must_use]
+    pub const fn as_bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl<'a, T: PackedElement> IntoIterator for PackedSlice<'a, T> {
+    type IntoIter = PackedIter<'a, T>;
+    type Item = T;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: PackedElement> IntoIterator for &PackedSlice<'a, T> {
+    type IntoIter = PackedIter<'a, T>;
+    type Item = T;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the elements of a [`PackedSlice`].
+#[derive(Debug, Clone)]
+pub struct PackedIter<'a, T> {
+    chunks: ChunksExact<'a, u8>,
+    _element: PhantomData<T>,
+}
+
+impl<T: PackedElement> Iterator for PackedIter<'_, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks.next().map(T::from_le_slice)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl<T: PackedElement> DoubleEndedIterator for PackedIter<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.chunks.next_back().map(T::from_le_slice)
+    }
+}
+
+impl<T: PackedElement> ExactSizeIterator for PackedIter<'_, T> {}
+
+impl<T: PackedElement> FusedIterator for PackedIter<'_, T> {}
+
+/// An iterator over the elements of a [`PackedArray`] as [`Value`]s.
+#[derive(Debug, Clone)]
+pub struct PackedValues<'a> {
+    element_type: PackedType,
+    chunks: ChunksExact<'a, u8>,
+}
+
+impl Iterator for PackedValues<'_> {
+    type Item = Value<'static>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.chunks
+            .next()
+            .map(|bytes| PackedNumber::from_le_slice(self.element_type, bytes).into_value())
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.chunks.size_hint()
+    }
+}
+
+impl DoubleEndedIterator for PackedValues<'_> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.chunks
+            .next_back()
+            .map(|bytes| PackedNumber::from_le_slice(self.element_type, bytes).into_value())
+    }
+}
+
+impl ExactSizeIterator for PackedValues<'_> {}
+
+impl FusedIterator for PackedValues<'_> {}
+
+/// A single element of a packed array.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum PackedNumber {
+    I8(i8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    F32(f32),
+    F64(f64),
+}
+
+/// Evaluates `$body` with `$value` bound to the number contained in `$number`.
+macro_rules! with_number {
+    ($number:expr, $value:ident => $body:expr) => {
+        match $number {
+            PackedNumber::I8($value) => $body,
+            PackedNumber::I16($value) => $body,
+            PackedNumber::I32($value) => $body,
+            PackedNumber::I64($value) => $body,
+            PackedNumber::U8($value) => $body,
+            PackedNumber::U16($value) => $body,
+            PackedNumber::U32($value) => $body,
+            PackedNumber::U64($value) => $body,
+            PackedNumber::F32($value) => $body,
+            PackedNumber::F64($value) => $body,
+        }
+    };
+}
+
+impl PackedNumber {
+    pub(crate) fn from_le_slice(element_type: PackedType, bytes: &[u8]) -> Self {
+        match element_type {
+            PackedType::I8 => Self::I8(i8::from_le_slice(bytes)),
+            PackedType::I16 => Self::I16(i16::from_le_slice(bytes)),
+            PackedType::I32 => Self::I32(i32::from_le_slice(bytes)),
+            PackedType::I64 => Self::I64(i64::from_le_slice(bytes)),
+            PackedType::U8 => Self::U8(u8::from_le_slice(bytes)),
+            PackedType::U16 => Self::U16(u16::from_le_slice(bytes)),
+            PackedType::U32 => Self::U32(u32::from_le_slice(bytes)),
+            PackedType::U64 => Self::U64(u64::from_le_slice(bytes)),
+            PackedType::F32 => Self::F32(f32::from_le_slice(bytes)),
+            PackedType::F64 => Self::F64(f64::from_le_slice(bytes)),
+        }
+    }
+
+    pub(crate) fn element_type(self) -> PackedType {
+        fn element_type_of<T: PackedElement>(_number: T) -> PackedType {
+            T::ELEMENT_TYPE
+        }
+
+        with_number!(self, number => element_type_of(number))
+    }
+
+    pub(crate) fn extend_le(self, bytes: &mut Vec<u8>) {
+        with_number!(self, number => number.extend_le(bytes));
+    }
+
+    pub(crate) fn into_value(self) -> Value<'static> {
+        with_number!(self, number => Value::from(number))
+    }
+
+    pub(crate) fn into_nucleus(self) -> Nucleus<'static> {
+        match self {
+            Self::I8(number) => Nucleus::Integer(Integer::from(number)),
+            Self::I16(number) => Nucleus::Integer(Integer::from(number)),
+            Self::I32(number) => Nucleus::Integer(Integer::from(number)),
+            Self::I64(number) => Nucleus::Integer(Integer::from(number)),
+            Self::U8(number) => Nucleus::Integer(Integer::from(number)),
+            Self::U16(number) => Nucleus::Integer(Integer::from(number)),
+            Self::U32(number) => Nucleus::Integer(Integer::from(number)),
+            Self::U64(number) => Nucleus::Integer(Integer::from(number)),
+            Self::F32(number) => Nucleus::Float(Float::from(number)),
+            Self::F64(number) => Nucleus::Float(Float::from(number)),
+        }
+    }
+
+    fn visit<'de, V, E>(self, visitor: V) -> Result<V::Value, E>
+    where
+        V: Visitor<'de>,
+        E: de::Error,
+    {
+        with_number!(self, number => {
+            IntoDeserializer::<E>::into_deserializer(number).deserialize_any(visitor)
+        })
+    }
+}
+
+impl Serialize for PackedNumber {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        with_number!(*self, number => number.serialize(serializer))
+    }
+}
+
+/// The elements of a packed array, deserialized one at a time.
+///
+/// Requesting a newtype struct named [`PACKED_ARRAY_NAME`] from an element
+/// hands over all of the remaining elements at once. See [`PackedSeed`].
+pub(crate) struct PackedElements<'de, 'b, E> {
+    element_type: PackedType,
+    bytes: PackedBytes<'de, 'b>,
+    _error: PhantomData<E>,
+}
+
+#[derive(Clone, Copy)]
+enum PackedBytes<'de, 'b> {
+    Borrowed(&'de [u8]),
+    Transient(&'b [u8]),
+}
+
+impl<'de, 'b, E> PackedElements<'de, 'b, E> {
+    /// Returns the elements of a packed array that are borrowed for `'de`.
+    pub(crate) const fn borrowed(element_type: PackedType, bytes: &'de [u8]) -> Self {
+        Self {
+            element_type,
+            bytes: PackedBytes::Borrowed(bytes),
+            _error: PhantomData,
+        }
+    }
+
+    /// Returns the elements of a packed array that can't be borrowed for
+    /// `'de`.
+    pub(crate) const fn new(element_type: PackedType, bytes: &'b [u8]) -> Self {
+        Self {
+            element_type,
+            bytes: PackedBytes::Transient(bytes),
+            _error: PhantomData,
+        }
+    }
+
+    fn remaining(&self) -> &[u8] {
+        match self.bytes {
+            PackedBytes::Borrowed(bytes) | PackedBytes::Transient(bytes) => bytes,
+        }
+    }
+
+    fn next_number(&mut self) -> Option<PackedNumber> {
+        let width = self.element_type.width();
+        let number = PackedNumber::from_le_slice(self.element_type, self.remaining().get(..width)?);
+        self.bytes = match self.bytes {
+            PackedBytes::Borrowed(bytes) => PackedBytes::Borrowed(&bytes[width..]),
+            PackedBytes::Transient(bytes) => PackedBytes::Transient(&bytes[width..]),
+        };
+        Some(number)
+    }
+
+    fn take_all(&mut self) -> PackedBytes<'de, 'b> {
+        let all = self.bytes;
+        self.bytes = PackedBytes::Transient(&[]);
+        all
+    }
+}
+
+impl<'de, E> SeqAccess<'de> for PackedElements<'de, '_, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if self.remaining().is_empty() {
+            Ok(None)
+        } else {
+            seed.deserialize(PackedElementDeserializer(self)).map(Some)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining().len() / self.element_type.width())
+    }
+}
+
+struct PackedElementDeserializer<'a, 'de, 'b, E>(&'a mut PackedElements<'de, 'b, E>);
+
+impl<'de, E> Deserializer<'de> for PackedElementDeserializer<'_, 'de, '_, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct enum
+        identifier ignored_any
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let number = self
+            .0
+            .next_number()
+            .ok_or_else(|| E::invalid_length(0, &"a packed array element"))?;
+        number.visit(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        if name == PACKED_ARRAY_NAME {
+            visitor.visit_seq(PackedParts::<E> {
+                element_type: Some(self.0.element_type),
+                bytes: Some(self.0.take_all()),
+                _error: PhantomData,
+            })
+        } else {
+            visitor.visit_newtype_struct(self)
+        }
+    }
+}
+
+/// The element type code and bytes of an entire packed array, provided to
+/// [`PackedSeed`] as a sequence.
+struct PackedParts<'de, 'b, E> {
+    element_type: Option<PackedType>,
+    bytes: Option<PackedBytes<'de, 'b>>,
+    _error: PhantomData<E>,
+}
+
+impl<'de, E> SeqAccess<'de> for PackedParts<'de, '_, E>
+where
+    E: de::Error,
+{
+    type Error = E;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        if let Some(element_type) = self.element_type.take() {
+            seed.deserialize(IntoDeserializer::<E>::into_deserializer(
+                element_type.code(),
+            ))
+            .map(Some)
+        } else {
+            match self.bytes.take() {
+                Some(PackedBytes::Borrowed(bytes)) => seed
+                    .deserialize(BorrowedBytesDeserializer::new(bytes))
+                    .map(Some),
+                Some(PackedBytes::Transient(bytes)) => {
+                    seed.deserialize(BytesDeserializer::new(bytes)).map(Some)
+                }
+                None => Ok(None),
+            }
+        }
+    }
+}
+
+/// The result of deserializing a sequence element with [`PackedSeed`].
+pub(crate) enum PackedOrElement<'de, T> {
+    /// The sequence is a packed array, and all of its elements were returned.
+    Packed(PackedArray<'de>),
+    /// A single element of the sequence.
+    Element(T),
+}
+
+/// Deserializes the next element of a sequence, or the entire sequence if it
+/// is a packed array being read by Pot.
+pub(crate) struct PackedSeed<T>(PhantomData<T>);
+
+impl<T> PackedSeed<T> {
+    pub(crate) const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<'de, T> DeserializeSeed<'de> for PackedSeed<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = PackedOrElement<'de, T>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(PACKED_ARRAY_NAME, self)
+    }
+}
+
+impl<'de, T> Visitor<'de> for PackedSeed<T>
+where
+    T: Deserialize<'de>,
+{
+    type Value = PackedOrElement<'de, T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a sequence element")
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(PackedOrElement::Element)
+    }
+
+    fn visit_seq<A>(self, mut parts: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let code = parts
+            .next_element::<u64>()?
+            .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+        let bytes = parts
+            .next_element_seed(PackedBytesSeed)?
+            .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+        PackedType::from_code(code)
+            .and_then(|element_type| PackedArray::from_bytes(element_type, bytes))
+            .map(PackedOrElement::Packed)
+            .map_err(de::Error::custom)
+    }
+}
+
+struct PackedBytesSeed;
+
+impl<'de> DeserializeSeed<'de> for PackedBytesSeed {
+    type Value = Cow<'de, [u8]>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+impl<'de> Visitor<'de> for PackedBytesSeed {
+    type Value = Cow<'de, [u8]>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("packed array bytes")
+    }
+
+    fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Cow::Borrowed(v))
+    }
+
+    fn visit_bytes<E>(self, v: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Cow::Owned(v.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, v: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Cow::Owned(v))
+    }
+}
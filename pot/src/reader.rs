@@ -1,12 +1,13 @@
-use std::fmt::Debug;
+use alloc::format;
+use alloc::vec::Vec;
+use core::fmt::Debug;
+#[cfg(feature = "std")]
 use std::io::Read;
 
-use byteorder::ReadBytesExt;
-
 use crate::Error;
 
 /// A reader that can temporarily buffer bytes read.
-pub trait Reader<'de>: ReadBytesExt {
+pub trait Reader<'de> {
     /// Reads exactly `length` bytes.
     ///
     /// If the reader supports borrowing bytes, [`BufferedBytes::Data`] should
@@ -18,6 +19,23 @@ pub trait Reader<'de>: ReadBytesExt {
         length: usize,
         scratch: &mut Vec<u8>,
     ) -> Result<BufferedBytes<'de>, Error>;
+
+    /// Reads exactly `buffer.len()` bytes into `buffer`.
+    ///
+    /// Returns [`Error::Eof`] if the input ends before `buffer` is filled.
+    #[inline]
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let mut scratch = Vec::new();
+        let bytes = self.buffered_read_bytes(buffer.len(), &mut scratch)?;
+        buffer.copy_from_slice(bytes.as_slice(&scratch));
+        Ok(())
+    }
+
+    /// Returns the number of bytes that have been read, if known.
+    #[inline]
+    fn bytes_read(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// Bytes that have been read into a buffer.
@@ -46,6 +64,7 @@ impl BufferedBytes<'_> {
 #[allow(clippy::module_name_repetitions)]
 pub struct SliceReader<'a> {
     pub(crate) data: &'a [u8],
+    position: usize,
 }
 
 impl<'a> SliceReader<'a> {
@@ -62,10 +81,18 @@ impl<'a> SliceReader<'a> {
     pub const fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
+
+    /// Returns the number of bytes that have been read since this reader was
+    /// created.
+    #[must_use]
+    #[inline]
+    pub const fn position(&self) -> usize {
+        self.position
+    }
 }
 
 impl<'a> Debug for SliceReader<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("SliceReader")
             .field(
                 "preview",
@@ -78,7 +105,7 @@ impl<'a> Debug for SliceReader<'a> {
 impl<'a> From<&'a [u8]> for SliceReader<'a> {
     #[inline]
     fn from(data: &'a [u8]) -> Self {
-        Self { data }
+        Self { data, position: 0 }
     }
 }
 
@@ -97,22 +124,68 @@ impl<'de> Reader<'de> for SliceReader<'de> {
         _scratch: &mut Vec<u8>,
     ) -> Result<BufferedBytes<'de>, Error> {
         if length > self.data.len() {
+            self.position += self.data.len();
             self.data = &self.data[self.data.len()..];
             Err(Error::Eof)
         } else {
             let (start, remaining) = self.data.split_at(length);
+            self.position += length;
             self.data = remaining;
             Ok(BufferedBytes::Data(start))
         }
     }
+
+    /// Reads as many bytes as are available into `buffer`. If the input ends
+    /// before `buffer` is filled, the remaining bytes are set to zero.
+    ///
+    /// Previous versions of Pot have always read fixed-size values from
+    /// slices this way, and changing it would cause some payloads that were
+    /// written with [`Compatibility::Full`](crate::Compatibility::Full) to no
+    /// longer be readable.
+    #[inline]
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let (to_copy, remaining) = self.data.split_at(self.data.len().min(buffer.len()));
+        buffer[..to_copy.len()].copy_from_slice(to_copy);
+        buffer[to_copy.len()..].fill(0);
+        self.position += to_copy.len();
+        self.data = remaining;
+        Ok(())
+    }
+
+    #[inline]
+    fn bytes_read(&self) -> Option<usize> {
+        Some(self.position)
+    }
 }
 
+/// A [`SliceReader`] that returns [`Error::Eof`] when a fixed-size read extends
+/// past the end of the input, rather than reading zeroes.
+pub(crate) struct StrictSliceReader<'a, 'de>(pub(crate) &'a mut SliceReader<'de>);
+
+impl<'de> Reader<'de> for StrictSliceReader<'_, 'de> {
+    #[inline]
+    fn buffered_read_bytes(
+        &mut self,
+        length: usize,
+        scratch: &mut Vec<u8>,
+    ) -> Result<BufferedBytes<'de>, Error> {
+        self.0.buffered_read_bytes(length, scratch)
+    }
+
+    #[inline]
+    fn bytes_read(&self) -> Option<usize> {
+        self.0.bytes_read()
+    }
+}
+
+#[cfg(feature = "std")]
 impl<'a> Read for SliceReader<'a> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         let remaining_length = self.data.len();
         let (to_copy, remaining) = self.data.split_at(remaining_length.min(buf.len()));
         buf[..to_copy.len()].copy_from_slice(to_copy);
+        self.position += to_copy.len();
         self.data = remaining;
         Ok(to_copy.len())
     }
@@ -123,31 +196,91 @@ impl<'a> Read for SliceReader<'a> {
     }
 }
 
-/// A reader over [`ReadBytesExt`].
+impl<'de> Reader<'de> for &'de [u8] {
+    #[inline]
+    fn buffered_read_bytes(
+        &mut self,
+        length: usize,
+        _scratch: &mut Vec<u8>,
+    ) -> Result<BufferedBytes<'de>, Error> {
+        if length > self.len() {
+            *self = &self[self.len()..];
+            Err(Error::Eof)
+        } else {
+            let (start, remaining) = self.split_at(length);
+            *self = remaining;
+            Ok(BufferedBytes::Data(start))
+        }
+    }
+}
+
+/// A reader over [`Read`].
+#[cfg(feature = "std")]
 #[allow(clippy::module_name_repetitions)]
-pub struct IoReader<R: ReadBytesExt> {
+pub struct IoReader<R: Read> {
     pub(crate) reader: R,
+    position: usize,
+    max_input_bytes: usize,
 }
-impl<R: ReadBytesExt> IoReader<R> {
+
+#[cfg(feature = "std")]
+impl<R: Read> IoReader<R> {
     pub(crate) const fn new(reader: R) -> Self {
-        Self { reader }
+        Self {
+            reader,
+            position: 0,
+            max_input_bytes: usize::MAX,
+        }
+    }
+
+    /// Limits the number of bytes read through [`Reader`] to
+    /// `max_input_bytes` and returns self.
+    #[must_use]
+    pub(crate) const fn with_max_input_bytes(mut self, max_input_bytes: usize) -> Self {
+        self.max_input_bytes = max_input_bytes;
+        self
+    }
+
+    /// Records that `length` bytes are about to be read, returning
+    /// [`Error::TooManyInputBytes`] if the input limit would be exceeded.
+    fn consume_input(&mut self, length: usize) -> Result<(), Error> {
+        self.position = self
+            .position
+            .checked_add(length)
+            .filter(|position| *position <= self.max_input_bytes)
+            .ok_or(Error::TooManyInputBytes)?;
+        Ok(())
     }
 }
 
-impl<'de, R: ReadBytesExt> Reader<'de> for IoReader<R> {
+#[cfg(feature = "std")]
+impl<'de, R: Read> Reader<'de> for IoReader<R> {
     #[inline]
     fn buffered_read_bytes(
         &mut self,
         length: usize,
         scratch: &mut Vec<u8>,
     ) -> Result<BufferedBytes<'de>, Error> {
+        self.consume_input(length)?;
         scratch.resize(length, 0);
         self.reader.read_exact(scratch)?;
         Ok(BufferedBytes::Scratch)
     }
+
+    #[inline]
+    fn read_into(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        self.consume_input(buffer.len())?;
+        Ok(self.reader.read_exact(buffer)?)
+    }
+
+    #[inline]
+    fn bytes_read(&self) -> Option<usize> {
+        Some(self.position)
+    }
 }
 
-impl<R: ReadBytesExt> Read for IoReader<R> {
+#[cfg(feature = "std")]
+impl<R: Read> Read for IoReader<R> {
     #[inline]
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         self.reader.read(buf)
@@ -175,6 +308,7 @@ impl<R: ReadBytesExt> Read for IoReader<R> {
 }
 
 #[test]
+#[cfg(feature = "std")]
 fn slice_reader_pub_methods() {
     let mut reader = SliceReader::from(&b"a"[..]);
     assert_eq!(reader.len(), 1);
@@ -183,5 +317,6 @@ fn slice_reader_pub_methods() {
 
     assert_eq!(reader.len(), 0);
     assert!(reader.is_empty());
+    assert_eq!(reader.position(), 1);
     assert_eq!(<&[u8]>::from(reader), b"");
 }
@@ -1,37 +1,53 @@
-use std::borrow::Cow;
-use std::cmp::Ordering;
-use std::fmt::{Debug, Display};
-use std::io::Write;
-use std::ops::Range;
-use std::usize;
-
-use byteorder::WriteBytesExt;
+use alloc::borrow::Cow;
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{Debug, Display};
+use core::ops::Range;
+use core::usize;
+
 use serde::de::{SeqAccess, Visitor};
 use serde::{ser, Deserialize, Serialize};
 #[cfg(feature = "tracing")]
 use tracing::instrument;
 
-use crate::format::{self, Kind, Special, INITIAL_VERSION, V4_VERSION};
-use crate::{Compatibility, Error, Result};
+use crate::format::{self, Kind, PackedType, Special};
+use crate::packed::{PackedNumber, PACKED_ARRAY_NAME};
+use crate::writer::Write;
+use crate::{Compatibility, Error, Result, StructRepr};
 
 /// A Pot serializer.
-pub struct Serializer<'a, W: WriteBytesExt> {
+pub struct Serializer<'a, W: Write> {
     symbol_map: SymbolMapRef<'a>,
     compatibility: Compatibility,
+    canonical: bool,
+    interned_bytes: Option<BytesInterner>,
+    compact_bytes: bool,
+    struct_representation: StructRepr,
+    pack_next_sequence: bool,
     output: W,
     bytes_written: usize,
 }
 
-impl<'a, W: WriteBytesExt> Debug for Serializer<'a, W> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Tracks the bytes values that have been interned by a [`Serializer`].
+#[derive(Debug)]
+struct BytesInterner {
+    min_length: usize,
+    ids: BTreeMap<Vec<u8>, u64>,
+}
+
+impl<'a, W: Write> Debug for Serializer<'a, W> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.debug_struct("Serializer")
             .field("symbol_map", &self.symbol_map)
+            .field("canonical", &self.canonical)
             .field("bytes_written", &self.bytes_written)
             .finish()
     }
 }
 
-impl<'a, W: WriteBytesExt> Serializer<'a, W> {
+impl<'a, W: Write> Serializer<'a, W> {
     /// Returns a new serializer outputting written bytes into `output`.
     #[inline]
     pub fn new(output: W) -> Result<Self> {
@@ -53,23 +69,153 @@ impl<'a, W: WriteBytesExt> Serializer<'a, W> {
         symbol_map: SymbolMapRef<'a>,
         compatibility: Compatibility,
     ) -> Result<Self> {
-        let bytes_written = format::write_header(
-            &mut output,
-            match compatibility {
-                Compatibility::Full => INITIAL_VERSION,
-                Compatibility::V4 => V4_VERSION,
-            },
-        )?;
+        let bytes_written = format::write_header(&mut output, compatibility.header_version())?;
         Ok(Self {
             compatibility,
+            canonical: false,
+            interned_bytes: None,
+            compact_bytes: false,
+            struct_representation: StructRepr::Named,
+            pack_next_sequence: false,
             symbol_map,
             output,
             bytes_written,
         })
     }
 
+    /// Enables or disables canonical serialization. See
+    /// [`Config::canonical`](crate::Config::canonical).
+    pub(crate) fn with_canonical(mut self, canonical: bool) -> Self {
+        self.canonical = canonical;
+        self
+    }
+
+    /// Enables interning bytes values that are at least `min_length` bytes
+    /// long. See [`Config::intern_bytes`](crate::Config::intern_bytes).
+    pub(crate) fn with_interned_bytes(mut self, min_length: Option<usize>) -> Self {
+        self.interned_bytes = min_length
+            .filter(|_| self.compatibility >= Compatibility::V6)
+            .map(|min_length| BytesInterner {
+                min_length,
+                ids: BTreeMap::new(),
+            });
+        self
+    }
+
+    /// Enables writing sequences of `u8`s as bytes. See
+    /// [`Config::compact_bytes`](crate::Config::compact_bytes).
+    pub(crate) fn with_compact_bytes(mut self, compact_bytes: bool) -> Self {
+        self.compact_bytes = compact_bytes && self.compatibility >= Compatibility::V6;
+        self
+    }
+
+    /// Sets how structs are serialized. See
+    /// [`Config::struct_representation`](crate::Config::struct_representation).
+    pub(crate) fn with_struct_representation(mut self, representation: StructRepr) -> Self {
+        self.struct_representation = representation;
+        self
+    }
+
+    /// Writes `value` in a single [`Kind::Int`] atom header if it is small
+    /// enough and [`Compatibility::V7`] is supported. Returns false if nothing
+    /// was written.
+    fn write_inline_int(&mut self, value: impl TryInto<i8>) -> Result<bool> {
+        match value.try_into() {
+            Ok(value)
+                if self.compatibility >= Compatibility::V7
+                    && (format::MIN_INLINE_INT..=format::MAX_INLINE_INT).contains(&value) =>
+            {
+                self.bytes_written += format::write_inline_int(&mut self.output, value)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Writes `value` in a single [`Kind::UInt`] atom header if it is small
+    /// enough and [`Compatibility::V7`] is supported. Returns false if nothing
+    /// was written.
+    fn write_inline_uint(&mut self, value: impl TryInto<u8>) -> Result<bool> {
+        match value.try_into() {
+            Ok(value)
+                if self.compatibility >= Compatibility::V7 && value <= format::MAX_INLINE_UINT =>
+            {
+                self.bytes_written += format::write_inline_uint(&mut self.output, value)?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Writes the numeric id of a struct field. See [`StructRepr::Numbered`].
+    fn write_field_id(&mut self, id: u64) -> Result<()> {
+        if !self.write_inline_uint(id)? {
+            self.bytes_written += format::write_u64(&mut self.output, id)?;
+        }
+        Ok(())
+    }
+
+    /// Writes `bytes`, emitting a reference instead if the same bytes have
+    /// already been interned.
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        match &mut self.interned_bytes {
+            Some(interner) if !self.canonical && bytes.len() >= interner.min_length => {
+                if let Some(id) = interner.ids.get(bytes) {
+                    self.bytes_written +=
+                        format::write_special(&mut self.output, Special::BytesReference)?;
+                    let id = *id;
+                    if !self.write_inline_uint(id)? {
+                        self.bytes_written += format::write_u64(&mut self.output, id)?;
+                    }
+                    return Ok(());
+                }
+
+                let id = interner.ids.len() as u64;
+                interner.ids.insert(bytes.to_vec(), id);
+                self.bytes_written +=
+                    format::write_special(&mut self.output, Special::InternedBytes)?;
+            }
+            _ => {}
+        }
+        self.bytes_written += format::write_bytes(&mut self.output, bytes)?;
+        Ok(())
+    }
+
+    /// Returns a serializer that writes a fragment of a canonical payload
+    /// into a buffer, deferring symbol id assignment until the fragment is
+    /// written with [`write_deferred()`](Self::write_deferred).
+    fn deferred(&self) -> Serializer<'static, Vec<u8>> {
+        Serializer {
+            symbol_map: SymbolMapRef::Deferred(Vec::new()),
+            compatibility: self.compatibility,
+            canonical: true,
+            interned_bytes: None,
+            compact_bytes: self.compact_bytes,
+            struct_representation: self.struct_representation,
+            pack_next_sequence: false,
+            output: Vec::new(),
+            bytes_written: 0,
+        }
+    }
+
+    /// Writes `fragment`, assigning ids to the symbols it contains.
+    fn write_deferred(&mut self, fragment: &Deferred) -> Result<()> {
+        let mut written = 0;
+        for (range, symbol) in &fragment.symbols {
+            self.output
+                .write_all(&fragment.bytes[written..range.start])?;
+            self.bytes_written += range.start - written;
+            self.write_symbol(symbol)?;
+            written = range.end;
+        }
+        self.output.write_all(&fragment.bytes[written..])?;
+        self.bytes_written += fragment.bytes.len() - written;
+        Ok(())
+    }
+
     #[cfg_attr(feature = "tracing", instrument)]
     fn write_symbol(&mut self, symbol: &'static str) -> Result<()> {
+        let start = self.bytes_written;
         let registered_symbol = self.symbol_map.find_or_add(symbol);
         if registered_symbol.new {
             // The arg is the length followed by a 0 bit.
@@ -77,6 +223,9 @@ impl<'a, W: WriteBytesExt> Serializer<'a, W> {
             self.bytes_written += format::write_atom_header(&mut self.output, Kind::Symbol, arg)?;
             self.output.write_all(symbol.as_bytes())?;
             self.bytes_written += symbol.len();
+            if let SymbolMapRef::Deferred(deferred) = &mut self.symbol_map {
+                deferred.push((start..self.bytes_written, symbol));
+            }
         } else {
             // When a symbol was already emitted, just emit the id followed by a 1 bit.
             self.bytes_written += format::write_atom_header(
@@ -89,14 +238,14 @@ impl<'a, W: WriteBytesExt> Serializer<'a, W> {
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serializer<'a, W> {
+impl<'de, 'a: 'de, W: Write + 'a> ser::Serializer for &'de mut Serializer<'a, W> {
     type Error = Error;
     type Ok = ();
     type SerializeMap = MapSerializer<'de, 'a, W>;
-    type SerializeSeq = Self;
+    type SerializeSeq = SequenceSerializer<'de, 'a, W>;
     type SerializeStruct = MapSerializer<'de, 'a, W>;
     type SerializeStructVariant = MapSerializer<'de, 'a, W>;
-    type SerializeTuple = Self;
+    type SerializeTuple = SequenceSerializer<'de, 'a, W>;
     type SerializeTupleStruct = Self;
     type SerializeTupleVariant = Self;
 
@@ -115,76 +264,100 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_i8(self, v: i8) -> Result<()> {
-        self.bytes_written += format::write_i8(&mut self.output, v)?;
+        if !self.write_inline_int(v)? {
+            self.bytes_written += format::write_i8(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_i16(self, v: i16) -> Result<()> {
-        self.bytes_written += format::write_i16(&mut self.output, v)?;
+        if !self.write_inline_int(v)? {
+            self.bytes_written += format::write_i16(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_i32(self, v: i32) -> Result<()> {
-        self.bytes_written += format::write_i32(&mut self.output, v)?;
+        if !self.write_inline_int(v)? {
+            self.bytes_written += format::write_i32(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.bytes_written += format::write_i64(&mut self.output, v)?;
+        if !self.write_inline_int(v)? {
+            self.bytes_written += format::write_i64(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_i128(self, v: i128) -> Result<()> {
-        self.bytes_written += format::write_i128(&mut self.output, v)?;
+        if !self.write_inline_int(v)? {
+            self.bytes_written += format::write_i128(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.bytes_written += format::write_u8(&mut self.output, v)?;
+        if !self.write_inline_uint(v)? {
+            self.bytes_written += format::write_u8(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_u16(self, v: u16) -> Result<()> {
-        self.bytes_written += format::write_u16(&mut self.output, v)?;
+        if !self.write_inline_uint(v)? {
+            self.bytes_written += format::write_u16(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.bytes_written += format::write_u32(&mut self.output, v)?;
+        if !self.write_inline_uint(v)? {
+            self.bytes_written += format::write_u32(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.bytes_written += format::write_u64(&mut self.output, v)?;
+        if !self.write_inline_uint(v)? {
+            self.bytes_written += format::write_u64(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_u128(self, v: u128) -> Result<()> {
-        self.bytes_written += format::write_u128(&mut self.output, v)?;
+        if !self.write_inline_uint(v)? {
+            self.bytes_written += format::write_u128(&mut self.output, v)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_f32(self, v: f32) -> Result<()> {
+        if self.canonical && v.is_nan() {
+            self.bytes_written += format::write_canonical_nan(&mut self.output)?;
+            return Ok(());
+        }
         self.bytes_written += format::write_f32(&mut self.output, v)?;
         Ok(())
     }
@@ -192,6 +365,10 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_f64(self, v: f64) -> Result<()> {
+        if self.canonical && v.is_nan() {
+            self.bytes_written += format::write_canonical_nan(&mut self.output)?;
+            return Ok(());
+        }
         self.bytes_written += format::write_f64(&mut self.output, v)?;
         Ok(())
     }
@@ -199,22 +376,22 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_char(self, v: char) -> Result<()> {
-        self.bytes_written += format::write_u32(&mut self.output, v as u32)?;
+        if !self.write_inline_uint(v as u32)? {
+            self.bytes_written += format::write_u32(&mut self.output, v as u32)?;
+        }
         Ok(())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_str(self, v: &str) -> Result<()> {
-        self.bytes_written += format::write_str(&mut self.output, v)?;
-        Ok(())
+        self.write_bytes(v.as_bytes())
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_bytes(self, v: &[u8]) -> Result<()> {
-        self.bytes_written += format::write_bytes(&mut self.output, v)?;
-        Ok(())
+        self.write_bytes(v)
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
@@ -263,11 +440,20 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
 
     #[cfg_attr(feature = "tracing", instrument(level = "trace", skip(value)))]
     #[inline]
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(self)
+        if name == PACKED_ARRAY_NAME && self.compatibility >= Compatibility::V6 {
+            // The sequence contained in this value is written as a packed
+            // array if all of its elements are numbers of the same type.
+            self.pack_next_sequence = true;
+            let result = value.serialize(&mut *self);
+            self.pack_next_sequence = false;
+            result
+        } else {
+            value.serialize(self)
+        }
     }
 
     #[cfg_attr(feature = "tracing", instrument(level = "trace", skip(value)))]
@@ -282,7 +468,7 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
     where
         T: ?Sized + Serialize,
     {
-        format::write_named(&mut self.output)?;
+        self.bytes_written += format::write_named(&mut self.output)?;
         self.write_symbol(variant)?;
         value.serialize(&mut *self)?;
         Ok(())
@@ -291,10 +477,27 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq> {
-        let len = len.ok_or(Error::SequenceSizeMustBeKnown)?;
-        self.bytes_written +=
-            format::write_atom_header(&mut self.output, Kind::Sequence, len as u64)?;
-        Ok(self)
+        let packed = core::mem::take(&mut self.pack_next_sequence);
+        let compact_bytes = self.compact_bytes;
+        let mut sequence = SequenceSerializer {
+            serializer: self,
+            known_length: true,
+            buffered: None,
+            pending_numbers: None,
+        };
+        if packed || compact_bytes {
+            // The sequence is written once it is known whether all of its
+            // elements are numbers of the same type.
+            sequence.pending_numbers = Some(PendingNumbers {
+                length: len,
+                packed,
+                element_type: None,
+                bytes: Vec::new(),
+            });
+        } else {
+            sequence.begin(len)?;
+        }
+        Ok(sequence)
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
@@ -310,38 +513,53 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
         _name: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        self.serialize_seq(Some(len))
+        self.bytes_written +=
+            format::write_atom_header(&mut self.output, Kind::Sequence, len as u64)?;
+        Ok(self)
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_tuple_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        format::write_named(&mut self.output)?;
+        self.bytes_written += format::write_named(&mut self.output)?;
         self.write_symbol(variant)?;
-        self.serialize_seq(Some(len))
+        self.serialize_tuple_struct(name, len)
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
-        if let Some(len) = len {
+        if self.canonical {
+            // The entries are sorted and written once they have all been
+            // serialized.
+            Ok(MapSerializer {
+                serializer: self,
+                known_length: true,
+                sorted: Some(SortedEntries::default()),
+                fields: None,
+            })
+        } else if let Some(len) = len {
             self.bytes_written +=
                 format::write_atom_header(&mut self.output, Kind::Map, len as u64)?;
             Ok(MapSerializer {
                 serializer: self,
                 known_length: true,
+                sorted: None,
+                fields: None,
             })
         } else {
             self.bytes_written += format::write_special(&mut self.output, Special::DynamicMap)?;
             Ok(MapSerializer {
                 serializer: self,
                 known_length: false,
+                sorted: None,
+                fields: None,
             })
         }
     }
@@ -349,7 +567,23 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
     #[cfg_attr(feature = "tracing", instrument)]
     #[inline]
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
-        self.serialize_map(Some(len))
+        let representation = self.struct_representation;
+        let kind = if representation == StructRepr::Positional {
+            Kind::Sequence
+        } else {
+            Kind::Map
+        };
+        self.bytes_written += format::write_atom_header(&mut self.output, kind, len as u64)?;
+        Ok(MapSerializer {
+            serializer: self,
+            known_length: true,
+            sorted: None,
+            fields: (representation != StructRepr::Named).then_some(StructFields {
+                representation,
+                index: 0,
+                skipped: false,
+            }),
+        })
     }
 
     #[cfg_attr(feature = "tracing", instrument)]
@@ -361,13 +595,81 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::Serializer for &'de mut Serialize
         variant: &'static str,
         len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        format::write_named(&mut self.output)?;
+        self.bytes_written += format::write_named(&mut self.output)?;
         self.write_symbol(variant)?;
         self.serialize_struct(name, len)
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeSeq for &'de mut Serializer<'a, W> {
+/// Serializes sequence values.
+pub struct SequenceSerializer<'de, 'a, W: Write> {
+    serializer: &'de mut Serializer<'a, W>,
+    known_length: bool,
+    buffered: Option<(Serializer<'static, Vec<u8>>, u64)>,
+    pending_numbers: Option<PendingNumbers>,
+}
+
+/// The elements of a sequence that have been serialized so far, while every
+/// element has been a number of the same type.
+struct PendingNumbers {
+    length: Option<usize>,
+    /// If true, the numbers are written as a packed array. Otherwise, only
+    /// `u8`s are accepted and they are written as bytes.
+    packed: bool,
+    element_type: Option<PackedType>,
+    bytes: Vec<u8>,
+}
+
+impl PendingNumbers {
+    /// Appends `number` if it can be stored with the numbers pending so far.
+    fn push(&mut self, number: PackedNumber) -> bool {
+        let element_type = number.element_type();
+        if (self.packed || element_type == PackedType::U8)
+            && *self.element_type.get_or_insert(element_type) == element_type
+        {
+            number.extend_le(&mut self.bytes);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl<'de, 'a: 'de, W: Write + 'a> SequenceSerializer<'de, 'a, W> {
+    /// Writes the start of a sequence containing `len` elements.
+    fn begin(&mut self, len: Option<usize>) -> Result<()> {
+        let serializer = &mut *self.serializer;
+        if let Some(len) = len {
+            serializer.bytes_written +=
+                format::write_atom_header(&mut serializer.output, Kind::Sequence, len as u64)?;
+        } else if serializer.canonical {
+            // The length is written once all elements have been buffered, to
+            // ensure the same sequence is always encoded identically.
+            self.buffered = Some((serializer.deferred(), 0));
+        } else if serializer.compatibility >= Compatibility::V5 {
+            serializer.bytes_written +=
+                format::write_special(&mut serializer.output, Special::DynamicSequence)?;
+            self.known_length = false;
+        } else {
+            return Err(Error::SequenceSizeMustBeKnown);
+        }
+        Ok(())
+    }
+
+    fn write_element<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        if let Some((buffer, count)) = &mut self.buffered {
+            *count += 1;
+            value.serialize(buffer)
+        } else {
+            value.serialize(&mut *self.serializer)
+        }
+    }
+}
+
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeSeq for SequenceSerializer<'de, 'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -376,16 +678,58 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeSeq for &'de mut Seriali
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        if let Some(mut pending) = self.pending_numbers.take() {
+            if value
+                .serialize(NumberProbe)
+                .is_ok_and(|number| pending.push(number))
+            {
+                self.pending_numbers = Some(pending);
+                return Ok(());
+            }
+
+            // This element can't be stored with the previous elements, so the
+            // sequence is written normally.
+            self.begin(pending.length)?;
+            if let Some(element_type) = pending.element_type {
+                for number in pending.bytes.chunks_exact(element_type.width()) {
+                    self.write_element(&PackedNumber::from_le_slice(element_type, number))?;
+                }
+            }
+        }
+
+        self.write_element(value)
     }
 
     #[inline]
-    fn end(self) -> Result<()> {
+    fn end(mut self) -> Result<()> {
+        if let Some(pending) = self.pending_numbers.take() {
+            match pending.element_type {
+                None => self.begin(pending.length)?,
+                Some(element_type) if pending.packed => {
+                    self.serializer.bytes_written += format::write_packed_array(
+                        &mut self.serializer.output,
+                        element_type,
+                        &pending.bytes,
+                    )?;
+                    return Ok(());
+                }
+                Some(_) => return self.serializer.write_bytes(&pending.bytes),
+            }
+        }
+
+        if let Some((buffer, count)) = self.buffered {
+            self.serializer.bytes_written +=
+                format::write_atom_header(&mut self.serializer.output, Kind::Sequence, count)?;
+            self.serializer.write_deferred(&buffer.into_deferred())?;
+        } else if !self.known_length {
+            self.serializer.bytes_written +=
+                format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
+        }
         Ok(())
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeTuple for &'de mut Serializer<'a, W> {
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeTuple for SequenceSerializer<'de, 'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -394,16 +738,256 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeTuple for &'de mut Seria
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        ser::SerializeSeq::serialize_element(self, value)
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        Ok(())
+        ser::SerializeSeq::end(self)
+    }
+}
+
+/// A serializer that only succeeds when serializing a number that can be
+/// stored in a packed array, used to detect sequences of bytes and numbers.
+struct NumberProbe;
+
+/// The error returned by [`NumberProbe`] for values that aren't a number.
+#[derive(Debug)]
+struct NotANumber;
+
+impl Display for NotANumber {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("not a number")
+    }
+}
+
+impl ser::StdError for NotANumber {}
+
+impl ser::Error for NotANumber {
+    fn custom<T: Display>(_msg: T) -> Self {
+        Self
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeTupleStruct for &'de mut Serializer<'a, W> {
+impl ser::Serializer for NumberProbe {
+    type Error = NotANumber;
+    type Ok = PackedNumber;
+    type SerializeMap = ser::Impossible<PackedNumber, NotANumber>;
+    type SerializeSeq = ser::Impossible<PackedNumber, NotANumber>;
+    type SerializeStruct = ser::Impossible<PackedNumber, NotANumber>;
+    type SerializeStructVariant = ser::Impossible<PackedNumber, NotANumber>;
+    type SerializeTuple = ser::Impossible<PackedNumber, NotANumber>;
+    type SerializeTupleStruct = ser::Impossible<PackedNumber, NotANumber>;
+    type SerializeTupleVariant = ser::Impossible<PackedNumber, NotANumber>;
+
+    #[inline]
+    fn serialize_u8(self, v: u8) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::U8(v))
+    }
+
+    #[inline]
+    fn serialize_bool(self, _v: bool) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_i8(self, v: i8) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::I8(v))
+    }
+
+    #[inline]
+    fn serialize_i16(self, v: i16) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::I16(v))
+    }
+
+    #[inline]
+    fn serialize_i32(self, v: i32) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::I32(v))
+    }
+
+    #[inline]
+    fn serialize_i64(self, v: i64) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::I64(v))
+    }
+
+    #[inline]
+    fn serialize_i128(self, _v: i128) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_u16(self, v: u16) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::U16(v))
+    }
+
+    #[inline]
+    fn serialize_u32(self, v: u32) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::U32(v))
+    }
+
+    #[inline]
+    fn serialize_u64(self, v: u64) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::U64(v))
+    }
+
+    #[inline]
+    fn serialize_u128(self, _v: u128) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_f32(self, v: f32) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::F32(v))
+    }
+
+    #[inline]
+    fn serialize_f64(self, v: f64) -> core::result::Result<PackedNumber, NotANumber> {
+        Ok(PackedNumber::F64(v))
+    }
+
+    #[inline]
+    fn serialize_char(self, _v: char) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_str(self, _v: &str) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, _v: &[u8]) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_none(self) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, _value: &T) -> core::result::Result<PackedNumber, NotANumber>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_unit_struct(
+        self,
+        _name: &'static str,
+    ) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> core::result::Result<PackedNumber, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        _value: &T,
+    ) -> core::result::Result<PackedNumber, NotANumber>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> core::result::Result<PackedNumber, NotANumber>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_seq(
+        self,
+        _len: Option<usize>,
+    ) -> core::result::Result<Self::SerializeSeq, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_tuple(
+        self,
+        _len: usize,
+    ) -> core::result::Result<Self::SerializeTuple, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> core::result::Result<Self::SerializeTupleStruct, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> core::result::Result<Self::SerializeTupleVariant, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_map(
+        self,
+        _len: Option<usize>,
+    ) -> core::result::Result<Self::SerializeMap, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> core::result::Result<Self::SerializeStruct, NotANumber> {
+        Err(NotANumber)
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> core::result::Result<Self::SerializeStructVariant, NotANumber> {
+        Err(NotANumber)
+    }
+}
+
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeTupleStruct for &'de mut Serializer<'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -421,9 +1005,7 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeTupleStruct for &'de mut
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeTupleVariant
-    for &'de mut Serializer<'a, W>
-{
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeTupleVariant for &'de mut Serializer<'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -442,12 +1024,92 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeTupleVariant
 }
 
 /// Serializes map-like values.
-pub struct MapSerializer<'de, 'a, W: WriteBytesExt> {
+pub struct MapSerializer<'de, 'a, W: Write> {
     serializer: &'de mut Serializer<'a, W>,
     known_length: bool,
+    sorted: Option<SortedEntries>,
+    fields: Option<StructFields>,
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeMap for MapSerializer<'de, 'a, W> {
+/// Tracks the fields of a struct being serialized without its field names.
+struct StructFields {
+    representation: StructRepr,
+    /// The index of the next field.
+    index: u64,
+    /// Whether any field has been skipped.
+    skipped: bool,
+}
+
+impl<'de, 'a: 'de, W: Write + 'a> MapSerializer<'de, 'a, W> {
+    /// Writes the struct field `key` and its `value`.
+    fn write_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        match &mut self.fields {
+            None => self.serializer.write_symbol(key)?,
+            Some(fields) => {
+                let index = fields.index;
+                fields.index += 1;
+                match fields.representation {
+                    // Skipping a field would cause the following fields to be
+                    // deserialized into the wrong positions.
+                    StructRepr::Positional if fields.skipped => {
+                        return Err(<Error as ser::Error>::custom(format_args!(
+                            "positional struct field `{key}` follows a skipped field"
+                        )))
+                    }
+                    StructRepr::Numbered => {
+                        let id = numeric_field_id(key).unwrap_or(index);
+                        self.serializer.write_field_id(id)?;
+                    }
+                    StructRepr::Named | StructRepr::Positional => {}
+                }
+            }
+        }
+        value.serialize(&mut *self.serializer)
+    }
+
+    /// Records that a struct field was skipped.
+    fn record_skipped_field(&mut self) {
+        if let Some(fields) = &mut self.fields {
+            fields.index += 1;
+            fields.skipped = true;
+        }
+    }
+}
+
+/// Map entries buffered by a canonical serializer.
+#[derive(Default)]
+struct SortedEntries {
+    entries: Vec<(Deferred, Deferred)>,
+    pending_key: Option<Deferred>,
+}
+
+/// A fragment of a canonical payload whose symbols have not yet been assigned
+/// ids.
+///
+/// Every symbol is written in its full form, and the byte ranges they occupy
+/// are recorded so that they can be replaced once the final position of the
+/// fragment is known.
+struct Deferred {
+    bytes: Vec<u8>,
+    symbols: Vec<(Range<usize>, &'static str)>,
+}
+
+impl Serializer<'static, Vec<u8>> {
+    fn into_deferred(self) -> Deferred {
+        let SymbolMapRef::Deferred(symbols) = self.symbol_map else {
+            unreachable!("only deferred serializers are converted")
+        };
+        Deferred {
+            bytes: self.output,
+            symbols,
+        }
+    }
+}
+
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeMap for MapSerializer<'de, 'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -456,7 +1118,14 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeMap for MapSerializer<'d
     where
         T: ?Sized + Serialize,
     {
-        key.serialize(&mut *self.serializer)
+        if let Some(sorted) = &mut self.sorted {
+            let mut buffer = self.serializer.deferred();
+            key.serialize(&mut buffer)?;
+            sorted.pending_key = Some(buffer.into_deferred());
+            Ok(())
+        } else {
+            key.serialize(&mut *self.serializer)
+        }
     }
 
     #[inline]
@@ -464,19 +1133,48 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeMap for MapSerializer<'d
     where
         T: ?Sized + Serialize,
     {
-        value.serialize(&mut *self.serializer)
+        if let Some(sorted) = &mut self.sorted {
+            let mut buffer = self.serializer.deferred();
+            value.serialize(&mut buffer)?;
+            let key = sorted.pending_key.take().ok_or_else(|| {
+                <Error as ser::Error>::custom("serialize_value called before serialize_key")
+            })?;
+            sorted.entries.push((key, buffer.into_deferred()));
+            Ok(())
+        } else {
+            value.serialize(&mut *self.serializer)
+        }
     }
 
     #[inline]
     fn end(self) -> Result<()> {
-        if !self.known_length {
-            format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
+        if let Some(mut sorted) = self.sorted {
+            sorted
+                .entries
+                .sort_by(|(a_key, a_value), (b_key, b_value)| {
+                    a_key
+                        .bytes
+                        .cmp(&b_key.bytes)
+                        .then_with(|| a_value.bytes.cmp(&b_value.bytes))
+                });
+            self.serializer.bytes_written += format::write_atom_header(
+                &mut self.serializer.output,
+                Kind::Map,
+                sorted.entries.len() as u64,
+            )?;
+            for (key, value) in &sorted.entries {
+                self.serializer.write_deferred(key)?;
+                self.serializer.write_deferred(value)?;
+            }
+        } else if !self.known_length {
+            self.serializer.bytes_written +=
+                format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
         }
         Ok(())
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeStruct for MapSerializer<'de, 'a, W> {
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeStruct for MapSerializer<'de, 'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -485,22 +1183,26 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeStruct for MapSerializer
     where
         T: ?Sized + Serialize,
     {
-        self.serializer.write_symbol(key)?;
-        value.serialize(&mut *self.serializer)
+        self.write_field(key, value)
+    }
+
+    #[inline]
+    fn skip_field(&mut self, _key: &'static str) -> Result<()> {
+        self.record_skipped_field();
+        Ok(())
     }
 
     #[inline]
     fn end(self) -> Result<()> {
         if !self.known_length {
-            format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
+            self.serializer.bytes_written +=
+                format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
         }
         Ok(())
     }
 }
 
-impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeStructVariant
-    for MapSerializer<'de, 'a, W>
-{
+impl<'de, 'a: 'de, W: Write + 'a> ser::SerializeStructVariant for MapSerializer<'de, 'a, W> {
     type Error = Error;
     type Ok = ();
 
@@ -509,27 +1211,41 @@ impl<'de, 'a: 'de, W: WriteBytesExt + 'a> ser::SerializeStructVariant
     where
         T: ?Sized + Serialize,
     {
-        self.serializer.write_symbol(key)?;
-        value.serialize(&mut *self.serializer)
+        self.write_field(key, value)
+    }
+
+    #[inline]
+    fn skip_field(&mut self, _key: &'static str) -> Result<()> {
+        self.record_skipped_field();
+        Ok(())
     }
 
     #[inline]
     fn end(self) -> Result<()> {
         if !self.known_length {
-            format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
+            self.serializer.bytes_written +=
+                format::write_special(&mut self.serializer.output, Special::DynamicEnd)?;
         }
         Ok(())
     }
 }
 
+/// Returns the id of a struct field named `#` followed by a number. See
+/// [`StructRepr::Numbered`].
+pub(crate) fn numeric_field_id(name: &str) -> Option<u64> {
+    name.strip_prefix('#')
+        .filter(|digits| !digits.is_empty() && digits.bytes().all(|byte| byte.is_ascii_digit()))
+        .and_then(|digits| digits.parse().ok())
+}
+
 #[derive(Default)]
 struct EphemeralSymbolMap {
     symbols: Vec<(&'static str, u32)>,
 }
 
-struct RegisteredSymbol {
-    id: u32,
-    new: bool,
+pub(crate) struct RegisteredSymbol {
+    pub(crate) id: u32,
+    pub(crate) new: bool,
 }
 
 impl EphemeralSymbolMap {
@@ -558,7 +1274,7 @@ impl EphemeralSymbolMap {
 }
 
 impl Debug for EphemeralSymbolMap {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut set = f.debug_set();
         for index in SymbolIdSorter::new(&self.symbols, |sym| sym.1) {
             set.entry(&self.symbols[index].0);
@@ -576,7 +1292,7 @@ pub struct SymbolMap {
 }
 
 impl Debug for SymbolMap {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         let mut s = f.debug_set();
         for entry in &self.entries {
             s.entry(&&self.symbols[entry.0.clone()]);
@@ -618,7 +1334,7 @@ impl SymbolMap {
     /// Returns a serializer that writes into `output` and persists symbols
     /// into `self`.
     #[inline]
-    pub fn serializer_for<W: WriteBytesExt>(&mut self, output: W) -> Result<Serializer<'_, W>> {
+    pub fn serializer_for<W: Write>(&mut self, output: W) -> Result<Serializer<'_, W>> {
         let compatibility = self.compatibility;
         Serializer::new_with_symbol_map(output, SymbolMapRef::Persistent(self), compatibility)
     }
@@ -668,7 +1384,7 @@ impl SymbolMap {
     }
 
     #[allow(clippy::cast_possible_truncation)]
-    fn find_entry_by_str(&mut self, symbol: &str) -> RegisteredSymbol {
+    pub(crate) fn find_entry_by_str(&mut self, symbol: &str) -> RegisteredSymbol {
         match self
             .entries
             .binary_search_by(|check| self.symbols[check.0.clone()].cmp(symbol))
@@ -728,7 +1444,7 @@ impl SymbolMap {
 
 impl Serialize for SymbolMap {
     #[inline]
-    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    fn serialize<S>(&self, serializer: S) -> core::result::Result<S::Ok, S::Error>
     where
         S: serde::Serializer,
     {
@@ -743,7 +1459,7 @@ impl Serialize for SymbolMap {
 
 impl<'de> Deserialize<'de> for SymbolMap {
     #[inline]
-    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    fn deserialize<D>(deserializer: D) -> core::result::Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
@@ -756,12 +1472,12 @@ struct SymbolMapVisitor;
 impl<'de> Visitor<'de> for SymbolMapVisitor {
     type Value = SymbolMap;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         formatter.write_str("symbol map")
     }
 
     #[inline]
-    fn visit_seq<A>(self, mut seq: A) -> std::result::Result<Self::Value, A::Error>
+    fn visit_seq<A>(self, mut seq: A) -> core::result::Result<Self::Value, A::Error>
     where
         A: SeqAccess<'de>,
     {
@@ -788,6 +1504,9 @@ impl<'de> Visitor<'de> for SymbolMapVisitor {
 enum SymbolMapRef<'a> {
     Ephemeral(EphemeralSymbolMap),
     Persistent(&'a mut SymbolMap),
+    /// Symbols are always written in full, and the location of each symbol is
+    /// recorded.
+    Deferred(Vec<(Range<usize>, &'static str)>),
 }
 
 impl SymbolMapRef<'_> {
@@ -795,6 +1514,7 @@ impl SymbolMapRef<'_> {
         match self {
             SymbolMapRef::Ephemeral(map) => map.find_or_add(symbol),
             SymbolMapRef::Persistent(map) => map.find_or_add(symbol),
+            SymbolMapRef::Deferred(_) => RegisteredSymbol { id: 0, new: true },
         }
     }
 }
@@ -813,47 +1533,47 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     type SerializeTupleVariant = Self;
 
     #[inline]
-    fn serialize_bool(self, _v: bool) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_bool(self, _v: bool) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_i8(self, _v: i8) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_i8(self, _v: i8) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_i16(self, _v: i16) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_i16(self, _v: i16) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_i32(self, _v: i32) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_i32(self, _v: i32) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_i64(self, _v: i64) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_i64(self, _v: i64) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_u8(self, _v: u8) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_u8(self, _v: u8) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_u16(self, _v: u16) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_u16(self, _v: u16) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_u32(self, _v: u32) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_u32(self, _v: u32) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_u64(self, _v: u64) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_u64(self, _v: u64) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
@@ -868,37 +1588,37 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     }
 
     #[inline]
-    fn serialize_f32(self, _v: f32) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_f32(self, _v: f32) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_f64(self, _v: f64) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_f64(self, _v: f64) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_char(self, _v: char) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_char(self, _v: char) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_str(self, _v: &str) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_str(self, _v: &str) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_bytes(self, _v: &[u8]) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_bytes(self, _v: &[u8]) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_none(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_none(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
     #[inline]
-    fn serialize_some<T: ?Sized>(self, value: &T) -> std::result::Result<Self::Ok, Self::Error>
+    fn serialize_some<T: ?Sized>(self, value: &T) -> core::result::Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
@@ -906,7 +1626,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     }
 
     #[inline]
-    fn serialize_unit(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn serialize_unit(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
@@ -914,7 +1634,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     fn serialize_unit_struct(
         self,
         _name: &'static str,
-    ) -> std::result::Result<Self::Ok, Self::Error> {
+    ) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 
@@ -924,7 +1644,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         _name: &'static str,
         _variant_index: u32,
         variant: &'static str,
-    ) -> std::result::Result<Self::Ok, Self::Error> {
+    ) -> core::result::Result<Self::Ok, Self::Error> {
         self.0.find_or_add(variant);
         Ok(())
     }
@@ -934,7 +1654,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         self,
         _name: &'static str,
         value: &T,
-    ) -> std::result::Result<Self::Ok, Self::Error>
+    ) -> core::result::Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
@@ -948,7 +1668,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         _variant_index: u32,
         variant: &'static str,
         value: &T,
-    ) -> std::result::Result<Self::Ok, Self::Error>
+    ) -> core::result::Result<Self::Ok, Self::Error>
     where
         T: Serialize,
     {
@@ -960,7 +1680,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     fn serialize_seq(
         self,
         _len: Option<usize>,
-    ) -> std::result::Result<Self::SerializeSeq, Self::Error> {
+    ) -> core::result::Result<Self::SerializeSeq, Self::Error> {
         Ok(self)
     }
 
@@ -968,7 +1688,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     fn serialize_tuple(
         self,
         _len: usize,
-    ) -> std::result::Result<Self::SerializeTuple, Self::Error> {
+    ) -> core::result::Result<Self::SerializeTuple, Self::Error> {
         Ok(self)
     }
 
@@ -977,7 +1697,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         self,
         _name: &'static str,
         _len: usize,
-    ) -> std::result::Result<Self::SerializeTupleStruct, Self::Error> {
+    ) -> core::result::Result<Self::SerializeTupleStruct, Self::Error> {
         Ok(self)
     }
 
@@ -988,7 +1708,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         _variant_index: u32,
         variant: &'static str,
         _len: usize,
-    ) -> std::result::Result<Self::SerializeTupleVariant, Self::Error> {
+    ) -> core::result::Result<Self::SerializeTupleVariant, Self::Error> {
         self.0.find_or_add(variant);
         Ok(self)
     }
@@ -997,7 +1717,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
     fn serialize_map(
         self,
         _len: Option<usize>,
-    ) -> std::result::Result<Self::SerializeMap, Self::Error> {
+    ) -> core::result::Result<Self::SerializeMap, Self::Error> {
         Ok(self)
     }
 
@@ -1006,7 +1726,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         self,
         _name: &'static str,
         _len: usize,
-    ) -> std::result::Result<Self::SerializeStruct, Self::Error> {
+    ) -> core::result::Result<Self::SerializeStruct, Self::Error> {
         Ok(self)
     }
 
@@ -1017,7 +1737,7 @@ impl<'ser, 'a> serde::ser::Serializer for &'ser mut SymbolMapPopulator<'a> {
         _variant_index: u32,
         variant: &'static str,
         _len: usize,
-    ) -> std::result::Result<Self::SerializeStructVariant, Self::Error> {
+    ) -> core::result::Result<Self::SerializeStructVariant, Self::Error> {
         self.0.find_or_add(variant);
         Ok(self)
     }
@@ -1028,7 +1748,7 @@ impl serde::ser::SerializeMap for &mut SymbolMapPopulator<'_> {
     type Ok = ();
 
     #[inline]
-    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_key<T: ?Sized>(&mut self, key: &T) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1036,7 +1756,7 @@ impl serde::ser::SerializeMap for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_value<T: ?Sized>(&mut self, value: &T) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1044,7 +1764,7 @@ impl serde::ser::SerializeMap for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1054,7 +1774,7 @@ impl serde::ser::SerializeSeq for &mut SymbolMapPopulator<'_> {
     type Ok = ();
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1062,7 +1782,7 @@ impl serde::ser::SerializeSeq for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1076,7 +1796,7 @@ impl serde::ser::SerializeStruct for &mut SymbolMapPopulator<'_> {
         &mut self,
         key: &'static str,
         value: &T,
-    ) -> std::result::Result<(), Self::Error>
+    ) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1085,7 +1805,7 @@ impl serde::ser::SerializeStruct for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1099,7 +1819,7 @@ impl serde::ser::SerializeStructVariant for &mut SymbolMapPopulator<'_> {
         &mut self,
         key: &'static str,
         value: &T,
-    ) -> std::result::Result<(), Self::Error>
+    ) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1108,7 +1828,7 @@ impl serde::ser::SerializeStructVariant for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1118,7 +1838,7 @@ impl serde::ser::SerializeTuple for &mut SymbolMapPopulator<'_> {
     type Ok = ();
 
     #[inline]
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1126,7 +1846,7 @@ impl serde::ser::SerializeTuple for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1135,7 +1855,7 @@ impl serde::ser::SerializeTupleStruct for &mut SymbolMapPopulator<'_> {
     type Ok = ();
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1143,7 +1863,7 @@ impl serde::ser::SerializeTupleStruct for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1152,7 +1872,7 @@ impl serde::ser::SerializeTupleVariant for &mut SymbolMapPopulator<'_> {
     type Ok = ();
 
     #[inline]
-    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> std::result::Result<(), Self::Error>
+    fn serialize_field<T: ?Sized>(&mut self, value: &T) -> core::result::Result<(), Self::Error>
     where
         T: Serialize,
     {
@@ -1160,7 +1880,7 @@ impl serde::ser::SerializeTupleVariant for &mut SymbolMapPopulator<'_> {
     }
 
     #[inline]
-    fn end(self) -> std::result::Result<Self::Ok, Self::Error> {
+    fn end(self) -> core::result::Result<Self::Ok, Self::Error> {
         Ok(())
     }
 }
@@ -1170,12 +1890,12 @@ impl serde::ser::SerializeTupleVariant for &mut SymbolMapPopulator<'_> {
 pub struct SymbolMapPopulationError(String);
 
 impl Display for SymbolMapPopulationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_str(&self.0)
     }
 }
 
-impl std::error::Error for SymbolMapPopulationError {}
+impl ser::StdError for SymbolMapPopulationError {}
 
 impl serde::ser::Error for SymbolMapPopulationError {
     fn custom<T>(msg: T) -> Self
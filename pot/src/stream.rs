@@ -0,0 +1,262 @@
+#[cfg(feature = "std")]
+use alloc::vec::Vec;
+use core::fmt::Debug;
+use core::marker::PhantomData;
+#[cfg(feature = "std")]
+use std::io::{ErrorKind, Read};
+
+#[cfg(feature = "std")]
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+
+use crate::de::{Deserializer, SymbolMap, SymbolMapRef};
+use crate::framing::{Framer, Progress};
+#[cfg(feature = "std")]
+use crate::reader::IoReader;
+use crate::reader::SliceReader;
+use crate::{Config, Error, Result};
+
+/// The maximum number of bytes requested from a reader at once. Atom headers
+/// can claim lengths far larger than the data that follows them, so the
+/// buffer only grows as data actually arrives.
+#[cfg(feature = "std")]
+const MAX_READ_CHUNK: usize = 16 * 1024;
+
+/// An iterator that deserializes a series of back-to-back Pot payloads.
+///
+/// Each payload is located by reading its atoms before it is deserialized,
+/// so no bytes past the end of a payload are ever read. After each item is
+/// returned, [`byte_offset()`](Self::byte_offset) is the offset of the byte
+/// following it. Once an error has been returned, the iterator returns `None`.
+///
+/// Payloads serialized with [`Compatibility::Full`](crate::Compatibility::Full)
+/// that contain enum variants without associated data cannot be located
+/// reliably. Streams should be serialized with
+/// [`Compatibility::V4`](crate::Compatibility::V4) or later.
+///
+/// Stream deserializers are created using
+/// [`Config::deserialize_stream()`](crate::Config::deserialize_stream) and
+/// [`Config::deserialize_stream_from()`](crate::Config::deserialize_stream_from).
+///
+/// ```rust
+/// let mut payloads = pot::to_vec(&"hello").unwrap();
+/// payloads.extend(pot::to_vec(&"world").unwrap());
+///
+/// let mut stream = pot::Config::default().deserialize_stream::<&str>(&payloads);
+/// assert_eq!(stream.next().unwrap().unwrap(), "hello");
+/// assert_eq!(stream.byte_offset(), payloads.len() / 2);
+/// assert_eq!(stream.next().unwrap().unwrap(), "world");
+/// assert!(stream.next().is_none());
+/// ```
+pub struct StreamDeserializer<R, T> {
+    input: R,
+    config: Config,
+    symbols: Option<SymbolMap>,
+    offset: usize,
+    #[cfg(feature = "std")]
+    buffer: Vec<u8>,
+    failed: bool,
+    _item: PhantomData<fn() -> T>,
+}
+
+impl<R, T> Debug for StreamDeserializer<R, T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("StreamDeserializer")
+            .field("symbols", &self.symbols)
+            .field("offset", &self.offset)
+            .field("failed", &self.failed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R, T> StreamDeserializer<R, T> {
+    pub(crate) fn new(input: R, config: Config) -> Self {
+        Self {
+            input,
+            config,
+            symbols: None,
+            offset: 0,
+            #[cfg(feature = "std")]
+            buffer: Vec::new(),
+            failed: false,
+            _item: PhantomData,
+        }
+    }
+
+    /// Uses `symbols` as a persistent symbol map shared by every payload in
+    /// the stream and returns self.
+    ///
+    /// This should only be used with data generated by using a persistent
+    /// [`ser::SymbolMap`](crate::ser::SymbolMap).
+    #[must_use]
+    pub fn with_symbol_map(mut self, symbols: SymbolMap) -> Self {
+        self.symbols = Some(symbols);
+        self
+    }
+
+    /// Returns the persistent symbol map, if one is being used.
+    #[must_use]
+    pub const fn symbol_map(&self) -> Option<&SymbolMap> {
+        self.symbols.as_ref()
+    }
+
+    /// Returns the persistent symbol map, if one was being used.
+    #[must_use]
+    pub fn into_symbol_map(self) -> Option<SymbolMap> {
+        self.symbols
+    }
+
+    /// Returns the number of bytes of the input that have been consumed.
+    /// After an item has been returned, this is the offset of the byte
+    /// following its payload.
+    #[must_use]
+    pub const fn byte_offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Deserializes a complete payload, returning [`Error::TrailingBytes`] if the
+/// value does not consume all of `payload`.
+pub(crate) fn deserialize_payload<'de, D>(
+    payload: &'de [u8],
+    symbols: Option<&mut SymbolMap>,
+    config: &Config,
+) -> Result<D>
+where
+    D: Deserialize<'de>,
+{
+    let symbols = match symbols {
+        Some(symbols) => symbols.persistent(),
+        None => SymbolMapRef::temporary(),
+    };
+    let mut deserializer =
+        Deserializer::from_slice_with_symbols(payload, symbols, config.allocation_budget)?
+            .with_max_depth(config.max_depth)
+            .with_limits(config.limits)
+            .with_error_positions(config.error_positions)
+            .with_compact_bytes(config.compact_bytes)
+            .with_lenient(config.lenient);
+    let value = D::deserialize(&mut deserializer).map_err(|err| deserializer.locate(err, None))?;
+    if deserializer.end_of_input() {
+        Ok(value)
+    } else {
+        Err(Error::TrailingBytes)
+    }
+}
+
+impl<'de, T> StreamDeserializer<SliceReader<'de>, T>
+where
+    T: Deserialize<'de>,
+{
+    fn next_payload(&mut self) -> Result<Option<&'de [u8]>> {
+        let remaining = self.input.data;
+        if remaining.is_empty() {
+            return Ok(None);
+        }
+
+        let mut framer =
+            Framer::with_known_symbols(self.symbols.as_ref().map_or(0, SymbolMap::len));
+        match framer.advance(remaining)? {
+            Progress::Complete(length) => {
+                self.config.limits.check_input_length(length)?;
+                let (payload, remaining) = remaining.split_at(length);
+                self.input.data = remaining;
+                self.offset += length;
+                Ok(Some(payload))
+            }
+            Progress::NeedMore(_) => Err(Error::Eof),
+        }
+    }
+}
+
+impl<'de, T> Iterator for StreamDeserializer<SliceReader<'de>, T>
+where
+    T: Deserialize<'de>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let result = match self.next_payload() {
+            Ok(Some(payload)) => {
+                deserialize_payload(payload, self.symbols.as_mut(), &self.config).map(Some)
+            }
+            Ok(None) => Ok(None),
+            Err(err) => Err(err),
+        };
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R, T> StreamDeserializer<IoReader<R>, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    /// Reads the next payload into `self.buffer`, returning false if the
+    /// input ended before the payload began.
+    fn read_payload(&mut self) -> Result<bool> {
+        self.buffer.clear();
+        let mut framer =
+            Framer::with_known_symbols(self.symbols.as_ref().map_or(0, SymbolMap::len));
+        loop {
+            match framer.advance(&self.buffer)? {
+                Progress::Complete(_) => return Ok(true),
+                Progress::NeedMore(needed) => {
+                    let start = self.buffer.len();
+                    let end = start + needed.min(MAX_READ_CHUNK);
+                    if end > self.config.allocation_budget {
+                        return Err(Error::TooManyBytesRead);
+                    }
+                    self.config.limits.check_input_length(end)?;
+                    self.buffer.resize(end, 0);
+                    let read = loop {
+                        match self.input.reader.read(&mut self.buffer[start..]) {
+                            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                            other => break other?,
+                        }
+                    };
+                    self.buffer.truncate(start + read);
+                    self.offset += read;
+                    if read == 0 {
+                        return if start == 0 {
+                            Ok(false)
+                        } else {
+                            Err(Error::from(std::io::Error::from(ErrorKind::UnexpectedEof)))
+                        };
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<R, T> Iterator for StreamDeserializer<IoReader<R>, T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let result = match self.read_payload() {
+            Ok(true) => {
+                deserialize_payload(&self.buffer, self.symbols.as_mut(), &self.config).map(Some)
+            }
+            Ok(false) => Ok(None),
+            Err(err) => Err(err),
+        };
+        self.failed = result.is_err();
+        result.transpose()
+    }
+}
@@ -8,7 +8,8 @@ use std::sync::OnceLock;
 use serde::{Deserializer, Serializer};
 
 use super::*;
-use crate::format::{Float, Integer, CURRENT_VERSION};
+use crate::de::Diagnostic;
+use crate::format::{Float, Integer, Kind, CURRENT_VERSION};
 use crate::value::Value;
 
 fn init_tracing() {
@@ -82,7 +83,7 @@ struct NumbersStruct {
     f64: f64,
 }
 
-#[derive(Serialize, PartialEq, Deserialize, Debug)]
+#[derive(Serialize, PartialEq, Deserialize, Debug, Clone)]
 enum EnumVariants {
     Unit,
     Tuple(u64),
@@ -800,3 +801,2621 @@ fn unit_enum_fix() {
         other => unreachable!("Unexpected value: {other:?}"),
     }
 }
+
+fn render_events<'de, R: reader::Reader<'de>>(
+    mut reader: events::Reader<'_, 'de, R>,
+) -> Vec<String> {
+    let mut rendered = Vec::new();
+    while let Some(event) = reader.next_event().unwrap() {
+        rendered.push(match event {
+            events::Event::StartSequence(len) => format!("seq({len:?})"),
+            events::Event::StartMap(len) => format!("map({len:?})"),
+            events::Event::Key => String::from("key"),
+            events::Event::End => String::from("end"),
+            events::Event::Named => String::from("named"),
+            events::Event::None => String::from("none"),
+            events::Event::Scalar(format::Nucleus::Integer(value)) => value.to_string(),
+            events::Event::Scalar(format::Nucleus::Boolean(value)) => value.to_string(),
+            events::Event::Scalar(other) => format!("{other:?}"),
+            events::Event::Symbol(symbol) => format!("#{}", &*symbol),
+            events::Event::Bytes(bytes) => format!("{:?}", String::from_utf8_lossy(&bytes)),
+            events::Event::PackedArray(element_type, bytes) => {
+                format!("packed({element_type:?}, {})", bytes.len())
+            }
+        });
+    }
+    rendered
+}
+
+#[test]
+fn event_reader() {
+    #[derive(Serialize)]
+    struct Entry {
+        id: u32,
+        tag: Option<&'static str>,
+    }
+
+    let payload = (
+        vec![
+            Entry { id: 1, tag: None },
+            Entry {
+                id: 2,
+                tag: Some("two"),
+            },
+        ],
+        EnumVariants::Tuple(3),
+        true,
+    );
+    let expected = [
+        "seq(Some(3))",
+        "seq(Some(2))",
+        "map(Some(2))",
+        "key",
+        "#id",
+        "1",
+        "key",
+        "#tag",
+        "none",
+        "end",
+        "map(Some(2))",
+        "key",
+        "#id",
+        "2",
+        "key",
+        "#tag",
+        "\"two\"",
+        "end",
+        "end",
+        "named",
+        "#Tuple",
+        "3",
+        "true",
+        "end",
+    ];
+
+    let bytes = to_vec(&payload).unwrap();
+    assert_eq!(
+        render_events(events::Reader::from_slice(&bytes).unwrap()),
+        expected
+    );
+    assert_eq!(
+        render_events(events::Reader::from_read(&bytes[..]).unwrap()),
+        expected
+    );
+}
+
+#[test]
+fn event_reader_dynamic_map() {
+    let bytes = to_vec(&Flatten {
+        structure: Flattened {
+            field: String::from("flat"),
+        },
+        enumeration: EnumVariants::Unit,
+    })
+    .unwrap();
+    assert_eq!(
+        render_events(events::Reader::from_slice(&bytes).unwrap()),
+        [
+            "map(None)",
+            "key",
+            "\"field\"",
+            "\"flat\"",
+            "key",
+            "\"Unit\"",
+            "Unit",
+            "end"
+        ]
+    );
+
+    let mut unbalanced = Vec::new();
+    format::write_header(&mut unbalanced, CURRENT_VERSION).unwrap();
+    format::write_special(&mut unbalanced, format::Special::DynamicMap).unwrap();
+    format::write_str(&mut unbalanced, "key").unwrap();
+    format::write_special(&mut unbalanced, format::Special::DynamicEnd).unwrap();
+    let mut reader = events::Reader::from_slice(&unbalanced).unwrap();
+    assert!(matches!(
+        reader.next_event().unwrap(),
+        Some(events::Event::StartMap(None))
+    ));
+    assert!(matches!(
+        reader.next_event().unwrap(),
+        Some(events::Event::Key)
+    ));
+    assert!(matches!(
+        reader.next_event().unwrap(),
+        Some(events::Event::Bytes(_))
+    ));
+    assert!(reader.next_event().is_err());
+}
+
+#[test]
+fn format_writer() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Entry {
+        index: u64,
+        name: String,
+    }
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.begin_sequence(2).unwrap();
+    for (index, name) in ["a", "b"].into_iter().enumerate() {
+        writer.begin_dynamic_map().unwrap();
+        writer.symbol("index").unwrap();
+        writer.write_u64(index as u64).unwrap();
+        writer.symbol("name").unwrap();
+        writer.write_str(name).unwrap();
+        writer.end().unwrap();
+    }
+    writer.end().unwrap();
+    let bytes = writer.finish().unwrap();
+
+    let entries: Vec<Entry> = from_slice(&bytes).unwrap();
+    assert_eq!(
+        entries,
+        [
+            Entry {
+                index: 0,
+                name: String::from("a"),
+            },
+            Entry {
+                index: 1,
+                name: String::from("b"),
+            },
+        ]
+    );
+    // The second map's keys must refer to the symbols by id.
+    assert_eq!(bytes.windows(5).filter(|w| w == b"index").count(), 1);
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.named("TupleTwoArgs").unwrap();
+    writer.begin_sequence(2).unwrap();
+    writer.write_u64(1).unwrap();
+    writer.write_u64(2).unwrap();
+    writer.end().unwrap();
+    let bytes = writer.finish().unwrap();
+    assert_eq!(
+        from_slice::<EnumVariants>(&bytes).unwrap(),
+        EnumVariants::TupleTwoArgs(1, 2)
+    );
+}
+
+#[test]
+fn format_writer_container_mismatch() {
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.begin_sequence(2).unwrap();
+    writer.write_unit().unwrap();
+    writer.write_unit().unwrap();
+    assert!(matches!(
+        writer.write_unit(),
+        Err(Error::ContainerLengthMismatch {
+            expected: 2,
+            written: 3
+        })
+    ));
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.begin_map(2).unwrap();
+    writer.symbol("key").unwrap();
+    writer.write_none().unwrap();
+    assert!(matches!(
+        writer.end(),
+        Err(Error::ContainerLengthMismatch {
+            expected: 2,
+            written: 1
+        })
+    ));
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.begin_map(1).unwrap();
+    writer.symbol("key").unwrap();
+    writer.write_none().unwrap();
+    assert!(matches!(
+        writer.symbol("extra"),
+        Err(Error::ContainerLengthMismatch {
+            expected: 1,
+            written: 2
+        })
+    ));
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    assert!(matches!(
+        writer.begin_map(usize::MAX),
+        Err(Error::ContainerTooLong)
+    ));
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.begin_dynamic_map().unwrap();
+    writer.symbol("key").unwrap();
+    assert!(matches!(writer.end(), Err(Error::UnbalancedContainers)));
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.begin_sequence(0).unwrap();
+    assert!(matches!(writer.finish(), Err(Error::UnbalancedContainers)));
+
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    writer.write_unit().unwrap();
+    assert!(matches!(
+        writer.write_unit(),
+        Err(Error::UnbalancedContainers)
+    ));
+}
+
+/// A sequence whose length isn't known until it has been fully iterated.
+#[derive(Debug)]
+struct Streamed<T>(Vec<T>);
+
+impl<T: Serialize> Serialize for Streamed<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(self.0.iter().filter(|_| true))
+    }
+}
+
+#[test]
+fn dynamic_sequence() {
+    let streamed = Streamed(vec![1_u32, 2, 3]);
+    assert!(matches!(
+        Config::new().serialize(&streamed),
+        Err(Error::SequenceSizeMustBeKnown)
+    ));
+    assert!(matches!(
+        Config::new()
+            .compatibility(Compatibility::V4)
+            .serialize(&streamed),
+        Err(Error::SequenceSizeMustBeKnown)
+    ));
+
+    let config = Config::new().compatibility(Compatibility::V5);
+    let bytes = config.serialize(&streamed).unwrap();
+    assert_eq!(config.deserialize::<Vec<u32>>(&bytes).unwrap(), [1, 2, 3]);
+    assert_eq!(
+        config.deserialize_from::<Vec<u32>, _>(&bytes[..]).unwrap(),
+        [1, 2, 3]
+    );
+    assert_eq!(
+        config.deserialize::<Value<'_>>(&bytes).unwrap(),
+        Value::Sequence(vec![
+            Value::from(1_u32),
+            Value::from(2_u32),
+            Value::from(3_u32)
+        ])
+    );
+    assert_eq!(
+        render_events(events::Reader::from_slice(&bytes).unwrap()),
+        ["seq(None)", "1", "2", "3", "end"]
+    );
+
+    let bytes = config.serialize(&Streamed(vec![0_u8, 255])).unwrap();
+    let buf: serde_bytes::ByteBuf = config.deserialize(&bytes).unwrap();
+    assert_eq!(buf.as_slice(), [0, 255]);
+
+    let nested = Streamed(vec![Streamed(vec![EnumVariants::Unit])]);
+    let bytes = config.serialize(&nested).unwrap();
+    assert_eq!(
+        config
+            .deserialize::<Vec<Vec<EnumVariants>>>(&bytes)
+            .unwrap(),
+        [[EnumVariants::Unit]]
+    );
+}
+
+#[test]
+fn dynamic_sequence_symbol_maps() {
+    let values = Streamed(vec![
+        EnumVariants::Struct { arg: 1 },
+        EnumVariants::Struct { arg: 2 },
+    ]);
+
+    let mut populated = ser::SymbolMap::new();
+    assert_eq!(populated.populate_from(&values).unwrap(), 2);
+
+    let mut sender = ser::SymbolMap::new().with_compatibility(Compatibility::V5);
+    let mut receiver = de::SymbolMap::new();
+    let bytes = sender.serialize_to_vec(&values).unwrap();
+    let result: Vec<EnumVariants> = receiver.deserialize_slice(&bytes).unwrap();
+    assert_eq!(result, values.0);
+    assert_eq!(receiver.len(), 2);
+}
+
+#[test]
+fn format_writer_dynamic_sequence() {
+    let mut writer = format::Writer::new(Vec::new()).unwrap();
+    assert!(matches!(
+        writer.begin_dynamic_sequence(),
+        Err(Error::SequenceSizeMustBeKnown)
+    ));
+
+    let mut writer = format::Writer::new_with_compatibility(Vec::new(), Compatibility::V5).unwrap();
+    writer.begin_dynamic_sequence().unwrap();
+    writer.write_str("a").unwrap();
+    writer.write_str("b").unwrap();
+    writer.end().unwrap();
+    let bytes = writer.finish().unwrap();
+    assert_eq!(from_slice::<Vec<String>>(&bytes).unwrap(), ["a", "b"]);
+}
+
+#[test]
+fn validation() {
+    let payload = to_vec(&vec![
+        EnumVariants::Struct { arg: 1 },
+        EnumVariants::Struct { arg: 2 },
+    ])
+    .unwrap();
+    let summary = crate::validate(&payload, &Limits::default()).unwrap();
+    // seq, 2 * (named, symbol, map, symbol, int)
+    assert_eq!(summary.atoms, 11);
+    assert_eq!(summary.max_depth, 2);
+    assert_eq!(summary.symbols, 2);
+
+    assert!(matches!(
+        crate::validate(&payload, &Limits::new().max_atoms(10)),
+        Err(Error::TooManyAtoms)
+    ));
+    assert!(matches!(
+        crate::validate(&payload, &Limits::new().max_symbols(1)),
+        Err(Error::TooManySymbols)
+    ));
+
+    assert!(matches!(
+        crate::validate(b"Pit\0\0", &Limits::default()),
+        Err(Error::IncompatibleVersion)
+    ));
+    assert!(matches!(
+        crate::validate(&payload[..payload.len() - 1], &Limits::default()),
+        Err(Error::Eof)
+    ));
+    let mut trailing = payload.clone();
+    trailing.push(0);
+    assert!(matches!(
+        crate::validate(&trailing, &Limits::default()),
+        Err(Error::TrailingBytes)
+    ));
+}
+
+#[test]
+fn validation_errors() {
+    fn validate_with(write: impl FnOnce(&mut Vec<u8>)) -> Result<Summary> {
+        let mut payload = Vec::new();
+        format::write_header(&mut payload, CURRENT_VERSION).unwrap();
+        write(&mut payload);
+        crate::validate(&payload, &Limits::default())
+    }
+
+    assert!(matches!(
+        validate_with(|payload| {
+            format::write_atom_header(&mut *payload, format::Kind::Symbol, (3 << 1) | 1).unwrap();
+        }),
+        Err(Error::UnknownSymbol(3))
+    ));
+    assert!(matches!(
+        validate_with(|payload| {
+            format::write_atom_header(&mut *payload, format::Kind::Symbol, 2 << 1).unwrap();
+            payload.extend_from_slice(&[0xC3, 0x28]);
+        }),
+        Err(Error::InvalidUtf8(_))
+    ));
+    assert!(matches!(
+        validate_with(|payload| {
+            format::write_atom_header(&mut *payload, format::Kind::Special, format::SPECIAL_COUNT)
+                .unwrap();
+        }),
+        Err(Error::UnknownSpecial(_))
+    ));
+    assert!(validate_with(|payload| {
+        format::write_special(&mut *payload, format::Special::DynamicEnd).unwrap();
+    })
+    .is_err());
+    assert!(matches!(
+        validate_with(|payload| {
+            format::write_special(&mut *payload, format::Special::DynamicMap).unwrap();
+            format::write_str(&mut *payload, "key").unwrap();
+            format::write_unit(payload).unwrap();
+        }),
+        Err(Error::Eof)
+    ));
+    assert!(matches!(
+        validate_with(|payload| {
+            payload.push(0xFF);
+        }),
+        Err(Error::Eof)
+    ));
+}
+
+#[test]
+fn skip_value() {
+    let values = vec![
+        EnumVariants::Struct { arg: 1 },
+        EnumVariants::TupleTwoArgs(2, 3),
+        EnumVariants::Struct { arg: 4 },
+    ];
+    let config = Config::new().compatibility(Compatibility::V5);
+    let payload = config
+        .serialize(&(
+            Streamed(values.clone()),
+            Flatten {
+                structure: Flattened {
+                    field: String::from("flat"),
+                },
+                enumeration: EnumVariants::Unit,
+            },
+        ))
+        .unwrap();
+
+    let mut reader = reader::SliceReader::from(&payload[..]);
+    format::read_header(&mut reader).unwrap();
+    let mut symbols = de::SymbolMap::new();
+    assert_eq!(
+        format::skip_value(&mut reader, &mut symbols).unwrap(),
+        4..payload.len()
+    );
+    assert!(reader.is_empty());
+    assert_eq!(symbols.len(), 3);
+
+    // Skip each element of the sequence individually, decoding the later
+    // elements using the symbols gathered while skipping.
+    let mut reader = reader::SliceReader::from(&payload[..]);
+    format::read_header(&mut reader).unwrap();
+    format::read_atom_header(&mut reader).unwrap();
+    format::read_atom_header(&mut reader).unwrap();
+    let mut symbols = de::SymbolMap::new();
+    for expected in &values {
+        let range = format::skip_value(&mut reader, &mut symbols).unwrap();
+        let mut element = payload[..4].to_vec();
+        element.extend_from_slice(&payload[range]);
+        let mut decode_symbols = de::SymbolMap::new();
+        for index in 0..symbols.len() {
+            decode_symbols.push(&symbols.get(index).unwrap());
+        }
+        assert_eq!(
+            &decode_symbols
+                .deserialize_slice::<EnumVariants>(&element)
+                .unwrap(),
+            expected
+        );
+    }
+
+    let mut reader = reader::SliceReader::from(&payload[..payload.len() - 1]);
+    format::read_header(&mut reader).unwrap();
+    assert!(format::skip_value(&mut reader, &mut de::SymbolMap::new()).is_err());
+
+    let mut unknown_symbol = Vec::new();
+    format::write_atom_header(&mut unknown_symbol, format::Kind::Symbol, 1).unwrap();
+    assert!(matches!(
+        format::skip_value(
+            &mut reader::SliceReader::from(&unknown_symbol[..]),
+            &mut de::SymbolMap::new()
+        ),
+        Err(Error::UnknownSymbol(0))
+    ));
+}
+
+#[test]
+fn lazy_document() {
+    #[derive(Serialize)]
+    struct Record<'a> {
+        id: u64,
+        user: Vec<EnumVariants>,
+        tags: Streamed<&'a str>,
+        enabled: bool,
+        score: f64,
+        data: &'a serde_bytes::Bytes,
+        missing: Option<u8>,
+    }
+
+    let payload = Config::new()
+        .compatibility(Compatibility::V5)
+        .serialize(&vec![
+            Record {
+                id: 1,
+                user: vec![
+                    EnumVariants::Unit,
+                    EnumVariants::Struct { arg: 2 },
+                    EnumVariants::Tuple(3),
+                    EnumVariants::Struct { arg: 4 },
+                ],
+                tags: Streamed(vec!["a", "b"]),
+                enabled: true,
+                score: 1.5,
+                data: serde_bytes::Bytes::new(b"bytes"),
+                missing: None,
+            },
+            Record {
+                id: 2,
+                user: vec![EnumVariants::Struct { arg: 5 }],
+                tags: Streamed(Vec::new()),
+                enabled: false,
+                score: 0.,
+                data: serde_bytes::Bytes::new(b""),
+                missing: None,
+            },
+        ])
+        .unwrap();
+
+    let doc = lazy::Document::new(&payload).unwrap();
+    assert_eq!(doc.kind().unwrap(), format::Kind::Sequence);
+    let first = doc.get(0).unwrap();
+    assert_eq!(
+        first.get("id").unwrap().as_integer().unwrap(),
+        Integer::from(1_u8)
+    );
+    // `arg` is defined in the first `Struct`, and is referenced by id in the
+    // second.
+    assert_eq!(
+        first
+            .get("user")
+            .unwrap()
+            .get(3)
+            .unwrap()
+            .get("Struct")
+            .unwrap()
+            .get("arg")
+            .unwrap()
+            .as_integer()
+            .unwrap(),
+        Integer::from(4_u8)
+    );
+    assert_eq!(
+        first.get("user").unwrap().get(0).unwrap().as_str().unwrap(),
+        "Unit"
+    );
+    assert_eq!(
+        first.get("tags").unwrap().get(1).unwrap().as_str().unwrap(),
+        "b"
+    );
+    assert!(first.get("enabled").unwrap().as_bool().unwrap());
+    assert_eq!(
+        first.get("score").unwrap().as_float().unwrap(),
+        Float::from(1.5_f32)
+    );
+    assert_eq!(first.get("data").unwrap().as_bytes().unwrap(), b"bytes");
+    assert!(first.get("missing").unwrap().is_none().unwrap());
+
+    // Navigate a fresh document directly to the second record, which only
+    // refers to symbols defined in the first record.
+    let doc = lazy::Document::new(&payload).unwrap();
+    assert_eq!(
+        doc.get(1)
+            .unwrap()
+            .get("user")
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .get("Struct")
+            .unwrap()
+            .get("arg")
+            .unwrap()
+            .as_integer()
+            .unwrap(),
+        Integer::from(5_u8)
+    );
+}
+
+#[test]
+fn lazy_document_errors() {
+    let payload = Config::new()
+        .compatibility(Compatibility::V5)
+        .serialize(&vec![EnumVariants::Unit, EnumVariants::Struct { arg: 1 }])
+        .unwrap();
+    let doc = lazy::Document::new(&payload).unwrap();
+    assert!(matches!(doc.get(2), Err(Error::NotFound)));
+    assert!(matches!(
+        doc.get(1).unwrap().get("Struct").unwrap().get("nope"),
+        Err(Error::NotFound)
+    ));
+    assert!(matches!(
+        doc.get(0).unwrap().get(0),
+        Err(Error::UnexpectedKind(
+            format::Kind::Symbol,
+            format::Kind::Sequence
+        ))
+    ));
+    assert!(matches!(
+        doc.get("id"),
+        Err(Error::UnexpectedKind(
+            format::Kind::Sequence,
+            format::Kind::Map
+        ))
+    ));
+    assert!(matches!(
+        lazy::Document::new(&payload[..3]),
+        Err(Error::Eof)
+    ));
+}
+
+#[test]
+fn lazy_document_interned_and_packed() {
+    use std::collections::BTreeMap;
+
+    use crate::packed::Packed;
+
+    #[derive(Serialize)]
+    struct Sample {
+        path: String,
+        labels: BTreeMap<String, String>,
+        readings: Packed<u16>,
+        offsets: Packed<f32>,
+        raw: Packed<u8>,
+    }
+
+    let path = String::from("/var/lib/pot/sample");
+    let samples = vec![
+        Sample {
+            path: path.clone(),
+            labels: BTreeMap::from([(String::from("location"), path.clone())]),
+            readings: Packed(vec![1000, 2000, 3000]),
+            offsets: Packed(vec![-0.5]),
+            raw: Packed(vec![1, 2, 3]),
+        },
+        Sample {
+            path: path.clone(),
+            labels: BTreeMap::from([(String::from("location"), String::from("north"))]),
+            readings: Packed(Vec::new()),
+            offsets: Packed(Vec::new()),
+            raw: Packed(Vec::new()),
+        },
+    ];
+    let config = Config::new().compatibility(Compatibility::V7);
+    let payload = config
+        .clone()
+        .intern_bytes(Some(4))
+        .serialize(&samples)
+        .unwrap();
+    assert!(payload.len() < config.serialize(&samples).unwrap().len());
+
+    // The second sample's path and label key refer to bytes interned by the
+    // first sample.
+    let doc = lazy::Document::new(&payload).unwrap();
+    let second = doc.get(1).unwrap();
+    assert_eq!(second.get("path").unwrap().as_str().unwrap(), path);
+    assert_eq!(
+        second
+            .get("labels")
+            .unwrap()
+            .get("location")
+            .unwrap()
+            .as_str()
+            .unwrap(),
+        "north"
+    );
+
+    let first = doc.get(0).unwrap();
+    assert_eq!(
+        first
+            .get("labels")
+            .unwrap()
+            .get("location")
+            .unwrap()
+            .as_bytes()
+            .unwrap(),
+        path.as_bytes()
+    );
+
+    let readings = first.get("readings").unwrap();
+    assert_eq!(readings.get(2).unwrap().kind().unwrap(), Kind::UInt);
+    assert_eq!(
+        readings.get(2).unwrap().as_integer().unwrap(),
+        Integer::from(3000_u16)
+    );
+    assert!(matches!(readings.get(3), Err(Error::NotFound)));
+    assert!(matches!(
+        readings.as_bytes(),
+        Err(Error::UnexpectedKind(Kind::Special, Kind::Bytes))
+    ));
+    assert!(matches!(
+        readings.get(0).unwrap().get(0),
+        Err(Error::UnexpectedKind(Kind::UInt, Kind::Sequence))
+    ));
+    assert_eq!(
+        first
+            .get("offsets")
+            .unwrap()
+            .get(0)
+            .unwrap()
+            .as_float()
+            .unwrap(),
+        Float::from(-0.5_f32)
+    );
+    assert_eq!(first.get("raw").unwrap().as_bytes().unwrap(), [1, 2, 3]);
+    assert!(matches!(
+        second.get("raw").unwrap().get(0),
+        Err(Error::NotFound)
+    ));
+}
+
+#[test]
+fn canonical_maps() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Entry {
+        name: String,
+        value: EnumVariants,
+    }
+
+    let canonical = Config::new().canonical(true);
+    let mut first = std::collections::HashMap::new();
+    let mut second = std::collections::HashMap::with_capacity(64);
+    for id in 0_u32..32 {
+        first.insert(
+            id,
+            Entry {
+                name: id.to_string(),
+                value: EnumVariants::Struct { arg: u64::from(id) },
+            },
+        );
+    }
+    for id in (0_u32..64).rev() {
+        second.insert(
+            id,
+            Entry {
+                name: id.to_string(),
+                value: EnumVariants::Struct { arg: u64::from(id) },
+            },
+        );
+    }
+    second.retain(|id, _| *id < 32);
+
+    let first_bytes = canonical.serialize(&first).unwrap();
+    assert_eq!(first_bytes, canonical.serialize(&second).unwrap());
+
+    // Symbols are assigned ids in the order they appear in the output, so the
+    // payload deserializes like any other.
+    let decoded: std::collections::HashMap<u32, Entry> = from_slice(&first_bytes).unwrap();
+    assert_eq!(decoded, first);
+    let value: Value<'_> = from_slice(&first_bytes).unwrap();
+
+    // Entries are sorted by their encoded keys.
+    let keys = match value {
+        Value::Mappings(mappings) => mappings
+            .into_iter()
+            .map(|(key, _)| key.as_integer().and_then(|key| key.as_u32().ok()))
+            .collect::<Option<Vec<_>>>()
+            .unwrap(),
+        other => unreachable!("expected mappings, got {other:?}"),
+    };
+    let mut sorted_keys = keys.clone();
+    sorted_keys.sort_by_key(|key| to_vec(key).unwrap());
+    assert_eq!(keys, sorted_keys);
+}
+
+#[test]
+fn canonical_sequences_and_floats() {
+    let canonical = Config::new().canonical(true);
+    let streamed = canonical.serialize(&Streamed(vec![1_u8, 2, 3])).unwrap();
+    assert_eq!(streamed, canonical.serialize(&vec![1_u8, 2, 3]).unwrap());
+    assert_eq!(from_slice::<Vec<u8>>(&streamed).unwrap(), vec![1, 2, 3]);
+
+    let nan = canonical.serialize(&f64::NAN).unwrap();
+    assert_eq!(nan, canonical.serialize(&-f64::NAN).unwrap());
+    assert_eq!(nan, canonical.serialize(&f32::NAN).unwrap());
+    assert_eq!(nan.len(), 4 + 3);
+    assert!(from_slice::<f64>(&nan).unwrap().is_nan());
+    assert_eq!(
+        canonical.serialize(&1.5_f64).unwrap(),
+        canonical.serialize(&1.5_f32).unwrap()
+    );
+}
+
+#[test]
+fn value_ordering() {
+    let mut values = vec![
+        Value::Mappings(vec![
+            (Value::from("b"), Value::from(2_u8)),
+            (Value::from("a"), Value::from(1_u64)),
+        ]),
+        Value::from(f64::NAN),
+        Value::from(vec![Value::from(1_u8)]),
+        Value::from("str"),
+        Value::from(-1_i8),
+        Value::Unit,
+        Value::from(1_u128),
+        Value::from(0.5_f32),
+        Value::None,
+        Value::from(true),
+        Value::from(i64::MIN),
+        Value::from(u64::MAX),
+    ];
+    values.sort();
+    assert_eq!(
+        values,
+        [
+            Value::None,
+            Value::Unit,
+            Value::from(true),
+            Value::from(i64::MIN),
+            Value::from(-1_i8),
+            Value::from(1_u128),
+            Value::from(u64::MAX),
+            Value::from(0.5_f64),
+            Value::from(f32::NAN),
+            Value::from(&b"str"[..]),
+            Value::from(vec![Value::from(1_u8)]),
+            Value::Mappings(vec![
+                (Value::from("a"), Value::from(1_u8)),
+                (Value::from("b"), Value::from(2_i16)),
+            ]),
+        ]
+    );
+    assert!(Value::from(1_u8) != Value::from(1_f32));
+    assert!(Value::from(-0_f64) == Value::from(0_f32));
+    assert!(Value::from(f64::NAN) > Value::from(f64::INFINITY));
+}
+
+#[test]
+fn value_hashing() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::HashSet;
+    use std::hash::{Hash, Hasher};
+
+    fn hash_of(value: &Value<'_>) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let equal_pairs = [
+        (Value::from(300_u16), Value::from(300_i64)),
+        (Value::from(-5_i8), Value::from(-5_i128)),
+        (Value::from(1.5_f32), Value::from(1.5_f64)),
+        (Value::from(f32::NAN), Value::from(-f64::NAN)),
+        (Value::from(-0_f64), Value::from(0_f32)),
+        (Value::from("bytes"), Value::from(&b"bytes"[..])),
+        (
+            Value::Mappings(vec![
+                (Value::from(1_u8), Value::None),
+                (Value::from(2_u8), Value::Unit),
+            ]),
+            Value::Mappings(vec![
+                (Value::from(2_u32), Value::Unit),
+                (Value::from(1_u32), Value::None),
+            ]),
+        ),
+        (
+            Value::from_mappings([(
+                "nested",
+                Value::from_mappings([("a", Value::Unit), ("b", Value::None)]),
+            )]),
+            Value::from_mappings([(
+                "nested",
+                Value::from_mappings([("b", Value::None), ("a", Value::Unit)]),
+            )]),
+        ),
+        (
+            Value::Packed(crate::packed::PackedArray::new(&[1_u16, 2])),
+            Value::from(vec![Value::from(1_u8), Value::from(2_i64)]),
+        ),
+    ];
+    for (left, right) in &equal_pairs {
+        assert_eq!(left, right);
+        assert_eq!(left.cmp(right), std::cmp::Ordering::Equal);
+        assert_eq!(hash_of(left), hash_of(right));
+    }
+
+    let unique = equal_pairs
+        .into_iter()
+        .flat_map(|(left, right)| [OwnedValue::from(left), OwnedValue::from(right)])
+        .collect::<HashSet<_>>();
+    assert_eq!(unique.len(), 9);
+
+    // Sorted and unsorted mappings are ordered by their sorted entries.
+    let sorted = Value::from_mappings([("a", 1_u8), ("c", 1_u8)]);
+    let unsorted = Value::from_mappings([("b", 1_u8), ("a", 2_u8)]);
+    assert!(sorted < unsorted);
+    assert!(unsorted > sorted);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_round_trip() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Record {
+        name: String,
+        #[serde(with = "serde_bytes")]
+        data: Vec<u8>,
+        variants: Vec<EnumVariants>,
+        by_id: BTreeMap<u32, Option<String>>,
+        dollars: BTreeMap<String, u8>,
+        aliases: BTreeMap<String, String>,
+        unit: (),
+        big: i128,
+        huge: u128,
+        float: f32,
+        infinity: f64,
+    }
+
+    let record = Record {
+        name: String::from("record"),
+        data: vec![0xff, 0, 1],
+        variants: vec![
+            EnumVariants::Unit,
+            EnumVariants::Tuple(1),
+            EnumVariants::TupleTwoArgs(2, 3),
+            EnumVariants::Struct { arg: 4 },
+        ],
+        by_id: [(1, Some(String::from("one"))), (2, None)]
+            .into_iter()
+            .collect(),
+        dollars: [(String::from("$unit"), 1)].into_iter().collect(),
+        aliases: [(String::from("record"), String::from("one"))]
+            .into_iter()
+            .collect(),
+        unit: (),
+        big: i128::MIN,
+        huge: u128::MAX,
+        float: 1.1,
+        infinity: f64::NEG_INFINITY,
+    };
+
+    for config in [
+        Config::new().compatibility(Compatibility::V4),
+        Config::new().compatibility(Compatibility::V5),
+        // Interns every string and bytes value, which makes the keys and values
+        // of `aliases` references to earlier values.
+        Config::new()
+            .compatibility(Compatibility::V7)
+            .intern_bytes(Some(1)),
+    ] {
+        let payload = config.serialize(&record).unwrap();
+        let mut json = Vec::new();
+        json::to_json_writer(&payload, &mut json).unwrap();
+        let round_tripped = json::from_json_reader(&json[..]).unwrap();
+        assert_eq!(from_slice::<Record>(&round_tripped).unwrap(), record);
+    }
+
+    let nan = json::from_json_reader(&br#"{"$float": "NaN"}"#[..]).unwrap();
+    assert!(from_slice::<f64>(&nan).unwrap().is_nan());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_representation() {
+    let payload = Config::new()
+        .compatibility(Compatibility::V5)
+        .serialize(&(
+            EnumVariants::Struct { arg: 1 },
+            serde_bytes::Bytes::new(b"\xff"),
+            (),
+            std::collections::BTreeMap::from([(1_u8, "a")]),
+        ))
+        .unwrap();
+    let mut json = Vec::new();
+    json::to_json_writer(&payload, &mut json).unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert_eq!(
+        json,
+        r#"[
+  {
+    "$named": {
+      "Struct": {
+        "arg": 1
+      }
+    }
+  },
+  {
+    "$bytes": "/w=="
+  },
+  {
+    "$unit": null
+  },
+  {
+    "$map": [
+      [
+        1,
+        "a"
+      ]
+    ]
+  }
+]"#
+    );
+
+    assert!(matches!(
+        json::from_json_reader(&br#"{"$unit": null, "other": 1}"#[..]),
+        Err(Error::Message(_))
+    ));
+    assert!(matches!(
+        json::from_json_reader(&br#"{"$bytes": "not base64!"}"#[..]),
+        Err(Error::Message(_))
+    ));
+    assert!(matches!(
+        json::from_json_reader(&b"[1, 2"[..]),
+        Err(Error::Message(_))
+    ));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn json_nesting_limit() {
+    let nested = |depth: usize| {
+        let mut payload = Vec::new();
+        format::write_header(&mut payload, CURRENT_VERSION).unwrap();
+        for _ in 0..depth {
+            format::write_atom_header(&mut payload, Kind::Sequence, 1).unwrap();
+        }
+        format::write_u8(&mut payload, 0).unwrap();
+        payload
+    };
+
+    let mut json = Vec::new();
+    json::to_json_writer(&nested(128), &mut json).unwrap();
+    assert!(matches!(
+        json::to_json_writer(&nested(129), Vec::new()),
+        Err(Error::TooDeeplyNested)
+    ));
+    assert!(matches!(
+        json::to_json_writer(&nested(200_000), Vec::new()),
+        Err(Error::TooDeeplyNested)
+    ));
+}
+
+#[test]
+fn dynamic_sequence_tuples() {
+    let mut writer = format::Writer::new_with_compatibility(Vec::new(), Compatibility::V5).unwrap();
+    writer.begin_dynamic_sequence().unwrap();
+    for value in [1, 2] {
+        writer.begin_dynamic_sequence().unwrap();
+        writer.write_u64(value).unwrap();
+        writer.write_u64(value + 1).unwrap();
+        writer.end().unwrap();
+    }
+    writer.end().unwrap();
+    let payload = writer.finish().unwrap();
+
+    // Tuple visitors stop reading after their last element, which must not
+    // leave the end of each sequence unread.
+    assert_eq!(
+        from_slice::<Vec<(u8, u8)>>(&payload).unwrap(),
+        vec![(1, 2), (2, 3)]
+    );
+    assert!(from_slice::<Vec<(u8,)>>(&payload).is_err());
+}
+
+#[test]
+fn text_round_trip() {
+    use crate::packed::PackedArray;
+
+    let value = Value::from_mappings([
+        (Value::from("unit"), Value::Unit),
+        (Value::from("none"), Value::None),
+        (Value::from("None"), Value::Bool(true)),
+        (
+            Value::from("widths"),
+            Value::Sequence(vec![
+                Value::Integer(Integer(format::InnerInteger::U16(1))),
+                Value::Integer(Integer(format::InnerInteger::I64(-2))),
+                Value::from(u128::MAX),
+                Value::from(i128::MIN),
+                Value::from(1.5_f32),
+                Value::from(0.1_f64),
+                Value::from(-0.0_f64),
+                Value::from(1e300_f64),
+                Value::from(f32::NAN),
+                Value::from(f64::NEG_INFINITY),
+            ]),
+        ),
+        (
+            Value::from(vec![0_u8, b'"', 0xff]),
+            Value::from("quote \" slash \\ line\ntab\t\u{1} snowman \u{2603}"),
+        ),
+        (Value::from(1_u8), Value::from(&b"text"[..])),
+        (Value::from("two words"), Value::Sequence(Vec::new())),
+        (Value::Sequence(Vec::new()), Value::Mappings(Vec::new())),
+        (
+            Value::from("packed"),
+            Value::Sequence(vec![
+                Value::Packed(PackedArray::new(&[1000_u16, 2000])),
+                Value::Packed(PackedArray::new(&[-1_i8, i8::MIN])),
+                Value::Packed(PackedArray::new(&[u64::MAX])),
+                Value::Packed(PackedArray::new(&[1.5_f32, f32::NAN, f32::NEG_INFINITY])),
+                Value::Packed(PackedArray::new(&[0.1_f64, -0.0, 1e300])),
+                Value::Packed(PackedArray::new::<i32>(&[])),
+            ]),
+        ),
+    ]);
+    let text = value.to_text_pretty();
+    let parsed = text::parse(&text).unwrap();
+    // Debug output includes the widths of numbers and distinguishes bytes
+    // from strings, which equality does not.
+    assert_eq!(format!("{parsed:?}"), format!("{value:?}"));
+    assert_eq!(
+        text::parse("[1u16, 2i8, 300, -1, 2.5, b\"a\", \"a\"]")
+            .unwrap()
+            .to_text_pretty(),
+        "[\n    1u16,\n    2i8,\n    300u16,\n    -1i8,\n    2.5f64,\n    b\"a\",\n    \"a\",\n]"
+    );
+    // Packed arrays name their element type, and their elements have no
+    // suffixes.
+    assert_eq!(
+        text::parse("u16[1, 2_000,]").unwrap().to_text_pretty(),
+        "u16[\n    1,\n    2000,\n]"
+    );
+    assert_eq!(
+        text::parse("f32[]").unwrap(),
+        Value::Packed(PackedArray::new::<f32>(&[]))
+    );
+}
+
+#[test]
+fn text_symbols_and_named() {
+    #[derive(Deserialize, Debug, PartialEq)]
+    enum Shape {
+        Empty,
+        Circle { radius: f32 },
+    }
+
+    let value = text::parse(
+        "[
+            // Symbols are parsed as strings.
+            Empty,
+            Circle({radius: 1_000.5f32,}),
+        ]",
+    )
+    .unwrap();
+    assert_eq!(
+        value,
+        Value::Sequence(vec![
+            Value::from("Empty"),
+            Value::from_mappings([(
+                "Circle",
+                Value::from_mappings([("radius", Value::from(1000.5_f32))])
+            )]),
+        ])
+    );
+    assert_eq!(
+        value.deserialize_as::<Vec<Shape>>().unwrap(),
+        vec![Shape::Empty, Shape::Circle { radius: 1000.5 }]
+    );
+
+    for invalid in [
+        "",
+        "[1, 2",
+        "[1 2]",
+        "[,]",
+        "{a 1}",
+        "1u7",
+        "256u8",
+        "-1u8",
+        "1.5i32",
+        "b\"\\u{1}\u{2603}\"",
+        "\"\\q\"",
+        "Name(1",
+        "1 2",
+        "u8[256]",
+        "u16[1.5]",
+        "i8[1i8]",
+        "u8[1",
+    ] {
+        assert!(
+            matches!(text::parse(invalid), Err(Error::Message(_))),
+            "{invalid:?} parsed"
+        );
+    }
+}
+
+#[test]
+fn text_nesting_limit() {
+    let nested = |depth: usize, open: &str, close: &str| {
+        format!("{}(){}", open.repeat(depth), close.repeat(depth))
+    };
+    assert!(text::parse(&nested(128, "[", "]")).is_ok());
+    assert!(text::parse(&nested(128, "A(", ")")).is_ok());
+    for (open, close) in [("[", "]"), ("{a: ", "}"), ("A(", ")")] {
+        assert!(matches!(
+            text::parse(&nested(129, open, close)),
+            Err(Error::TooDeeplyNested)
+        ));
+    }
+    assert!(matches!(
+        text::parse(&"[".repeat(1_000_000)),
+        Err(Error::TooDeeplyNested)
+    ));
+}
+
+#[test]
+fn custom_writer() {
+    struct FixedBuffer {
+        bytes: [u8; 16],
+        len: usize,
+    }
+
+    impl crate::writer::Write for &mut FixedBuffer {
+        fn write_all(&mut self, bytes: &[u8]) -> Result<()> {
+            let end = self.len + bytes.len();
+            let Some(destination) = self.bytes.get_mut(self.len..end) else {
+                return Err(Error::Message(String::from("buffer full")));
+            };
+            destination.copy_from_slice(bytes);
+            self.len = end;
+            Ok(())
+        }
+    }
+
+    let mut buffer = FixedBuffer {
+        bytes: [0; 16],
+        len: 0,
+    };
+    crate::to_writer(&(1_u8, "hi"), &mut buffer).unwrap();
+    assert_eq!(
+        crate::from_slice::<(u8, String)>(&buffer.bytes[..buffer.len]).unwrap(),
+        (1, String::from("hi"))
+    );
+
+    let mut buffer = FixedBuffer {
+        bytes: [0; 16],
+        len: 0,
+    };
+    assert!(matches!(
+        crate::to_writer(&"a string that will not fit", &mut buffer),
+        Err(Error::Message(_))
+    ));
+}
+
+#[cfg(feature = "async")]
+fn block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    use std::thread::{self, Thread};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+    let mut future = std::pin::pin!(future);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn async_round_trip() {
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// Returns one byte per read, and is pending before every other poll.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        ready: bool,
+    }
+
+    impl futures_io::AsyncRead for Trickle<'_> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.ready = !self.ready;
+            if !self.ready {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            let Some((first, remaining)) = self.data.split_first() else {
+                return Poll::Ready(Ok(0));
+            };
+            buf[0] = *first;
+            self.data = remaining;
+            Poll::Ready(Ok(1))
+        }
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Message {
+        Hello { name: String, tags: Vec<String> },
+        Data(#[serde(with = "serde_bytes")] Vec<u8>),
+    }
+
+    let config = Config::new().compatibility(Compatibility::V5);
+    let first = Message::Hello {
+        name: String::from("pot"),
+        tags: vec![String::from("a"), String::from("b")],
+    };
+    let second = Message::Data(vec![0xAA; 300]);
+    let mut stream = Vec::new();
+    block_on(config.serialize_into_async(&first, &mut stream)).unwrap();
+    block_on(config.serialize_into_async(&second, &mut stream)).unwrap();
+    // A sequence of unknown length.
+    let mut writer = format::Writer::new_with_compatibility(Vec::new(), Compatibility::V5).unwrap();
+    writer.begin_dynamic_sequence().unwrap();
+    for value in [1, 1_000, 1_000_000] {
+        writer.write_u64(value).unwrap();
+    }
+    writer.end().unwrap();
+    let third = writer.finish().unwrap();
+    stream.extend_from_slice(&third);
+
+    let mut reader = Trickle {
+        data: &stream,
+        ready: false,
+    };
+    assert_eq!(
+        block_on(config.deserialize_from_async::<Message, _>(&mut reader)).unwrap(),
+        first
+    );
+    assert_eq!(
+        block_on(config.deserialize_from_async::<Message, _>(&mut reader)).unwrap(),
+        second
+    );
+    assert_eq!(reader.data, &third[..]);
+    assert_eq!(
+        block_on(config.deserialize_from_async::<Vec<u32>, _>(&mut reader)).unwrap(),
+        vec![1, 1_000, 1_000_000]
+    );
+    assert!(reader.data.is_empty());
+
+    let truncated = &stream[..stream.len() - 1];
+    let mut reader = Trickle {
+        data: truncated,
+        ready: false,
+    };
+    block_on(config.deserialize_from_async::<Message, _>(&mut reader)).unwrap();
+    block_on(config.deserialize_from_async::<Message, _>(&mut reader)).unwrap();
+    assert!(matches!(
+        block_on(config.deserialize_from_async::<Vec<u32>, _>(&mut reader)),
+        Err(Error::Io(err)) if err.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+
+    let mut reader = &stream[..];
+    block_on(
+        config
+            .clone()
+            .allocation_budget(100)
+            .deserialize_from_async::<Message, _>(&mut reader),
+    )
+    .unwrap();
+    assert!(matches!(
+        block_on(
+            config
+                .allocation_budget(100)
+                .deserialize_from_async::<Message, _>(&mut reader)
+        ),
+        Err(Error::TooManyBytesRead)
+    ));
+}
+
+#[test]
+fn stream_deserializer() {
+    let config = Config::default().compatibility(Compatibility::V4);
+    let mut payloads = Vec::new();
+    let mut offsets = Vec::new();
+    for value in [
+        EnumVariants::Unit,
+        EnumVariants::Tuple(1),
+        EnumVariants::Struct { arg: 2 },
+    ] {
+        config.serialize_into(&value, &mut payloads).unwrap();
+        offsets.push(payloads.len());
+    }
+
+    let mut stream = config.deserialize_stream::<EnumVariants>(&payloads);
+    assert_eq!(stream.byte_offset(), 0);
+    assert_eq!(stream.next().unwrap().unwrap(), EnumVariants::Unit);
+    assert_eq!(stream.byte_offset(), offsets[0]);
+    assert_eq!(stream.next().unwrap().unwrap(), EnumVariants::Tuple(1));
+    assert_eq!(stream.byte_offset(), offsets[1]);
+    assert_eq!(
+        stream.next().unwrap().unwrap(),
+        EnumVariants::Struct { arg: 2 }
+    );
+    assert_eq!(stream.byte_offset(), offsets[2]);
+    assert!(stream.next().is_none());
+
+    // Reading from a `Read` implementer never reads past the current payload.
+    let mut reader = &payloads[..];
+    let mut stream = config.deserialize_stream_from::<EnumVariants, _>(&mut reader);
+    assert_eq!(stream.next().unwrap().unwrap(), EnumVariants::Unit);
+    assert_eq!(stream.byte_offset(), offsets[0]);
+    drop(stream);
+    assert_eq!(reader, &payloads[offsets[0]..]);
+    let values = config
+        .deserialize_stream_from::<EnumVariants, _>(reader)
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
+    assert_eq!(values.len(), 2);
+
+    // Truncated input returns an error, and then the iterator ends.
+    let truncated = &payloads[..offsets[2] - 1];
+    let mut stream = config.deserialize_stream::<EnumVariants>(truncated);
+    assert_eq!(stream.by_ref().take(2).count(), 2);
+    assert!(matches!(stream.next(), Some(Err(Error::Eof))));
+    assert!(stream.next().is_none());
+    let mut stream = config.deserialize_stream_from::<EnumVariants, _>(truncated);
+    assert_eq!(stream.by_ref().take(2).count(), 2);
+    assert!(matches!(
+        stream.next(),
+        Some(Err(Error::Io(err))) if err.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+    assert!(stream.next().is_none());
+
+    // Trailing bytes that are not a payload.
+    let mut stream = config.deserialize_stream::<EnumVariants>(b"Pot");
+    assert!(matches!(stream.next(), Some(Err(Error::Eof))));
+    let mut stream = config.deserialize_stream::<EnumVariants>(b"nope");
+    assert!(matches!(
+        stream.next(),
+        Some(Err(Error::IncompatibleVersion))
+    ));
+}
+
+#[test]
+fn stream_deserializer_persistent_symbols() {
+    let mut sender = ser::SymbolMap::new();
+    let mut payloads = Vec::new();
+    for arg in 0..3 {
+        sender
+            .serialize_to(&mut payloads, &EnumVariants::Struct { arg })
+            .unwrap();
+    }
+
+    let mut stream = Config::default()
+        .deserialize_stream_from::<EnumVariants, _>(&payloads[..])
+        .with_symbol_map(de::SymbolMap::new());
+    for arg in 0..3 {
+        assert_eq!(
+            stream.next().unwrap().unwrap(),
+            EnumVariants::Struct { arg }
+        );
+    }
+    assert!(stream.next().is_none());
+    assert_eq!(stream.into_symbol_map().unwrap().len(), 2);
+
+    // Without the shared symbols, later payloads refer to unknown symbols.
+    let mut stream = Config::default().deserialize_stream::<EnumVariants>(&payloads);
+    assert!(stream.next().unwrap().is_ok());
+    assert!(matches!(stream.next(), Some(Err(Error::UnknownSymbol(_)))));
+}
+
+#[test]
+fn incremental_decoder() {
+    use crate::incremental::{Decoder, Status};
+
+    let config = Config::default().compatibility(Compatibility::V4);
+    let values = [
+        EnumVariants::Unit,
+        EnumVariants::Tuple(1),
+        EnumVariants::Struct { arg: 2 },
+    ];
+    let mut payloads = Vec::new();
+    for value in &values {
+        config.serialize_into(value, &mut payloads).unwrap();
+    }
+
+    // One byte at a time. Every payload needs at least its header.
+    let mut decoder = Decoder::with_config(&config);
+    let mut received = Vec::new();
+    for byte in &payloads {
+        match decoder
+            .feed::<EnumVariants>(std::slice::from_ref(byte))
+            .unwrap()
+        {
+            Status::NeedMore(needed) => assert!(needed > 0),
+            Status::Complete(value) => received.push(value),
+        }
+    }
+    assert_eq!(received, values);
+    assert_eq!(decoder.buffered_len(), 0);
+    assert_eq!(
+        decoder.feed::<EnumVariants>(&[]).unwrap(),
+        Status::NeedMore(4)
+    );
+
+    // All payloads at once, draining with empty feeds.
+    let mut decoder = Decoder::new();
+    assert_eq!(
+        decoder.feed::<EnumVariants>(&payloads).unwrap(),
+        Status::Complete(EnumVariants::Unit)
+    );
+    assert_eq!(
+        decoder.feed::<EnumVariants>(&[]).unwrap(),
+        Status::Complete(EnumVariants::Tuple(1))
+    );
+    assert_eq!(
+        decoder.feed::<EnumVariants>(&[]).unwrap(),
+        Status::Complete(EnumVariants::Struct { arg: 2 })
+    );
+
+    // The minimum number of bytes needed covers a partially received atom.
+    let payload = to_vec(&"hello world").unwrap();
+    let mut decoder = Decoder::new();
+    assert_eq!(
+        decoder.feed::<String>(&payload[..6]).unwrap(),
+        Status::NeedMore(payload.len() - 6)
+    );
+
+    // Corruption is reported as soon as it is received.
+    let mut decoder = Decoder::new();
+    assert!(matches!(
+        decoder.feed::<EnumVariants>(b"Pat"),
+        Ok(Status::NeedMore(1))
+    ));
+    assert!(matches!(
+        decoder.feed::<EnumVariants>(b"\0"),
+        Err(Error::IncompatibleVersion)
+    ));
+    let mut decoder = Decoder::new();
+    // A sequence of two items containing a reference to an undefined symbol.
+    assert!(matches!(
+        decoder.feed::<Vec<String>>(&[b'P', b'o', b't', 0, 0x82, 0xC1]),
+        Err(Error::UnknownSymbol(0))
+    ));
+
+    // The allocation budget includes the bytes buffered.
+    let mut decoder = Decoder::with_config(&Config::default().allocation_budget(10));
+    assert!(matches!(
+        decoder.feed::<String>(&payload[..6]),
+        Err(Error::TooManyBytesRead)
+    ));
+}
+
+#[test]
+fn incremental_decoder_persistent_symbols() {
+    use crate::incremental::{Decoder, Status};
+
+    let mut sender = ser::SymbolMap::new();
+    let mut decoder = Decoder::new().with_symbol_map(de::SymbolMap::new());
+    for arg in 0..3 {
+        let payload = sender
+            .serialize_to_vec(&EnumVariants::Struct { arg })
+            .unwrap();
+        assert_eq!(
+            decoder.feed::<EnumVariants>(&payload).unwrap(),
+            Status::Complete(EnumVariants::Struct { arg })
+        );
+    }
+    assert_eq!(decoder.symbol_map().unwrap().len(), 2);
+}
+
+#[test]
+fn max_depth() {
+    fn nested_sequences(depth: usize) -> Vec<u8> {
+        // Sequences containing one value, ending with an unsigned 0.
+        let mut payload = vec![b'P', b'o', b't', 0];
+        payload.resize(depth + 4, 0x81);
+        payload.push(0x40);
+        payload
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum Recursive {
+        Leaf,
+        Node(Box<Recursive>),
+    }
+
+    // Untrusted payloads must not be able to overflow the stack.
+    let payload = nested_sequences(100_000);
+    assert!(matches!(
+        from_slice::<Value<'_>>(&payload),
+        Err(Error::TooDeeplyNested)
+    ));
+    assert!(matches!(
+        de::SymbolMap::new().deserialize_slice::<Value<'_>>(&payload),
+        Err(Error::TooDeeplyNested)
+    ));
+    assert!(matches!(
+        Config::default().deserialize_from::<OwnedValue, _>(&payload[..]),
+        Err(Error::TooDeeplyNested)
+    ));
+
+    let payload = nested_sequences(128);
+    assert!(from_slice::<Value<'_>>(&payload).is_ok());
+    let payload = nested_sequences(129);
+    assert!(matches!(
+        from_slice::<Value<'_>>(&payload),
+        Err(Error::TooDeeplyNested)
+    ));
+    assert!(Config::default()
+        .max_depth(129)
+        .deserialize::<Value<'_>>(&payload)
+        .is_ok());
+    assert_eq!(
+        Config::default()
+            .max_depth(3)
+            .deserialize::<Vec<Vec<Vec<u8>>>>(&nested_sequences(3))
+            .unwrap(),
+        vec![vec![vec![0]]]
+    );
+    assert!(matches!(
+        Config::default()
+            .max_depth(2)
+            .deserialize::<Vec<Vec<Vec<u8>>>>(&nested_sequences(3)),
+        Err(Error::TooDeeplyNested)
+    ));
+
+    // Enum variants are counted as a level of nesting.
+    let mut value = Recursive::Leaf;
+    for _ in 0..10 {
+        value = Recursive::Node(Box::new(value));
+    }
+    let payload = to_vec(&value).unwrap();
+    assert_eq!(
+        Config::default()
+            .max_depth(11)
+            .deserialize::<Recursive>(&payload)
+            .unwrap(),
+        value
+    );
+    assert!(matches!(
+        Config::default()
+            .max_depth(10)
+            .deserialize::<Recursive>(&payload),
+        Err(Error::TooDeeplyNested)
+    ));
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct LimitedFields {
+    a: u8,
+    bcd: u8,
+}
+
+#[test]
+fn limits() {
+    /// Serializes as a sequence of unknown length.
+    struct Unsized(Vec<u8>);
+
+    impl Serialize for Unsized {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            use serde::ser::SerializeSeq;
+
+            let mut seq = serializer.serialize_seq(None)?;
+            for value in &self.0 {
+                seq.serialize_element(value)?;
+            }
+            seq.end()
+        }
+    }
+
+    fn check<T>(payload: &[u8], limits: Limits) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned + Debug,
+    {
+        let config = Config::new().limits(limits);
+        let from_slice = config.deserialize::<T>(payload);
+        let from_read = config.deserialize_from::<T, _>(payload);
+        let validated = crate::validate(payload, &limits);
+        match (&from_slice, &from_read, &validated) {
+            (Ok(_), Ok(_), Ok(_)) => {}
+            (Err(a), Err(b), Err(c)) => {
+                assert_eq!(a.to_string(), b.to_string());
+                assert_eq!(a.to_string(), c.to_string());
+            }
+            other => unreachable!("inconsistent results: {other:?}"),
+        }
+        from_slice
+    }
+
+    let payload = to_vec(&"hello").unwrap();
+    assert!(check::<String>(&payload, Limits::new().max_bytes_length(5)).is_ok());
+    assert!(matches!(
+        check::<String>(&payload, Limits::new().max_bytes_length(4)),
+        Err(Error::BytesTooLong)
+    ));
+    assert!(check::<String>(&payload, Limits::new().max_input_bytes(payload.len())).is_ok());
+    assert!(matches!(
+        check::<String>(&payload, Limits::new().max_input_bytes(payload.len() - 1)),
+        Err(Error::TooManyInputBytes)
+    ));
+
+    let payload = to_vec(&vec![1_u8, 2, 3]).unwrap();
+    assert!(check::<Vec<u8>>(&payload, Limits::new().max_container_length(3)).is_ok());
+    assert!(matches!(
+        check::<Vec<u8>>(&payload, Limits::new().max_container_length(2)),
+        Err(Error::ContainerTooLong)
+    ));
+    let payload = Config::new()
+        .compatibility(Compatibility::V5)
+        .serialize(&Unsized(vec![1, 2, 3]))
+        .unwrap();
+    assert!(check::<Vec<u8>>(&payload, Limits::new().max_container_length(3)).is_ok());
+    assert!(matches!(
+        check::<Vec<u8>>(&payload, Limits::new().max_container_length(2)),
+        Err(Error::ContainerTooLong)
+    ));
+
+    let payload = to_vec(&LimitedFields { a: 1, bcd: 2 }).unwrap();
+    assert!(
+        check::<LimitedFields>(&payload, Limits::new().max_symbols(2).max_symbol_bytes(4)).is_ok()
+    );
+    assert!(matches!(
+        check::<LimitedFields>(&payload, Limits::new().max_symbols(1)),
+        Err(Error::TooManySymbols)
+    ));
+    assert!(matches!(
+        check::<LimitedFields>(&payload, Limits::new().max_symbol_bytes(3)),
+        Err(Error::TooManySymbolBytes)
+    ));
+    assert!(matches!(
+        check::<LimitedFields>(&payload, Limits::new().max_atoms(4)),
+        Err(Error::TooManyAtoms)
+    ));
+}
+
+#[test]
+fn limits_streaming() {
+    #[derive(Serialize)]
+    struct Extra {
+        e: u8,
+    }
+
+    // Symbols known from earlier payloads count towards the symbol limits.
+    let mut sender = ser::SymbolMap::new();
+    let mut receiver = de::SymbolMap::new();
+    let config = Config::new().limits(Limits::new().max_symbol_bytes(4));
+    let first = sender
+        .serialize_to_vec(&LimitedFields { a: 1, bcd: 2 })
+        .unwrap();
+    let mut stream = config
+        .deserialize_stream::<LimitedFields>(&first)
+        .with_symbol_map(receiver);
+    stream.next().unwrap().unwrap();
+    receiver = stream.into_symbol_map().unwrap();
+    let second = sender.serialize_to_vec(&Extra { e: 1 }).unwrap();
+    let mut stream = config
+        .deserialize_stream::<OwnedValue>(&second)
+        .with_symbol_map(receiver);
+    assert!(matches!(
+        stream.next().unwrap(),
+        Err(Error::TooManySymbolBytes)
+    ));
+
+    // Reading stops before an atom that would exceed the input limit.
+    let payload = to_vec(&"a".repeat(1024)).unwrap();
+    let config = Config::new().limits(Limits::new().max_input_bytes(16));
+    let mut stream = config.deserialize_stream_from::<String, _>(&payload[..]);
+    assert!(matches!(
+        stream.next().unwrap(),
+        Err(Error::TooManyInputBytes)
+    ));
+    assert!(stream.byte_offset() < 16);
+    let mut decoder = incremental::Decoder::with_config(&config);
+    assert!(matches!(
+        decoder.feed::<String>(&payload[..8]),
+        Err(Error::TooManyInputBytes)
+    ));
+}
+
+#[test]
+fn error_positions() {
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct Record {
+        id: u32,
+        tags: BTreeMap<String, u16>,
+        by_index: BTreeMap<u8, String>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct NarrowRecord {
+        id: u32,
+        tags: BTreeMap<String, u8>,
+        by_index: BTreeMap<u8, u32>,
+    }
+
+    let records = vec![
+        Record {
+            id: 1,
+            tags: BTreeMap::from([(String::from("ok"), 1)]),
+            by_index: BTreeMap::new(),
+        },
+        Record {
+            id: 2,
+            tags: BTreeMap::from([(String::from("large"), 1000)]),
+            by_index: BTreeMap::new(),
+        },
+    ];
+    let payload = to_vec(&records).unwrap();
+    let config = Config::new().error_positions(true);
+
+    let err = config
+        .deserialize::<Vec<NarrowRecord>>(&payload)
+        .unwrap_err();
+    assert_eq!(err.path(), Some("[1].tags.large"));
+    assert!(matches!(
+        err.without_position(),
+        Error::ImpreciseCastWouldLoseData
+    ));
+    let offset = err.offset().unwrap();
+    assert!(offset > payload.len() / 2 && offset < payload.len());
+    assert_eq!(
+        err.to_string(),
+        format!("numerical data cannot fit at [1].tags.large (byte {offset})")
+    );
+
+    let err = config
+        .deserialize_from::<Vec<NarrowRecord>, _>(&payload[..])
+        .unwrap_err();
+    assert_eq!(err.path(), Some("[1].tags.large"));
+    assert_eq!(err.offset(), Some(offset));
+
+    // Positions are only attached when enabled.
+    let err = from_slice::<Vec<NarrowRecord>>(&payload).unwrap_err();
+    assert!(matches!(err, Error::ImpreciseCastWouldLoseData));
+    assert_eq!(err.path(), None);
+
+    let records = vec![Record {
+        id: 1,
+        tags: BTreeMap::new(),
+        by_index: BTreeMap::from([(7, String::from("seven"))]),
+    }];
+    let payload = to_vec(&records).unwrap();
+    let err = config
+        .deserialize::<Vec<NarrowRecord>>(&payload)
+        .unwrap_err();
+    assert_eq!(err.path(), Some("[0].by_index[7]"));
+
+    // Errors in the root value have an empty path.
+    let err = config
+        .deserialize::<u8>(&to_vec(&1000_u16).unwrap())
+        .unwrap_err();
+    assert_eq!(err.path(), Some(""));
+}
+
+#[test]
+fn lenient() {
+    #[derive(Serialize, Debug)]
+    struct Stored {
+        id: u64,
+        nested: Vec<Vec<u8>>,
+        name: String,
+        after: u8,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Record {
+        id: u8,
+        nested: u32,
+        name: String,
+        after: u8,
+    }
+
+    let payload = to_vec(&Stored {
+        id: 1000,
+        nested: vec![vec![1, 2], vec![3]],
+        name: String::from("abc"),
+        after: 42,
+    })
+    .unwrap();
+    // Replace the first character of the name with an invalid UTF-8 byte.
+    let mut payload = payload;
+    let name = payload.windows(3).position(|w| w == b"abc").unwrap();
+    payload[name] = 0xFF;
+
+    let config = Config::new().lenient(true);
+    let (record, diagnostics) = config
+        .deserialize_with_diagnostics::<Record>(&payload)
+        .unwrap();
+    assert_eq!(
+        record,
+        Record {
+            id: 0,
+            nested: 0,
+            name: String::from("\u{FFFD}bc"),
+            after: 42,
+        }
+    );
+    assert_eq!(diagnostics.len(), 3);
+    assert!(matches!(
+        diagnostics[0].error,
+        Error::ImpreciseCastWouldLoseData
+    ));
+    assert!(matches!(
+        diagnostics[1].error,
+        Error::UnexpectedKind(Kind::Sequence, Kind::UInt)
+    ));
+    assert!(matches!(diagnostics[2].error, Error::InvalidUtf8(_)));
+    assert!(diagnostics[0].offset < diagnostics[1].offset);
+    let (from_read, read_diagnostics) = config
+        .deserialize_from_with_diagnostics::<Record, _>(&payload[..])
+        .unwrap();
+    assert_eq!(from_read, record);
+    assert_eq!(read_diagnostics.len(), 3);
+
+    // Without lenient mode, the first problem is an error.
+    assert!(matches!(
+        from_slice::<Record>(&payload),
+        Err(Error::ImpreciseCastWouldLoseData)
+    ));
+    let (_, diagnostics) = Config::new()
+        .deserialize_with_diagnostics::<u8>(&to_vec(&1_u8).unwrap())
+        .unwrap();
+    assert!(diagnostics.is_empty());
+
+    // Unknown specials are treated as None.
+    let mut payload = to_vec(&(None::<u8>, 5_u8)).unwrap();
+    assert_eq!(payload[5], 0);
+    payload[5] = u8::try_from(format::SPECIAL_COUNT).unwrap();
+    assert!(matches!(
+        from_slice::<(Option<u8>, u8)>(&payload),
+        Err(Error::UnknownSpecial(_))
+    ));
+    let (value, diagnostics) = config
+        .deserialize_with_diagnostics::<(Option<u8>, u8)>(&payload)
+        .unwrap();
+    assert_eq!(value, (None, 5));
+    assert!(matches!(
+        diagnostics[..],
+        [Diagnostic {
+            error: Error::UnknownSpecial(_),
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn interned_bytes() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Entry {
+        path: String,
+        #[serde(with = "serde_bytes")]
+        contents: Vec<u8>,
+        short: String,
+    }
+
+    let entries = (0..8)
+        .map(|index| Entry {
+            path: String::from(if index % 2 == 0 {
+                "/srv/even"
+            } else {
+                "/srv/odd"
+            }),
+            contents: vec![index % 3; 64],
+            short: String::from("abc"),
+        })
+        .collect::<Vec<_>>();
+    let config = Config::new()
+        .compatibility(Compatibility::V6)
+        .intern_bytes(Some(4));
+    let plain = to_vec(&entries).unwrap();
+    let interned = config.serialize(&entries).unwrap();
+    assert!(interned.len() < plain.len() / 2);
+
+    assert_eq!(
+        config.deserialize::<Vec<Entry>>(&interned).unwrap(),
+        entries
+    );
+    assert_eq!(from_slice::<Vec<Entry>>(&interned).unwrap(), entries);
+    assert_eq!(
+        config
+            .deserialize_from::<Vec<Entry>, _>(&interned[..])
+            .unwrap(),
+        entries
+    );
+    assert_eq!(
+        from_slice::<Value<'_>>(&interned).unwrap(),
+        from_slice::<Value<'_>>(&plain).unwrap()
+    );
+    assert_eq!(
+        from_slice::<Value<'_>>(&interned).unwrap().to_string(),
+        from_slice::<Value<'_>>(&plain).unwrap().to_string()
+    );
+    crate::validate(&interned, &Limits::default()).unwrap();
+    let mut reader = crate::events::Reader::from_read(&interned[..]).unwrap();
+    let mut paths = 0;
+    while let Some(event) = reader.next_event().unwrap() {
+        if matches!(event, crate::events::Event::Bytes(bytes) if bytes.starts_with(b"/srv/")) {
+            paths += 1;
+        }
+    }
+    assert_eq!(paths, 8);
+
+    // Resolved references count against the allocation budget.
+    assert!(matches!(
+        config
+            .clone()
+            .allocation_budget(plain.len() / 2)
+            .deserialize::<Vec<Entry>>(&interned),
+        Err(Error::TooManyBytesRead)
+    ));
+
+    // Interning requires V6 and is disabled by canonical serialization.
+    let v5 = config.clone().compatibility(Compatibility::V5);
+    assert_eq!(v5.serialize(&entries).unwrap().len(), plain.len());
+    let canonical = config.clone().canonical(true).serialize(&entries).unwrap();
+    assert_eq!(
+        canonical,
+        Config::new()
+            .compatibility(Compatibility::V6)
+            .canonical(true)
+            .serialize(&entries)
+            .unwrap()
+    );
+
+    let mut unknown = Vec::new();
+    format::write_header(&mut unknown, CURRENT_VERSION).unwrap();
+    format::write_special(&mut unknown, format::Special::BytesReference).unwrap();
+    format::write_u64(&mut unknown, 0).unwrap();
+    assert!(matches!(
+        from_slice::<String>(&unknown),
+        Err(Error::UnknownBytesReference(0))
+    ));
+}
+
+#[test]
+fn compact_bytes() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Blobs {
+        data: Vec<u8>,
+        hash: [u8; 4],
+        pair: (u8, u8),
+        empty: Vec<u8>,
+        wide: Vec<u16>,
+        mixed: (u8, i8),
+        nested: Vec<Vec<u8>>,
+    }
+
+    let blobs = Blobs {
+        data: (0..=255).collect(),
+        hash: [1, 2, 3, 4],
+        pair: (5, 6),
+        empty: Vec::new(),
+        wide: vec![1, 2, 300],
+        mixed: (7, -8),
+        nested: vec![vec![9; 16], Vec::new()],
+    };
+    let config = Config::new()
+        .compatibility(Compatibility::V6)
+        .compact_bytes(true);
+    let plain = config
+        .clone()
+        .compact_bytes(false)
+        .serialize(&blobs)
+        .unwrap();
+    let compact = config.serialize(&blobs).unwrap();
+    assert!(compact.len() + 256 < plain.len());
+    assert_eq!(config.deserialize::<Blobs>(&compact).unwrap(), blobs);
+    assert_eq!(
+        config.deserialize_from::<Blobs, _>(&compact[..]).unwrap(),
+        blobs
+    );
+
+    // Values contain bytes where sequences of u8s were compacted, and can be
+    // deserialized as sequences.
+    let value = from_slice::<Value<'_>>(&compact).unwrap();
+    let Value::Mappings(fields) = &value else {
+        unreachable!("expected mappings")
+    };
+    assert!(matches!(fields[0].1, Value::Bytes(_)));
+    assert!(matches!(fields[3].1, Value::Sequence(_)));
+    assert!(matches!(fields[4].1, Value::Sequence(_)));
+    assert_eq!(value.deserialize_as::<Blobs>().unwrap(), blobs);
+
+    // Sequences of u8s can't be read from bytes that are too long.
+    assert!(config
+        .deserialize::<[u8; 3]>(&config.serialize(&[1_u8, 2, 3, 4]).unwrap())
+        .is_err());
+
+    // Strings are encoded as bytes, so bytes are only read as sequences when
+    // compact bytes are enabled.
+    let text = to_vec(&"text").unwrap();
+    assert!(from_slice::<Blobs>(&compact).is_err());
+    assert!(from_slice::<Vec<u8>>(&text).is_err());
+    assert_eq!(config.deserialize::<Vec<u8>>(&text).unwrap(), b"text");
+    let (bytes, diagnostics) = Config::new()
+        .lenient(true)
+        .deserialize_with_diagnostics::<Vec<u8>>(&text)
+        .unwrap();
+    assert!(bytes.is_empty());
+    assert_eq!(diagnostics.len(), 1);
+
+    // Canonical output is compacted, and compaction requires V6.
+    let canonical = config.clone().canonical(true);
+    assert_eq!(
+        canonical
+            .deserialize::<Blobs>(&canonical.serialize(&blobs).unwrap())
+            .unwrap(),
+        blobs
+    );
+    assert!(
+        canonical.serialize(&vec![1_u8; 8]).unwrap().len()
+            < canonical
+                .clone()
+                .compact_bytes(false)
+                .serialize(&vec![1_u8; 8])
+                .unwrap()
+                .len()
+    );
+    let v5 = config.compatibility(Compatibility::V5);
+    assert_eq!(v5.serialize(&blobs).unwrap()[4..], plain[4..]);
+}
+
+#[test]
+fn packed_arrays() {
+    use crate::packed::{Packed, PackedArray};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Telemetry {
+        samples: Packed<f32>,
+        counters: Packed<u32>,
+        deltas: Packed<i64>,
+        empty: Packed<u16>,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct PlainTelemetry {
+        samples: Vec<f32>,
+        counters: Vec<u32>,
+        deltas: Vec<i64>,
+        empty: Vec<u16>,
+    }
+
+    let telemetry = Telemetry {
+        samples: Packed((0..1000_u16).map(|i| f32::from(i) / 7.).collect()),
+        counters: Packed((0..1000).map(|i| i * 100_003).collect()),
+        deltas: Packed(vec![i64::MIN, -1, 0, 1, i64::MAX]),
+        empty: Packed(Vec::new()),
+    };
+    let plain_telemetry = PlainTelemetry {
+        samples: telemetry.samples.0.clone(),
+        counters: telemetry.counters.0.clone(),
+        deltas: telemetry.deltas.0.clone(),
+        empty: Vec::new(),
+    };
+    let config = Config::new().compatibility(Compatibility::V6);
+    let packed = config.serialize(&telemetry).unwrap();
+    let plain = config.serialize(&plain_telemetry).unwrap();
+    assert!(packed.len() + 1000 < plain.len());
+
+    // Packed arrays and sequences can be read interchangeably.
+    assert_eq!(from_slice::<Telemetry>(&packed).unwrap(), telemetry);
+    assert_eq!(
+        config
+            .deserialize_from::<Telemetry, _>(&packed[..])
+            .unwrap(),
+        telemetry
+    );
+    assert_eq!(
+        from_slice::<PlainTelemetry>(&packed).unwrap(),
+        plain_telemetry
+    );
+    assert_eq!(from_slice::<Telemetry>(&plain).unwrap(), telemetry);
+
+    // Older compatibility levels write sequences.
+    let v5 = Config::new().compatibility(Compatibility::V5);
+    assert_eq!(
+        v5.serialize(&telemetry).unwrap()[4..],
+        v5.serialize(&plain_telemetry).unwrap()[4..]
+    );
+
+    // Values expose packed arrays as typed views, and are equal to the same
+    // numbers stored in sequences.
+    let value = from_slice::<Value<'_>>(&packed).unwrap();
+    let Value::Mappings(fields) = &value else {
+        unreachable!("expected mappings")
+    };
+    let samples = fields[0].1.as_packed::<f32>().unwrap();
+    assert_eq!(samples.len(), 1000);
+    assert_eq!(samples.get(7), Some(1.));
+    assert_eq!(
+        samples.iter().next_back(),
+        telemetry.samples.last().copied()
+    );
+    assert!(fields[0].1.as_packed::<f64>().is_none());
+    assert!(matches!(fields[3].1, Value::Sequence(_)));
+    assert_eq!(value, from_slice::<Value<'_>>(&plain).unwrap());
+    assert_eq!(value.deserialize_as::<Telemetry>().unwrap(), telemetry);
+    let reserialized = config.serialize(&value).unwrap();
+    assert!(matches!(
+        from_slice::<Value<'_>>(&reserialized)
+            .unwrap()
+            .values()
+            .next(),
+        Some(Value::Packed(_))
+    ));
+    assert_eq!(
+        Value::Packed(PackedArray::new(&[1_u8, 2])).to_string(),
+        "[1, 2]"
+    );
+
+    // Elements are converted when they are read as a different type.
+    let bytes = config.serialize(&Packed(vec![1_u8, 2, 255])).unwrap();
+    assert_eq!(
+        from_slice::<Packed<u32>>(&bytes).unwrap(),
+        Packed(vec![1, 2, 255])
+    );
+    assert_eq!(from_slice::<Vec<f64>>(&bytes).unwrap(), [1., 2., 255.]);
+    assert!(from_slice::<Packed<i8>>(&bytes).is_err());
+}
+
+#[test]
+fn packed_array_format() {
+    use crate::format::PackedType;
+    use crate::packed::Packed;
+
+    // Events and validation report the packed array as a single value.
+    let config = Config::new().compatibility(Compatibility::V6);
+    let payload = config.serialize(&Packed(vec![1_i16, -2])).unwrap();
+    assert_eq!(
+        render_events(events::Reader::from_slice(&payload).unwrap()),
+        ["packed(I16, 4)"]
+    );
+    assert_eq!(validate(&payload, &Limits::default()).unwrap().atoms, 3);
+
+    // Packed arrays must contain whole elements of a known type.
+    assert!(matches!(
+        format::write_packed_array(&mut Vec::new(), PackedType::U32, &[0; 3]),
+        Err(Error::InvalidPackedArray)
+    ));
+    let mut invalid = Vec::new();
+    format::write_header(&mut invalid, format::V6_VERSION).unwrap();
+    format::write_special(&mut invalid, format::Special::PackedArray).unwrap();
+    format::write_u64(&mut invalid, 0x3f).unwrap();
+    format::write_bytes(&mut invalid, &[0; 4]).unwrap();
+    assert!(matches!(
+        from_slice::<Vec<f32>>(&invalid),
+        Err(Error::InvalidPackedArray)
+    ));
+}
+
+#[test]
+fn inline_integers() {
+    let config = Config::new().compatibility(Compatibility::V7);
+
+    // Small integers are stored in their atom header.
+    assert_eq!(config.serialize(&0_u8).unwrap().len(), 5);
+    assert_eq!(config.serialize(&15_u64).unwrap().len(), 5);
+    assert_eq!(config.serialize(&16_u64).unwrap().len(), 6);
+    assert_eq!(config.serialize(&-8_i32).unwrap().len(), 5);
+    assert_eq!(config.serialize(&7_i128).unwrap().len(), 5);
+    assert_eq!(config.serialize(&-9_i16).unwrap().len(), 6);
+    assert_eq!(config.serialize(&'\u{3}').unwrap().len(), 5);
+    let v6 = Config::new().compatibility(Compatibility::V6);
+    assert_eq!(v6.serialize(&0_u8).unwrap().len(), 6);
+
+    for value in -10_i8..=10 {
+        let payload = config.serialize(&value).unwrap();
+        assert_eq!(config.deserialize::<i8>(&payload).unwrap(), value);
+        assert_eq!(
+            config.deserialize::<i64>(&payload).unwrap(),
+            i64::from(value)
+        );
+        // The signedness is kept, so values can be deserialized without a
+        // type hint.
+        let Value::Integer(integer) = from_slice::<Value<'_>>(&payload).unwrap() else {
+            unreachable!()
+        };
+        assert_eq!(integer.as_i8().unwrap(), value);
+    }
+    for value in 0_u8..=20 {
+        let payload = config.serialize(&value).unwrap();
+        assert_eq!(config.deserialize::<u8>(&payload).unwrap(), value);
+        assert_eq!(
+            config.deserialize::<i16>(&payload).unwrap(),
+            i16::from(value)
+        );
+    }
+    let Value::Integer(integer) =
+        from_slice::<Value<'_>>(&config.serialize(&-1_i64).unwrap()).unwrap()
+    else {
+        unreachable!()
+    };
+    assert_eq!(integer, format::Integer::from(-1_i8));
+    // Integer::read_from accepts the inline argument plus one, like it does
+    // for other arguments.
+    let mut payload = Vec::new();
+    format::write_inline_int(&mut payload, -3).unwrap();
+    let (kind, arg) = format::read_atom_header(&mut &payload[..]).unwrap();
+    assert_eq!(arg, format::INLINE_INTEGER | 0b1101);
+    assert_eq!(
+        format::Integer::read_from(kind, 0b1_1110, &mut &[][..]).unwrap(),
+        format::Integer::from(-3_i8)
+    );
+
+    // Inline integers are a single atom wherever atoms are walked.
+    let values = ((0_u32..20).collect::<Vec<_>>(), -3_i8, Some(2_u8));
+    let payload = config.serialize(&values).unwrap();
+    assert_eq!(
+        config
+            .deserialize::<(Vec<u32>, i8, Option<u8>)>(&payload)
+            .unwrap(),
+        values
+    );
+    let mut reader = reader::SliceReader::from(&payload[..]);
+    format::read_header(&mut reader).unwrap();
+    assert_eq!(
+        format::skip_value(&mut reader, &mut de::SymbolMap::new()).unwrap(),
+        4..payload.len()
+    );
+    assert_eq!(validate(&payload, &Limits::default()).unwrap().atoms, 24);
+    let mut decoder = crate::incremental::Decoder::new();
+    for byte in &payload[..payload.len() - 1] {
+        assert!(matches!(
+            decoder
+                .feed::<(Vec<u32>, i8, Option<u8>)>(std::slice::from_ref(byte))
+                .unwrap(),
+            crate::incremental::Status::NeedMore(_)
+        ));
+    }
+    assert!(matches!(
+        decoder
+            .feed::<(Vec<u32>, i8, Option<u8>)>(&payload[payload.len() - 1..])
+            .unwrap(),
+        crate::incremental::Status::Complete(_)
+    ));
+
+    // The format writer uses inline integers when they are supported.
+    let mut writer = format::Writer::new_with_compatibility(Vec::new(), Compatibility::V7).unwrap();
+    writer.begin_sequence(3).unwrap();
+    writer.write_integer(-4_i64).unwrap();
+    writer.write_i64(7).unwrap();
+    writer.write_u64(15).unwrap();
+    writer.end().unwrap();
+    let payload = writer.finish().unwrap();
+    assert_eq!(payload.len(), 8);
+    assert_eq!(from_slice::<[i64; 3]>(&payload).unwrap(), [-4, 7, 15]);
+
+    assert!(matches!(
+        format::write_inline_int(&mut Vec::new(), 8),
+        Err(Error::ImpreciseCastWouldLoseData)
+    ));
+    assert!(matches!(
+        format::write_inline_uint(&mut Vec::new(), 16),
+        Err(Error::ImpreciseCastWouldLoseData)
+    ));
+}
+
+#[test]
+fn positional_structs() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Version1 {
+        id: u64,
+        name: String,
+        variant: EnumVariants,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Version2 {
+        id: u64,
+        name: String,
+        variant: EnumVariants,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent: Option<u64>,
+        #[serde(default)]
+        children: Vec<u64>,
+    }
+
+    let config = Config::new().struct_representation(StructRepr::Positional);
+    let value = Version1 {
+        id: 1,
+        name: String::from("one"),
+        variant: EnumVariants::Struct { arg: 2 },
+    };
+    let positional = config.serialize(&value).unwrap();
+    assert!(positional.len() < to_vec(&value).unwrap().len());
+    assert_eq!(
+        render_events(events::Reader::from_slice(&positional).unwrap()),
+        [
+            "seq(Some(3))",
+            "1",
+            "\"one\"",
+            "named",
+            "#Struct",
+            "seq(Some(1))",
+            "2",
+            "end",
+            "end"
+        ]
+    );
+    assert_eq!(from_slice::<Version1>(&positional).unwrap(), value);
+    // Named structs can still be deserialized.
+    assert_eq!(
+        config
+            .deserialize::<Version1>(&to_vec(&value).unwrap())
+            .unwrap(),
+        value
+    );
+
+    // Omitted trailing fields are `None` or their default value, and extra
+    // values are ignored.
+    let upgraded = from_slice::<Version2>(&positional).unwrap();
+    assert_eq!(upgraded.parent, None);
+    assert!(upgraded.children.is_empty());
+    let newer = config
+        .serialize(&Version2 {
+            parent: Some(3),
+            children: vec![4, 5],
+            ..upgraded
+        })
+        .unwrap();
+    assert_eq!(from_slice::<Version1>(&newer).unwrap(), value);
+    assert_eq!(
+        from_slice::<(Version1, u8)>(
+            &config
+                .serialize(&(
+                    Version2 {
+                        id: 1,
+                        name: String::from("one"),
+                        variant: EnumVariants::Struct { arg: 2 },
+                        parent: Some(8),
+                        children: vec![6],
+                    },
+                    7_u8
+                ))
+                .unwrap()
+        )
+        .unwrap(),
+        (value, 7)
+    );
+}
+
+#[test]
+fn positional_struct_errors() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Optional {
+        id: u64,
+        parent: Option<u64>,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Required {
+        id: u64,
+        count: u64,
+    }
+
+    #[derive(Serialize)]
+    struct SkipsMiddle {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        first: Option<u64>,
+        second: u64,
+    }
+
+    let config = Config::new().struct_representation(StructRepr::Positional);
+    let positional = config.serialize(&(1_u64,)).unwrap();
+    assert_eq!(
+        from_slice::<Optional>(&positional).unwrap(),
+        Optional {
+            id: 1,
+            parent: None
+        }
+    );
+
+    // Fields that are neither optional nor have a default are required.
+    let err = from_slice::<Required>(&positional).unwrap_err();
+    assert!(err.to_string().contains("invalid length 1"), "{err}");
+    let err = Config::new()
+        .error_positions(true)
+        .deserialize::<Optional>(&config.serialize(&(1_u64, "parent")).unwrap())
+        .unwrap_err();
+    assert_eq!(err.path(), Some(".parent"));
+
+    // Only trailing fields may be skipped.
+    assert!(config
+        .serialize(&SkipsMiddle {
+            first: None,
+            second: 1
+        })
+        .is_err());
+    assert!(config
+        .serialize(&SkipsMiddle {
+            first: Some(1),
+            second: 1
+        })
+        .is_ok());
+}
+
+#[test]
+fn numeric_field_ids() {
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Indexed {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        parent: Option<u64>,
+        name: String,
+        variant: EnumVariants,
+    }
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Version1 {
+        #[serde(rename = "#1")]
+        id: u64,
+        #[serde(rename = "#20")]
+        name: String,
+        #[serde(rename = "#3")]
+        retired: u64,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Version2 {
+        #[serde(rename = "#20")]
+        title: String,
+        #[serde(rename = "#1")]
+        key: u64,
+        #[serde(rename = "#4", default)]
+        added: u8,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Strict {
+        #[serde(rename = "#1")]
+        id: u64,
+    }
+
+    let config = Config::new()
+        .compatibility(Compatibility::V7)
+        .struct_representation(StructRepr::Numbered);
+    let value = Indexed {
+        parent: None,
+        name: String::from("one"),
+        variant: EnumVariants::Struct { arg: 2 },
+    };
+    let numbered = config.serialize(&value).unwrap();
+    assert!(numbered.len() < to_vec(&value).unwrap().len());
+    assert_eq!(
+        render_events(events::Reader::from_slice(&numbered).unwrap()),
+        [
+            "map(Some(2))",
+            "key",
+            "1",
+            "\"one\"",
+            "key",
+            "2",
+            "named",
+            "#Struct",
+            "map(Some(1))",
+            "key",
+            "0",
+            "2",
+            "end",
+            "end"
+        ]
+    );
+    assert_eq!(from_slice::<Indexed>(&numbered).unwrap(), value);
+    // Named structs can still be deserialized.
+    assert_eq!(
+        config
+            .deserialize::<Indexed>(&to_vec(&value).unwrap())
+            .unwrap(),
+        value
+    );
+
+    // Fields with explicit ids can be renamed and reordered, and unknown ids
+    // are ignored.
+    let stored = config
+        .serialize(&Version1 {
+            id: 1,
+            name: String::from("one"),
+            retired: 3,
+        })
+        .unwrap();
+    assert_eq!(
+        from_slice::<Version2>(&stored).unwrap(),
+        Version2 {
+            title: String::from("one"),
+            key: 1,
+            added: 0
+        }
+    );
+    let err = from_slice::<Strict>(&stored).unwrap_err();
+    assert!(err.to_string().contains("unknown field `#20`"), "{err}");
+
+    // Errors report the field's name.
+    let err = Config::new()
+        .error_positions(true)
+        .deserialize::<Indexed>(
+            &config
+                .serialize(&std::collections::BTreeMap::from([(2_u8, 0_u8)]))
+                .unwrap(),
+        )
+        .unwrap_err();
+    assert_eq!(err.path(), Some(".variant"));
+
+    assert_eq!(ser::numeric_field_id("#17"), Some(17));
+    assert_eq!(ser::numeric_field_id("#"), None);
+    assert_eq!(ser::numeric_field_id("#+1"), None);
+    assert_eq!(ser::numeric_field_id("name"), None);
+}
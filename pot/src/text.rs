@@ -0,0 +1,585 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use alloc::{format, vec};
+use core::fmt::Write;
+
+use crate::de::DEFAULT_MAX_DEPTH;
+use crate::format::{InnerFloat, InnerInteger, Integer, PackedType};
+use crate::packed::{PackedArray, PackedNumber};
+use crate::{Error, Result, Value};
+
+/// Keywords that cannot be used as bare symbols.
+const KEYWORDS: [&str; 7] = [
+    "None", "true", "false", "NaNf32", "NaNf64", "inff32", "inff64",
+];
+
+/// The names of the element types of packed arrays.
+const PACKED_TYPES: [(&str, PackedType); 10] = [
+    ("i8", PackedType::I8),
+    ("i16", PackedType::I16),
+    ("i32", PackedType::I32),
+    ("i64", PackedType::I64),
+    ("u8", PackedType::U8),
+    ("u16", PackedType::U16),
+    ("u32", PackedType::U32),
+    ("u64", PackedType::U64),
+    ("f32", PackedType::F32),
+    ("f64", PackedType::F64),
+];
+
+/// Parses `text` written in Pot's text notation into a [`Value`].
+///
+/// The notation describes every kind of value Pot can encode:
+///
+/// | Notation | Value |
+/// |----------|-------|
+/// | `None` | [`Value::None`] |
+/// | `()` | [`Value::Unit`] |
+/// | `true`, `false` | [`Value::Bool`] |
+/// | `42u8`, `-1i16`, `7u128` | [`Value::Integer`] with the given width |
+/// | `1.5f32`, `-2e10f64`, `NaNf32`, `-inff64` | [`Value::Float`] with the given width |
+/// | `"text"` | [`Value::String`] |
+/// | `b"bytes\x00"` | [`Value::Bytes`] |
+/// | `[a, b]` | [`Value::Sequence`] |
+/// | `{key: value}` | [`Value::Mappings`], keeping the order of entries |
+/// | `u16[1, 2]`, `f32[1.5, NaN]` | [`Value::Packed`] with the given element type |
+/// | `Symbol` | A symbol, such as a field or variant name |
+/// | `Name(value)` | A named value, such as an enum variant with data |
+///
+/// Integers without a suffix use the smallest width that Pot would encode
+/// them with, and floats without a suffix are `f64`. The elements of packed
+/// arrays are written without suffixes. Digits may be separated with `_`.
+/// Strings support the escapes `\n`, `\r`, `\t`, `\0`, `\\`, `\"`, `\'` and `\u{XXXX}`, and byte
+/// strings additionally support `\xXX`. Trailing commas and `//` comments
+/// are allowed. Sequences, mappings and named values nested more than 128
+/// levels deep return [`Error::TooDeeplyNested`].
+///
+/// [`Value`] does not distinguish symbols from strings, nor named values from
+/// mappings. Symbols are parsed as [`Value::String`] and named values as a
+/// [`Value::Mappings`] containing a single entry whose key is the name, which
+/// is how [`Value`] represents enum variants when deserializing.
+///
+/// ```rust
+/// use pot::Value;
+///
+/// let value = pot::text::parse(r#"{name: "pot", version: [3u8, 0u8], data: b"\x00"}"#).unwrap();
+/// assert_eq!(
+///     value,
+///     Value::from_mappings([
+///         ("name", Value::from("pot")),
+///         ("version", Value::from_sequence([3_u8, 0])),
+///         ("data", Value::from(vec![0_u8])),
+///     ])
+/// );
+/// assert_eq!(pot::text::parse(&value.to_text_pretty()).unwrap(), value);
+/// ```
+pub fn parse(text: &str) -> Result<Value<'static>> {
+    let mut parser = Parser {
+        text,
+        offset: 0,
+        remaining_depth: DEFAULT_MAX_DEPTH,
+    };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    if parser.offset < text.len() {
+        return Err(parser.error("expected end of input"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    offset: usize,
+    remaining_depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, message: &str) -> Error {
+        Error::Message(format!("{message} at offset {}", self.offset))
+    }
+
+    /// Invokes `parse` one level deeper, returning
+    /// [`Error::TooDeeplyNested`] if the maximum depth would be exceeded.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        self.remaining_depth = self
+            .remaining_depth
+            .checked_sub(1)
+            .ok_or(Error::TooDeeplyNested)?;
+        let result = parse(self);
+        self.remaining_depth += 1;
+        result
+    }
+
+    fn remaining(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.remaining().chars().next()
+    }
+
+    fn next_char(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.offset += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.offset += expected.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<()> {
+        self.skip_whitespace();
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected `{expected}`")))
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        loop {
+            let remaining = self.remaining();
+            let trimmed = remaining.trim_start();
+            self.offset += remaining.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.offset += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value<'static>> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('(') => {
+                self.offset += 1;
+                self.expect(')')?;
+                Ok(Value::Unit)
+            }
+            Some('[') => self.nested(|this| {
+                this.offset += 1;
+                let mut values = Vec::new();
+                while !this.end_of_list(']', values.is_empty())? {
+                    values.push(this.parse_value()?);
+                }
+                Ok(Value::Sequence(values))
+            }),
+            Some('{') => self.nested(|this| {
+                this.offset += 1;
+                let mut mappings = Vec::new();
+                while !this.end_of_list('}', mappings.is_empty())? {
+                    let key = this.parse_value()?;
+                    this.expect(':')?;
+                    mappings.push((key, this.parse_value()?));
+                }
+                Ok(Value::Mappings(mappings))
+            }),
+            Some('"') => {
+                self.offset += 1;
+                let bytes = self.parse_quoted(false)?;
+                // Only characters and `\u` escapes were accepted.
+                let string = String::from_utf8(bytes).expect("valid utf-8");
+                Ok(Value::String(Cow::Owned(string)))
+            }
+            Some('b') if self.remaining().starts_with("b\"") => {
+                self.offset += 2;
+                Ok(Value::Bytes(Cow::Owned(self.parse_quoted(true)?)))
+            }
+            Some(ch) if ch == '-' || ch.is_ascii_digit() => self.parse_number(),
+            Some(ch) if is_symbol_start(ch) => {
+                let symbol = self.parse_symbol();
+                match symbol {
+                    "None" => Ok(Value::None),
+                    "true" => Ok(Value::Bool(true)),
+                    "false" => Ok(Value::Bool(false)),
+                    "NaNf32" | "NaNf64" | "inff32" | "inff64" => {
+                        self.offset -= symbol.len();
+                        self.parse_number()
+                    }
+                    _ => {
+                        self.skip_whitespace();
+                        if self.peek() == Some('[') {
+                            if let Some((_, element_type)) =
+                                PACKED_TYPES.iter().find(|(name, _)| *name == symbol)
+                            {
+                                return self.parse_packed(*element_type);
+                            }
+                        }
+                        let name = Value::String(Cow::Owned(symbol.to_string()));
+                        if self.eat('(') {
+                            let value = self.nested(Self::parse_value)?;
+                            self.expect(')')?;
+                            Ok(Value::Mappings(vec![(name, value)]))
+                        } else {
+                            Ok(name)
+                        }
+                    }
+                }
+            }
+            Some(_) => Err(self.error("expected a value")),
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    /// Consumes the separator before the next item of a list, returning true
+    /// if `close` ended the list instead.
+    fn end_of_list(&mut self, close: char, first: bool) -> Result<bool> {
+        self.skip_whitespace();
+        if self.eat(close) {
+            return Ok(true);
+        }
+        if first || self.eat(',') {
+            self.skip_whitespace();
+            Ok(self.eat(close))
+        } else {
+            Err(self.error(&format!("expected `,` or `{close}`")))
+        }
+    }
+
+    fn parse_symbol(&mut self) -> &'a str {
+        let remaining = self.remaining();
+        let length = remaining
+            .find(|ch: char| !is_symbol_continue(ch))
+            .unwrap_or(remaining.len());
+        self.offset += length;
+        &remaining[..length]
+    }
+
+    fn parse_quoted(&mut self, bytes: bool) -> Result<Vec<u8>> {
+        let mut contents = Vec::new();
+        loop {
+            let start = self.offset;
+            let ch = self
+                .next_char()
+                .ok_or_else(|| self.error("unterminated string"))?;
+            match ch {
+                '"' => return Ok(contents),
+                '\\' => {
+                    let escaped = match self.next_char() {
+                        Some('n') => b'\n',
+                        Some('r') => b'\r',
+                        Some('t') => b'\t',
+                        Some('0') => b'\0',
+                        Some('\\') => b'\\',
+                        Some('"') => b'"',
+                        Some('\'') => b'\'',
+                        Some('x') if bytes => {
+                            let digits = self.remaining().get(..2).unwrap_or_default();
+                            let byte = u8::from_str_radix(digits, 16)
+                                .map_err(|_| self.error("invalid `\\x` escape"))?;
+                            self.offset += 2;
+                            byte
+                        }
+                        Some('u') => {
+                            let ch = self.parse_unicode_escape()?;
+                            let mut encoded = [0; 4];
+                            contents.extend(ch.encode_utf8(&mut encoded).as_bytes());
+                            continue;
+                        }
+                        _ => {
+                            self.offset = start;
+                            return Err(self.error("invalid escape"));
+                        }
+                    };
+                    contents.push(escaped);
+                }
+                ch if bytes && !ch.is_ascii() => {
+                    self.offset = start;
+                    return Err(self.error("non-ascii character in byte string"));
+                }
+                ch => {
+                    let mut encoded = [0; 4];
+                    contents.extend(ch.encode_utf8(&mut encoded).as_bytes());
+                }
+            }
+        }
+    }
+
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let remaining = self.remaining();
+        remaining
+            .strip_prefix('{')
+            .and_then(|rest| rest.split_once('}'))
+            .and_then(|(digits, _)| {
+                let ch = u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)?;
+                self.offset += digits.len() + 2;
+                Some(ch)
+            })
+            .ok_or_else(|| self.error("invalid `\\u` escape"))
+    }
+
+    /// Parses the elements of a packed array of `element_type`, whose name
+    /// has already been parsed.
+    fn parse_packed(&mut self, element_type: PackedType) -> Result<Value<'static>> {
+        self.expect('[')?;
+        let mut bytes = Vec::new();
+        while !self.end_of_list(']', bytes.is_empty())? {
+            let start = self.offset;
+            let (digits, _) = self.take_number();
+            let number = match element_type {
+                PackedType::I8 => digits.parse().ok().map(PackedNumber::I8),
+                PackedType::I16 => digits.parse().ok().map(PackedNumber::I16),
+                PackedType::I32 => digits.parse().ok().map(PackedNumber::I32),
+                PackedType::I64 => digits.parse().ok().map(PackedNumber::I64),
+                PackedType::U8 => digits.parse().ok().map(PackedNumber::U8),
+                PackedType::U16 => digits.parse().ok().map(PackedNumber::U16),
+                PackedType::U32 => digits.parse().ok().map(PackedNumber::U32),
+                PackedType::U64 => digits.parse().ok().map(PackedNumber::U64),
+                PackedType::F32 => digits.parse().ok().map(PackedNumber::F32),
+                PackedType::F64 => digits.parse().ok().map(PackedNumber::F64),
+            };
+            let Some(number) = number else {
+                self.offset = start;
+                return Err(self.error("invalid packed array element"));
+            };
+            number.extend_le(&mut bytes);
+        }
+        Ok(Value::Packed(PackedArray::from_bytes(element_type, bytes)?))
+    }
+
+    fn parse_number(&mut self) -> Result<Value<'static>> {
+        let start = self.offset;
+        let (digits, is_float) = self.take_number();
+        let negative = digits.starts_with('-');
+        let integer =
+            |inner: Option<InnerInteger>| inner.map(|inner| Value::Integer(Integer(inner)));
+        let value = match self.parse_symbol() {
+            "f32" => digits.parse().ok().map(|value: f32| Value::from(value)),
+            "f64" => digits.parse().ok().map(|value: f64| Value::from(value)),
+            "" if is_float => digits.parse().ok().map(|value: f64| Value::from(value)),
+            _ if is_float => None,
+            "i8" => integer(digits.parse().ok().map(InnerInteger::I8)),
+            "i16" => integer(digits.parse().ok().map(InnerInteger::I16)),
+            "i32" => integer(digits.parse().ok().map(InnerInteger::I32)),
+            "i64" => integer(digits.parse().ok().map(InnerInteger::I64)),
+            "i128" => integer(digits.parse().ok().map(InnerInteger::I128)),
+            "u8" => integer(digits.parse().ok().map(InnerInteger::U8)),
+            "u16" => integer(digits.parse().ok().map(InnerInteger::U16)),
+            "u32" => integer(digits.parse().ok().map(InnerInteger::U32)),
+            "u64" => integer(digits.parse().ok().map(InnerInteger::U64)),
+            "u128" => integer(digits.parse().ok().map(InnerInteger::U128)),
+            "" if negative => digits.parse().ok().map(|value: i128| Value::from(value)),
+            "" => digits.parse().ok().map(|value: u128| Value::from(value)),
+            _ => None,
+        };
+        value.ok_or_else(|| {
+            self.offset = start;
+            self.error("invalid number")
+        })
+    }
+
+    /// Consumes a number without its suffix, returning its digits and whether
+    /// it is a float.
+    fn take_number(&mut self) -> (String, bool) {
+        let mut is_float = false;
+        let mut digits = String::new();
+        if self.eat('-') {
+            digits.push('-');
+        }
+        if self.remaining().starts_with("NaN") || self.remaining().starts_with("inf") {
+            digits.push_str(&self.remaining()[..3]);
+            self.offset += 3;
+            is_float = true;
+        } else {
+            self.take_digits(&mut digits);
+            if self.remaining().starts_with('.')
+                && self.remaining()[1..].starts_with(|ch: char| ch.is_ascii_digit())
+            {
+                self.offset += 1;
+                digits.push('.');
+                self.take_digits(&mut digits);
+                is_float = true;
+            }
+            if self.peek().is_some_and(|ch| ch == 'e' || ch == 'E') {
+                self.offset += 1;
+                digits.push('e');
+                if let Some(sign) = self.peek().filter(|ch| *ch == '-' || *ch == '+') {
+                    self.offset += 1;
+                    digits.push(sign);
+                }
+                self.take_digits(&mut digits);
+                is_float = true;
+            }
+        }
+        (digits, is_float)
+    }
+
+    fn take_digits(&mut self, digits: &mut String) {
+        let remaining = self.remaining();
+        let length = remaining
+            .find(|ch: char| !ch.is_ascii_digit() && ch != '_')
+            .unwrap_or(remaining.len());
+        digits.extend(remaining[..length].chars().filter(|ch| *ch != '_'));
+        self.offset += length;
+    }
+}
+
+fn is_symbol_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_'
+}
+
+fn is_symbol_continue(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+/// Returns true if `string` can be written as a bare symbol.
+fn is_symbol(string: &str) -> bool {
+    string.starts_with(is_symbol_start)
+        && string.chars().all(is_symbol_continue)
+        && !KEYWORDS.contains(&string)
+}
+
+/// Writes `value` to `output` in Pot's text notation, indenting nested
+/// values by four spaces per level.
+pub(crate) fn write_pretty(value: &Value<'_>, indent: usize, output: &mut String) {
+    match value {
+        Value::None => output.push_str("None"),
+        Value::Unit => output.push_str("()"),
+        Value::Bool(true) => output.push_str("true"),
+        Value::Bool(false) => output.push_str("false"),
+        Value::Integer(integer) => write_integer(*integer, output),
+        Value::Float(float) => match float.0 {
+            InnerFloat::F32(value) => write!(output, "{value:?}f32").expect("infallible"),
+            InnerFloat::F64(value) => write!(output, "{value:?}f64").expect("infallible"),
+        },
+        Value::Bytes(bytes) => write_bytes(bytes, output),
+        Value::String(string) => write_string(string, output),
+        Value::Sequence(values) => {
+            write_list(values, ('[', ']'), indent, output, |value, output| {
+                write_pretty(value, indent + 1, output);
+            });
+        }
+        Value::Packed(array) => {
+            let (name, _) = PACKED_TYPES
+                .iter()
+                .find(|(_, element_type)| *element_type == array.element_type())
+                .expect("every packed type is named");
+            output.push_str(name);
+            let numbers = array.numbers().collect::<Vec<_>>();
+            write_list(&numbers, ('[', ']'), indent, output, |number, output| {
+                write_packed_number(*number, output);
+            });
+        }
+        Value::Mappings(mappings) => {
+            write_list(
+                mappings,
+                ('{', '}'),
+                indent,
+                output,
+                |(key, value), output| {
+                    match key {
+                        Value::String(key) if is_symbol(key) => output.push_str(key),
+                        key => write_pretty(key, indent + 1, output),
+                    }
+                    output.push_str(": ");
+                    write_pretty(value, indent + 1, output);
+                },
+            );
+        }
+    }
+}
+
+fn write_list<T>(
+    items: &[T],
+    (open, close): (char, char),
+    indent: usize,
+    output: &mut String,
+    mut write_item: impl FnMut(&T, &mut String),
+) {
+    output.push(open);
+    if !items.is_empty() {
+        for item in items {
+            output.push('\n');
+            push_indent(indent + 1, output);
+            write_item(item, output);
+            output.push(',');
+        }
+        output.push('\n');
+        push_indent(indent, output);
+    }
+    output.push(close);
+}
+
+fn push_indent(indent: usize, output: &mut String) {
+    for _ in 0..indent {
+        output.push_str("    ");
+    }
+}
+
+fn write_integer(integer: Integer, output: &mut String) {
+    let result = match integer.0 {
+        InnerInteger::I8(value) => write!(output, "{value}i8"),
+        InnerInteger::I16(value) => write!(output, "{value}i16"),
+        InnerInteger::I32(value) => write!(output, "{value}i32"),
+        InnerInteger::I64(value) => write!(output, "{value}i64"),
+        InnerInteger::I128(value) => write!(output, "{value}i128"),
+        InnerInteger::U8(value) => write!(output, "{value}u8"),
+        InnerInteger::U16(value) => write!(output, "{value}u16"),
+        InnerInteger::U32(value) => write!(output, "{value}u32"),
+        InnerInteger::U64(value) => write!(output, "{value}u64"),
+        InnerInteger::U128(value) => write!(output, "{value}u128"),
+    };
+    result.expect("infallible");
+}
+
+fn write_packed_number(number: PackedNumber, output: &mut String) {
+    let result = match number {
+        PackedNumber::I8(value) => write!(output, "{value}"),
+        PackedNumber::I16(value) => write!(output, "{value}"),
+        PackedNumber::I32(value) => write!(output, "{value}"),
+        PackedNumber::I64(value) => write!(output, "{value}"),
+        PackedNumber::U8(value) => write!(output, "{value}"),
+        PackedNumber::U16(value) => write!(output, "{value}"),
+        PackedNumber::U32(value) => write!(output, "{value}"),
+        PackedNumber::U64(value) => write!(output, "{value}"),
+        PackedNumber::F32(value) => write!(output, "{value:?}"),
+        PackedNumber::F64(value) => write!(output, "{value:?}"),
+    };
+    result.expect("infallible");
+}
+
+fn write_string(string: &str, output: &mut String) {
+    output.push('"');
+    for ch in string.chars() {
+        match ch {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            '\0' => output.push_str("\\0"),
+            ch if ch.is_control() => {
+                write!(output, "\\u{{{:x}}}", u32::from(ch)).expect("infallible");
+            }
+            ch => output.push(ch),
+        }
+    }
+    output.push('"');
+}
+
+fn write_bytes(bytes: &[u8], output: &mut String) {
+    output.push_str("b\"");
+    for &byte in bytes {
+        match byte {
+            b'"' => output.push_str("\\\""),
+            b'\\' => output.push_str("\\\\"),
+            b'\n' => output.push_str("\\n"),
+            b'\r' => output.push_str("\\r"),
+            b'\t' => output.push_str("\\t"),
+            b'\0' => output.push_str("\\0"),
+            b' '..=b'~' => output.push(char::from(byte)),
+            byte => write!(output, "\\x{byte:02x}").expect("infallible"),
+        }
+    }
+    output.push('"');
+}
@@ -0,0 +1,53 @@
+use crate::de::SymbolMapRef;
+use crate::events::Reader;
+use crate::reader::{SliceReader, StrictSliceReader};
+use crate::{Error, Limits, Result};
+
+/// Verifies that `pot` contains a single, well-formed Pot value without
+/// deserializing it.
+///
+/// The header, every atom header, symbol references, the UTF-8 encoding of
+/// symbols, [`Special`](crate::format::Special) values and the balance of
+/// containers are all checked. The payload must not contain any bytes after
+/// the value. Reading stops as soon as any of the `limits` are exceeded.
+///
+/// Successful validation does not guarantee that the payload can be
+/// deserialized into a specific type.
+///
+/// ```rust
+/// let payload = pot::to_vec(&vec![(1_u8, "one"), (2, "two")]).unwrap();
+/// let summary = pot::validate(&payload, &pot::Limits::default()).unwrap();
+/// assert_eq!(summary.max_depth, 2);
+///
+/// assert!(pot::validate(&payload[..payload.len() - 1], &pot::Limits::default()).is_err());
+/// ```
+pub fn validate(pot: &[u8], limits: &Limits) -> Result<Summary> {
+    limits.check_input_length(pot.len())?;
+    let mut input = SliceReader::from(pot);
+    let mut reader =
+        Reader::new(StrictSliceReader(&mut input), SymbolMapRef::temporary())?.with_limits(*limits);
+    let mut summary = Summary::default();
+    while reader.next_event()?.is_some() {
+        summary.atoms = reader.atoms_read();
+        summary.symbols = reader.symbol_count();
+        summary.max_depth = summary.max_depth.max(reader.depth());
+    }
+
+    if reader.input().0.is_empty() {
+        Ok(summary)
+    } else {
+        Err(Error::TrailingBytes)
+    }
+}
+
+/// Statistics about a payload gathered by [`validate()`].
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct Summary {
+    /// The total number of atoms in the payload.
+    pub atoms: usize,
+    /// The maximum number of nested sequences and maps.
+    pub max_depth: usize,
+    /// The number of unique symbols defined in the payload.
+    pub symbols: usize,
+}
@@ -1,7 +1,12 @@
-use std::borrow::Cow;
-use std::fmt::{Display, Write};
-use std::marker::PhantomData;
-use std::ops::{Deref, DerefMut};
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt::{Display, Write};
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
 
 use serde::de::{EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor};
 use serde::ser::{
@@ -11,9 +16,28 @@ use serde::ser::{
 use serde::{Deserialize, Serialize};
 
 use crate::format::{Float, InnerFloat, InnerInteger, Integer};
+use crate::packed::{
+    PackedArray, PackedElement, PackedElements, PackedOrElement, PackedSeed, PackedSlice,
+    PackedValues,
+};
 
 /// A Pot-encoded value. This type can be used to deserialize to and from Pot
 /// without knowing the original data structure.
+///
+/// Values implement [`Eq`], [`Ord`] and [`Hash`] consistently, allowing them to
+/// be used as keys in maps and sets:
+///
+/// - Integers and floats are compared by their numeric values, regardless of
+///   the widths they are stored with. Integers and floats are never equal to
+///   each other.
+/// - Floats are totally ordered: `0.0` and `-0.0` are equal, all NaN values are
+///   equal, and NaN is ordered after all other floats.
+/// - Bytes and strings are compared by their bytes, as Pot does not
+///   distinguish between them when serializing.
+/// - Mappings are compared without regard to the order of their entries.
+/// - Packed arrays are compared with sequences element by element.
+/// - Values of different kinds are ordered: `None`, `Unit`, `Bool`,
+///   `Integer`, `Float`, `Bytes`/`String`, `Sequence`/`Packed`, `Mappings`.
 #[derive(Debug, Clone)]
 pub enum Value<'a> {
     /// A value representing `None`.
@@ -34,6 +58,10 @@ pub enum Value<'a> {
     Sequence(Vec<Self>),
     /// A sequence of key-value mappings.
     Mappings(Vec<(Self, Self)>),
+    /// A packed array of numbers. See [`Packed`](crate::packed::Packed).
+    ///
+    /// Packed arrays are equal to sequences containing the same numbers.
+    Packed(PackedArray<'a>),
 }
 
 impl<'a> Value<'a> {
@@ -161,6 +189,7 @@ impl<'a> Value<'a> {
             Value::String(value) => value.is_empty(),
             Value::Sequence(value) => value.is_empty(),
             Value::Mappings(value) => value.is_empty(),
+            Value::Packed(value) => value.is_empty(),
         }
     }
 
@@ -214,6 +243,7 @@ impl<'a> Value<'a> {
             Value::String(value) => !value.is_empty(),
             Value::Sequence(value) => !value.is_empty(),
             Value::Mappings(value) => !value.is_empty(),
+            Value::Packed(value) => !value.is_empty(),
         }
     }
 
@@ -251,7 +281,7 @@ impl<'a> Value<'a> {
     #[inline]
     pub fn as_str(&self) -> Option<&str> {
         match self {
-            Self::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            Self::Bytes(bytes) => core::str::from_utf8(bytes).ok(),
             Self::String(string) => Some(string),
             _ => None,
         }
@@ -270,10 +300,22 @@ impl<'a> Value<'a> {
         }
     }
 
+    /// Returns a view of the elements of a [`Self::Packed`] as `T`, or `None`
+    /// if the value is not a packed array of `T`.
+    #[must_use]
+    #[inline]
+    pub fn as_packed<T: PackedElement>(&self) -> Option<PackedSlice<'_, T>> {
+        match self {
+            Self::Packed(array) => array.as_slice(),
+            _ => None,
+        }
+    }
+
     /// Returns an iterator that iterates over all values contained inside of
     /// this value. Returns an empty iterator if not a [`Self::Sequence`] or
     /// [`Self::Mappings`]. If a [`Self::Mappings`], only the value portion of
-    /// the mapping is returned.
+    /// the mapping is returned. The elements of a [`Self::Packed`] can be
+    /// iterated with [`PackedArray::values()`].
     #[must_use]
     #[inline]
     pub fn values(&self) -> ValueIter<'_> {
@@ -289,7 +331,7 @@ impl<'a> Value<'a> {
     /// [`Self::Mappings`]. If a [`Self::Sequence`], the key will always be
     /// `Self::None`.
     #[inline]
-    pub fn mappings(&self) -> std::slice::Iter<'_, (Self, Self)> {
+    pub fn mappings(&self) -> core::slice::Iter<'_, (Self, Self)> {
         match self {
             Self::Mappings(mappings) => mappings.iter(),
             _ => [].iter(),
@@ -317,6 +359,7 @@ impl<'a> Value<'a> {
                     .map(|(k, v)| (k.into_static(), v.into_static()))
                     .collect(),
             ),
+            Self::Packed(value) => Value::Packed(value.into_static()),
         }
     }
 
@@ -339,30 +382,247 @@ impl<'a> Value<'a> {
                     .map(|(k, v)| (k.to_static(), v.to_static()))
                     .collect(),
             ),
+            Self::Packed(value) => Value::Packed(value.to_static()),
         }
     }
+
+    /// Returns this value written in Pot's text notation, with nested values
+    /// indented on separate lines.
+    ///
+    /// Every integer and float is written with a suffix describing its
+    /// width, packed arrays are prefixed with their element type, and bytes
+    /// are written as `b"..."` byte strings, allowing
+    /// [`text::parse`](crate::text::parse) to restore an identical value. See
+    /// [`text::parse`](crate::text::parse) for a description of the notation.
+    ///
+    /// ```rust
+    /// use pot::Value;
+    ///
+    /// let value = Value::from_mappings([("id", Value::from(300_u16))]);
+    /// assert_eq!(value.to_text_pretty(), "{\n    id: 300u16,\n}");
+    /// ```
+    #[must_use]
+    pub fn to_text_pretty(&self) -> String {
+        let mut output = String::new();
+        crate::text::write_pretty(self, 0, &mut output);
+        output
+    }
 }
 
-impl<'a, 'b> PartialEq<Value<'b>> for Value<'a> {
+impl<'b> PartialEq<Value<'b>> for Value<'_> {
     #[inline]
     fn eq(&self, other: &Value<'b>) -> bool {
+        self.compare(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value<'_> {}
+
+impl<'b> PartialOrd<Value<'b>> for Value<'_> {
+    #[inline]
+    fn partial_cmp(&self, other: &Value<'b>) -> Option<Ordering> {
+        Some(self.compare(other))
+    }
+}
+
+impl Ord for Value<'_> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.compare(other)
+    }
+}
+
+impl Hash for Value<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Value::None | Value::Unit => {}
+            Value::Bool(value) => value.hash(state),
+            Value::Integer(value) => value.hash(state),
+            Value::Float(value) => value.hash(state),
+            Value::Bytes(bytes) => bytes.hash(state),
+            Value::String(string) => string.as_bytes().hash(state),
+            Value::Sequence(_) | Value::Packed(_) => {
+                // Sequences and packed arrays containing the same numbers are
+                // equal, so their elements must be hashed identically.
+                let elements = self.elements();
+                elements.len().hash(state);
+                for element in elements {
+                    element.hash(state);
+                }
+            }
+            Value::Mappings(mappings) => {
+                // Mappings are equal regardless of the order of their entries,
+                // so each entry is hashed on its own and the results are
+                // combined with an order-independent sum.
+                mappings.len().hash(state);
+                mappings
+                    .iter()
+                    .fold(0_u64, |sum, entry| {
+                        let mut hasher = EntryHasher::default();
+                        entry.hash(&mut hasher);
+                        sum.wrapping_add(hasher.finish())
+                    })
+                    .hash(state);
+            }
+        }
+    }
+}
+
+/// A 64-bit FNV-1a hasher used to hash the entries of a [`Value::Mappings`]
+/// independently of the caller's hasher.
+struct EntryHasher(u64);
+
+impl Default for EntryHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for EntryHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = (self.0 ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+impl<'a> Value<'a> {
+    /// Returns the position of this value's variant in the ordering of
+    /// variants. Bytes and strings share a rank, because they are compared by
+    /// their bytes.
+    const fn rank(&self) -> u8 {
+        match self {
+            Value::None => 0,
+            Value::Unit => 1,
+            Value::Bool(_) => 2,
+            Value::Integer(_) => 3,
+            Value::Float(_) => 4,
+            Value::Bytes(_) | Value::String(_) => 5,
+            Value::Sequence(_) | Value::Packed(_) => 6,
+            Value::Mappings(_) => 7,
+        }
+    }
+
+    /// Returns the elements of a [`Self::Sequence`] or [`Self::Packed`].
+    fn elements(&self) -> Elements<'_, 'a> {
+        match self {
+            Value::Packed(array) => Elements::Packed(array.values()),
+            Value::Sequence(values) => Elements::Sequence(values.iter()),
+            _ => Elements::Sequence([].iter()),
+        }
+    }
+
+    fn compare(&self, other: &Value<'_>) -> Ordering {
         match (self, other) {
-            (Self::Bool(l0), Value::Bool(r0)) => l0 == r0,
-            (Self::Integer(l0), Value::Integer(r0)) => l0 == r0,
-            (Self::Float(l0), Value::Float(r0)) => l0 == r0,
-            (Self::Bytes(l0), Value::Bytes(r0)) => l0 == r0,
-            (Self::String(l0), Value::String(r0)) => l0 == r0,
-            (Self::Bytes(l0), Value::String(r0)) => *l0 == r0.as_bytes(),
-            (Self::String(l0), Value::Bytes(r0)) => l0.as_bytes() == &**r0,
-            (Self::Sequence(l0), Value::Sequence(r0)) => l0 == r0,
-            (Self::Mappings(l0), Value::Mappings(r0)) => l0 == r0,
-            _ => core::mem::discriminant(self) == core::mem::discriminant(other),
+            (Value::Bool(left), Value::Bool(right)) => left.cmp(right),
+            (Value::Integer(left), Value::Integer(right)) => left.cmp(right),
+            (Value::Float(left), Value::Float(right)) => left.cmp(right),
+            (Value::Bytes(_) | Value::String(_), Value::Bytes(_) | Value::String(_)) => {
+                self.as_bytes().cmp(&other.as_bytes())
+            }
+            (Value::Sequence(_) | Value::Packed(_), Value::Sequence(_) | Value::Packed(_)) => {
+                compare_all(self.elements(), other.elements(), |left, right| {
+                    left.compare(&right)
+                })
+            }
+            (Value::Mappings(left), Value::Mappings(right)) => {
+                if is_sorted(left) && is_sorted(right) {
+                    compare_all(left.iter(), right.iter(), compare_entries)
+                } else {
+                    compare_all(
+                        sorted_mappings(left).into_iter(),
+                        sorted_mappings(right).into_iter(),
+                        compare_entries,
+                    )
+                }
+            }
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+/// The elements of a [`Value::Sequence`] or [`Value::Packed`].
+enum Elements<'v, 'a> {
+    Sequence(core::slice::Iter<'v, Value<'a>>),
+    Packed(PackedValues<'v>),
+}
+
+impl<'v, 'a> Iterator for Elements<'v, 'a> {
+    type Item = Cow<'v, Value<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Elements::Sequence(values) => values.next().map(Cow::Borrowed),
+            Elements::Packed(values) => values.next().map(Cow::Owned),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Elements::Sequence(values) => values.size_hint(),
+            Elements::Packed(values) => values.size_hint(),
+        }
+    }
+}
+
+impl ExactSizeIterator for Elements<'_, '_> {}
+
+/// Compares two mapping entries by key, then by value.
+fn compare_entries(
+    (left_key, left_value): &(Value<'_>, Value<'_>),
+    (right_key, right_value): &(Value<'_>, Value<'_>),
+) -> Ordering {
+    left_key
+        .compare(right_key)
+        .then_with(|| left_value.compare(right_value))
+}
+
+/// Returns true if the entries of `mappings` are already in the order
+/// [`sorted_mappings()`] would return them in. Mappings deserialized from
+/// canonical payloads or built from ordered maps are sorted, and can be
+/// compared without allocating.
+fn is_sorted(mappings: &[(Value<'_>, Value<'_>)]) -> bool {
+    mappings
+        .windows(2)
+        .all(|pair| compare_entries(&pair[0], &pair[1]) != Ordering::Greater)
+}
+
+/// Returns the entries of `mappings` sorted by key and value, which allows
+/// mappings to be compared regardless of the order of their entries.
+fn sorted_mappings<'v, 'a>(
+    mappings: &'v [(Value<'a>, Value<'a>)],
+) -> Vec<&'v (Value<'a>, Value<'a>)> {
+    let mut sorted = mappings.iter().collect::<Vec<_>>();
+    sorted.sort_unstable();
+    sorted
+}
+
+/// Compares two sequences lexicographically.
+fn compare_all<L, R>(
+    mut left: impl Iterator<Item = L>,
+    mut right: impl Iterator<Item = R>,
+    mut compare: impl FnMut(L, R) -> Ordering,
+) -> Ordering {
+    loop {
+        match (left.next(), right.next()) {
+            (Some(left), Some(right)) => match compare(left, right) {
+                Ordering::Equal => {}
+                other => return other,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
         }
     }
 }
 
 impl<'a> Display for Value<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Value::None => f.write_str("None"),
             Value::Unit => f.write_str("()"),
@@ -381,13 +641,13 @@ impl<'a> Display for Value<'a> {
                 Ok(())
             }
             Value::String(string) => f.write_str(string),
-            Value::Sequence(sequence) => {
+            Value::Sequence(_) | Value::Packed(_) => {
                 f.write_char('[')?;
-                for (index, value) in sequence.iter().enumerate() {
+                for (index, value) in self.elements().enumerate() {
                     if index > 0 {
                         f.write_str(", ")?;
                     }
-                    Display::fmt(value, f)?;
+                    Display::fmt(&value, f)?;
                 }
                 f.write_char(']')
             }
@@ -449,6 +709,7 @@ impl<'a> Serialize for Value<'a> {
                 }
                 map.end()
             }
+            Value::Packed(array) => array.serialize(serializer),
         }
     }
 }
@@ -471,7 +732,7 @@ impl<'de: 'a, 'a> Deserialize<'de> for Value<'a> {
 /// [`OwnedValue`] implements [`Deserialize`] by first deserializing a
 /// `Value<'a>` and then using [`Value::into_static`] to convert borrowed data
 /// to owned data.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OwnedValue(pub Value<'static>);
 
 impl Deref for OwnedValue {
@@ -532,7 +793,7 @@ struct ValueVisitor<'a>(PhantomData<&'a ()>);
 impl<'de: 'a, 'a> Visitor<'de> for ValueVisitor<'a> {
     type Value = Value<'a>;
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         formatter.write_str("any value")
     }
 
@@ -722,6 +983,11 @@ impl<'de: 'a, 'a> Visitor<'de> for ValueVisitor<'a> {
         } else {
             Vec::new()
         };
+        match seq.next_element_seed(PackedSeed::new())? {
+            Some(PackedOrElement::Packed(array)) => return Ok(Value::Packed(array)),
+            Some(PackedOrElement::Element(first)) => values.push(first),
+            None => {}
+        }
         while let Some(value) = seq.next_element()? {
             values.push(value);
         }
@@ -874,8 +1140,8 @@ where
 pub struct ValueIter<'a>(SequenceIterState<'a>);
 
 enum SequenceIterState<'a> {
-    Sequence(std::slice::Iter<'a, Value<'a>>),
-    Mappings(std::slice::Iter<'a, (Value<'a>, Value<'a>)>),
+    Sequence(core::slice::Iter<'a, Value<'a>>),
+    Mappings(core::slice::Iter<'a, (Value<'a>, Value<'a>)>),
 }
 
 impl<'a> Iterator for ValueIter<'a> {
@@ -1481,6 +1747,10 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
             Value::String(str) => visitor.visit_str(str),
             Value::Sequence(seq) => visitor.visit_seq(SequenceDeserializer(seq)),
             Value::Mappings(mappings) => visitor.visit_map(MappingsDeserializer(mappings)),
+            Value::Packed(array) => visitor.visit_seq(PackedElements::borrowed(
+                array.element_type(),
+                array.as_bytes(),
+            )),
         }
     }
 
@@ -1820,13 +2090,18 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if let Value::Sequence(sequence) = &self.0 {
-            visitor.visit_seq(SequenceDeserializer(sequence))
-        } else {
-            Err(ValueError::Expected {
+        match &self.0 {
+            Value::Sequence(sequence) => visitor.visit_seq(SequenceDeserializer(sequence)),
+            Value::Packed(array) => visitor.visit_seq(PackedElements::borrowed(
+                array.element_type(),
+                array.as_bytes(),
+            )),
+            Value::Bytes(bytes) => visit_byte_sequence(bytes, visitor),
+            Value::String(string) => visit_byte_sequence(string.as_bytes(), visitor),
+            _ => Err(ValueError::Expected {
                 kind: "sequence",
                 value: self.0.to_static(),
-            })
+            }),
         }
     }
 
@@ -1834,13 +2109,18 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if let Value::Sequence(sequence) = &self.0 {
-            visitor.visit_seq(SequenceDeserializer(sequence))
-        } else {
-            Err(ValueError::Expected {
+        match &self.0 {
+            Value::Sequence(sequence) => visitor.visit_seq(SequenceDeserializer(sequence)),
+            Value::Packed(array) => visitor.visit_seq(PackedElements::borrowed(
+                array.element_type(),
+                array.as_bytes(),
+            )),
+            Value::Bytes(bytes) => visit_byte_sequence(bytes, visitor),
+            Value::String(string) => visit_byte_sequence(string.as_bytes(), visitor),
+            _ => Err(ValueError::Expected {
                 kind: "tuple",
                 value: self.0.to_static(),
-            })
+            }),
         }
     }
 
@@ -1853,13 +2133,18 @@ impl<'de> serde::Deserializer<'de> for Deserializer<'de> {
     where
         V: Visitor<'de>,
     {
-        if let Value::Sequence(sequence) = &self.0 {
-            visitor.visit_seq(SequenceDeserializer(sequence))
-        } else {
-            Err(ValueError::Expected {
+        match &self.0 {
+            Value::Sequence(sequence) => visitor.visit_seq(SequenceDeserializer(sequence)),
+            Value::Packed(array) => visitor.visit_seq(PackedElements::borrowed(
+                array.element_type(),
+                array.as_bytes(),
+            )),
+            Value::Bytes(bytes) => visit_byte_sequence(bytes, visitor),
+            Value::String(string) => visit_byte_sequence(string.as_bytes(), visitor),
+            _ => Err(ValueError::Expected {
                 kind: "tuple struct",
                 value: self.0.to_static(),
-            })
+            }),
         }
     }
 
@@ -2006,6 +2291,20 @@ impl<'de> VariantAccess<'de> for Deserializer<'de> {
     }
 }
 
+/// Visits `bytes` as a sequence of `u8`s, which is how sequences of bytes
+/// serialized with [`Config::compact_bytes`](crate::Config::compact_bytes)
+/// are deserialized. Bytes containing valid UTF-8 are deserialized as
+/// [`Value::String`], so strings are accepted as well.
+fn visit_byte_sequence<'de, V>(bytes: &[u8], visitor: V) -> Result<V::Value, ValueError>
+where
+    V: Visitor<'de>,
+{
+    serde::Deserializer::deserialize_any(
+        serde::de::value::SeqDeserializer::new(bytes.iter().copied()),
+        visitor,
+    )
+}
+
 struct SequenceDeserializer<'de>(&'de [Value<'de>]);
 
 impl<'de> SeqAccess<'de> for SequenceDeserializer<'de> {
@@ -2075,10 +2374,10 @@ pub enum ValueError {
     Custom(String),
 }
 
-impl std::error::Error for ValueError {}
+impl serde::ser::StdError for ValueError {}
 
 impl Display for ValueError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             ValueError::Expected { kind, value } => write!(f, "expected {kind} but got {value}"),
             ValueError::Custom(msg) => f.write_str(msg),
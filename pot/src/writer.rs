@@ -0,0 +1,45 @@
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use crate::Error;
+
+/// A destination that Pot can write bytes to.
+///
+/// When the `std` feature is enabled, this trait is implemented for all types
+/// that implement [`std::io::Write`]. Otherwise, it is implemented for
+/// `Vec<u8>` and can be implemented for other destinations, such as a
+/// peripheral on an embedded target.
+///
+/// Serialization takes its writer by value. To be able to use a destination
+/// after writing to it, implement this trait for a mutable reference to it.
+/// When the `std` feature is disabled, this is implemented for `&mut W` where
+/// `W: Write`.
+pub trait Write {
+    /// Writes all of `bytes` to this destination.
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error>;
+}
+
+#[cfg(feature = "std")]
+impl<W: std::io::Write + ?Sized> Write for W {
+    #[inline]
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        std::io::Write::write_all(self, bytes).map_err(Error::from)
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Write for Vec<u8> {
+    #[inline]
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(bytes);
+        Ok(())
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl<W: Write + ?Sized> Write for &mut W {
+    #[inline]
+    fn write_all(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        (**self).write_all(bytes)
+    }
+}